@@ -0,0 +1,103 @@
+//! Contains logic for approximating the liquidity of an automated market
+//! maker with resting orders
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use web3::types::{Address, U256};
+
+use crate::order::OrderSide;
+use crate::util::u256_sqrt;
+
+/// Represents a constant-product pool, whose reserves keep a fixed product
+/// as it trades
+///
+/// The pool's price is the quote reserve per contract of base reserve.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct AmmCurve {
+    pub base_reserve: U256,  /* contracts held by the pool */
+    pub quote_reserve: U256, /* quote currency held by the pool */
+    pub tick_size: U256,     /* spacing of the levels approximating it */
+    pub owner: Address,      /* trader owning the approximating orders */
+    pub expiration: DateTime<Utc>, /* of the approximating orders */
+}
+
+impl AmmCurve {
+    /// Constructor for the `AmmCurve` type
+    pub fn new(
+        base_reserve: U256,
+        quote_reserve: U256,
+        tick_size: U256,
+        owner: Address,
+        expiration: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            base_reserve,
+            quote_reserve,
+            tick_size,
+            owner,
+            expiration,
+        }
+    }
+
+    /// Returns the current price of the pool, rounded down
+    ///
+    /// Returns `None` if the pool holds no base reserve.
+    pub fn price(&self) -> Option<U256> {
+        self.quote_reserve.checked_div(self.base_reserve)
+    }
+
+    /// Returns the base reserve the pool would hold once trading had moved
+    /// its price to `price`, rounded down
+    pub fn base_reserve_at(&self, price: U256) -> Option<U256> {
+        let product: U256 =
+            self.base_reserve.checked_mul(self.quote_reserve)?;
+        Some(u256_sqrt(product.checked_div(price)?))
+    }
+
+    /// Returns up to `count` price levels approximating the pool's liquidity
+    /// on `side`, each with the quantity the pool would trade across it
+    ///
+    /// Levels lie `tick_size` apart, moving away from the pool's price. Each
+    /// quantity is the change in the base reserve as the price crosses the
+    /// level, so levels rounding to no quantity at all are left out, as are
+    /// bid prices that would reach zero.
+    pub fn levels(&self, side: OrderSide, count: usize) -> Vec<(U256, U256)> {
+        let mut levels: Vec<(U256, U256)> = vec![];
+        let mut price: U256 = match self.price() {
+            Some(t) if !self.tick_size.is_zero() => t,
+            _ => return levels,
+        };
+        let mut reserve: U256 = self.base_reserve;
+
+        for _ in 0..count {
+            price = match side {
+                OrderSide::Bid => match price.checked_sub(self.tick_size) {
+                    Some(t) if !t.is_zero() => t,
+                    _ => break,
+                },
+                OrderSide::Ask => match price.checked_add(self.tick_size) {
+                    Some(t) => t,
+                    None => break,
+                },
+            };
+
+            let next: U256 = match self.base_reserve_at(price) {
+                Some(t) => t,
+                None => break,
+            };
+
+            /* the pool buys base as its price falls and sells as it rises */
+            let quantity: U256 = match side {
+                OrderSide::Bid => next.saturating_sub(reserve),
+                OrderSide::Ask => reserve.saturating_sub(next),
+            };
+
+            if !quantity.is_zero() {
+                levels.push((price, quantity));
+            }
+
+            reserve = next;
+        }
+
+        levels
+    }
+}
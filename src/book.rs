@@ -1,21 +1,52 @@
 //! Contains logic and type definitions for the order book itself and the
 //! matching engine also
 use std::{
-    cmp::Ordering,
-    collections::{BTreeMap, VecDeque},
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     fmt::Display,
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    sync::{mpsc::Receiver, Arc},
 };
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use ethereum_types::U256;
 use itertools::Either;
+use memmap2::Mmap;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use web3::types::Address;
+use web3::types::{Address, H256};
 
-use crate::order::{ExternalOrder, Order, OrderId, OrderSide};
+use crate::amm::AmmCurve;
+use crate::clock::{Clock, ClockReading, SharedClock};
+use crate::event::{BookEvent, ReplicatedOp, Subscribers};
+use crate::fee::{
+    apply_bps, FeeSchedule, IncentiveProgram, TieredFeeSchedule,
+    BPS_DENOMINATOR,
+};
+use crate::fill::{self, Fill, FillId, FillStats, PendingFill};
+use crate::journal;
+use crate::merkle;
+use crate::order::{
+    DepthTrigger, ExternalOrder, IcebergOrder, Order, OrderId, OrderParseError,
+    OrderSide, SliceJitter, StopOrder,
+};
+use crate::quote::{Quote, QuoteId};
+use crate::replay::{self, ReplayOp};
+use crate::revalidation::{RevalidationPolicy, Revalidator};
 use crate::rpc;
-use crate::util::{from_hex_de, from_hex_se};
+use crate::rpc::RpcError;
+use crate::settlement::{Settlement, SharedSettlement};
+use crate::shutdown::ShutdownState;
+use crate::snapshot::{BookSnapshot, L3Entry, L3Snapshot, SequencedDelta};
+use crate::tick::TickGrid;
+use crate::util::{
+    duration_opt_de, duration_opt_se, from_hex_de, from_hex_se, u256_to_f64,
+};
+
+/// The number of best price levels `Book::concentration` measures over
+pub const CONCENTRATION_LEVELS: usize = 5;
 
 /// Represents an order book for a particular Tracer market
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -30,16 +61,236 @@ pub struct Book {
     )]
     pub ltp: U256, /* last traded price */
     pub depth: (usize, usize), /* depth  */
+    #[serde(default)]
+    pub volume: (U256, U256), /* resting quantity per side */
+    #[serde(default)]
+    pub open_orders: HashMap<Address, usize>, /* resting orders per trader */
     pub crossed: bool,   /* is book crossed? */
     #[serde(serialize_with = "from_hex_se", deserialize_with = "from_hex_de")]
     pub spread: U256, /* bid-ask spread */
+    #[serde(default)]
+    pub pending_fills: Vec<PendingFill>, /* fills awaiting maker confirmation */
+    #[serde(default)]
+    pub inactive: Vec<Order>, /* orders awaiting activation, oldest first */
+    #[serde(default)]
+    pub halted: bool, /* is matching suspended? */
+    #[serde(default)]
+    pub emergency_stopped: bool, /* are submissions rejected until resumed? */
+    #[serde(default)]
+    pub halt_queue: Vec<Order>, /* orders submitted while halted */
+    #[serde(default)]
+    pub quarantined: Vec<Order>, /* failed re-validation, oldest first */
+    #[serde(default)]
+    pub quotes: Vec<Quote>, /* outstanding, from the latest request */
+    #[serde(default)]
+    pub quote_round: u64, /* number of quote requests */
+    #[serde(default)]
+    pub stops: Vec<StopOrder>, /* stops awaiting their trigger, oldest first */
+    #[serde(default)]
+    pub depth_triggered: Vec<(DepthTrigger, Order)>, /* held for depth */
+    #[serde(default)]
+    pub icebergs: Vec<IcebergOrder>, /* hidden reserves behind slices */
+    #[serde(default = "default_max_cascade_iterations")]
+    pub max_cascade_iterations: usize, /* bound on stop rounds per trigger */
+    #[serde(default = "default_reopening_fills")]
+    pub reopening_fills: usize, /* fills at the reopening price on resume */
+    #[serde(default)]
+    pub reopening_price: Option<U256>, /* reference price when reopening */
+    #[serde(default)]
+    pub reopening_fills_left: usize,
+    #[serde(default)]
+    pub tape: Vec<Fill>, /* executed fills, oldest first */
+    #[serde(default)]
+    pub sequence: u64, /* number of submissions accepted */
+    #[serde(default)]
+    pub order_counts: HashMap<Address, (u64, u64)>, /* submitted, cancelled */
+    #[serde(default = "default_contract_size")]
+    pub contract_size: U256, /* base units per contract */
+    #[serde(default)]
+    pub settlements: VecDeque<Fill>, /* fills awaiting the executioner */
+    #[serde(default)]
+    pub settlement_capacity: Option<usize>, /* bound on `settlements` */
+    #[serde(default)]
+    pub awaiting_confirmation: Vec<PendingFill>, /* forwarded, unconfirmed */
+    #[serde(
+        default,
+        serialize_with = "duration_opt_se",
+        deserialize_with = "duration_opt_de"
+    )]
+    pub settlement_timeout: Option<Duration>, /* wait for confirmation */
+    #[serde(default)]
+    pub max_match_steps: Option<usize>, /* bound on maker fills per order */
+    #[serde(default)]
+    pub max_sweep_levels: Option<usize>, /* bound on levels per order */
+    #[serde(default)]
+    pub max_notional: Option<U256>, /* bound on the notional of each order */
+    #[serde(default)]
+    pub max_open_orders_per_trader: Option<usize>, /* bound on resting */
+    #[serde(default)]
+    pub level_capacity: usize, /* orders reserved by each new level */
+    #[serde(default)]
+    pub market_order_protection: Option<u32>, /* bps from reference price */
+    #[serde(default)]
+    pub market_order_fallback: MarketOrderFallback, /* with nothing to fill */
+    #[serde(default)]
+    pub min_self_spread: Option<U256>, /* least spread against own quotes */
+    #[serde(
+        default,
+        serialize_with = "duration_opt_se",
+        deserialize_with = "duration_opt_de"
+    )]
+    pub max_timestamp_skew: Option<Duration>, /* of creation from the clock */
+    #[serde(default)]
+    pub tick_grid: Option<TickGrid>, /* validation only, levels stay U256 */
+    #[serde(default)]
+    pub price_bucket: Option<U256>, /* width of approximate matching buckets */
+    #[serde(default)]
+    pub log_path: Option<PathBuf>, /* order log that mutations append to */
+    #[serde(default)]
+    pub self_trade_prevention: SelfTradePrevention,
+    #[serde(default)]
+    pub allocation: Allocation, /* among orders resting at the same price */
+    #[serde(
+        default,
+        serialize_with = "duration_opt_se",
+        deserialize_with = "duration_opt_de"
+    )]
+    pub priority_decay: Option<Duration>, /* age beyond which orders yield */
+    #[serde(default)]
+    pub lot_size: Option<U256>, /* least quantity worth matching, if any */
+    #[serde(default)]
+    pub dust_policy: DustPolicy, /* for sub-lot remainders of takers */
+    #[serde(default)]
+    pub fill_granularity: FillGranularity, /* of fills in receipts */
+    #[serde(default)]
+    pub return_ioc_remainder: bool, /* hand back unfilled IOC quantity */
+    #[serde(default)]
+    pub account_groups: Vec<Vec<Address>>, /* related traders, per group */
+    #[serde(default)]
+    pub priority_boosts: HashMap<Address, i64>, /* in milliseconds */
+    #[serde(default)]
+    pub merge_duplicate_levels: bool, /* combine a trader's orders per level */
+    #[serde(default)]
+    pub reject_if_one_sided: bool, /* refuse takers with nothing to match */
+    #[serde(default)]
+    pub rfq: bool, /* takers match only by accepting quotes */
+    #[serde(default)]
+    pub fee_schedule: FeeSchedule, /* fees charged on new fills */
+    #[serde(default)]
+    pub fee_tiers: Option<TieredFeeSchedule>, /* overrides `fee_schedule` */
+    #[serde(default)]
+    pub incentive: Option<IncentiveProgram>, /* rebates for patient makers */
+    #[serde(default = "default_created_at")]
+    pub created_at: ClockReading<DateTime<Utc>>, /* when constructed */
+    #[serde(default)]
+    pub last_update: ClockReading<Option<DateTime<Utc>>>, /* latest change */
+    #[serde(skip)]
+    pub subscribers: Subscribers<BookEvent>,
+    #[serde(skip)]
+    pub replicas: Subscribers<ReplicatedOp>, /* hot-standby books */
+    #[serde(skip)]
+    pub delta_feed: Subscribers<SequencedDelta>, /* incremental L2 clients */
+    #[serde(skip)]
+    pub delta_sequence: u64, /* sequence of the latest delta published */
+    #[serde(skip)]
+    pub published: BookSnapshot, /* L2 state as of `delta_sequence` */
+    #[serde(skip)]
+    pub clock: SharedClock, /* source of every timestamp the book records */
+    #[serde(skip)]
+    pub settlement: Option<SharedSettlement>, /* two-phase venue, if any */
+    #[serde(skip)]
+    pub revalidation: Option<RevalidationPolicy>, /* for aged makers */
+    #[serde(skip)]
+    pub match_limit: Option<BookError>, /* cut short the latest submission */
+    #[serde(skip)]
+    pub held_journal: Option<Vec<Order>>, /* until a transaction commits */
 }
 
+/// Represents an error condition arising from the order book
+///
+/// New variants may be added at any time, so callers should prefer the
+/// classification helpers over matching on individual variants.
 #[derive(
     Clone, Copy, Debug, Display, Error, Serialize, Deserialize, PartialEq, Eq,
 )]
+#[non_exhaustive]
 pub enum BookError {
     Web3Error,
+    SettlementBacklog,
+    MatchLimitReached,
+    SweepLimited,
+    PersistenceError,
+    SelfTrade,
+    InvalidSide,
+    OrderNotFound,
+    InvariantViolation,
+    InvalidOrder,
+    NotionalTooLarge,
+    SpreadTooNarrow,
+    FillPriceInvariant,
+    TimestampSkew,
+    OrderBusy,
+    WouldMatch,
+    MarketHalted,
+    OneSidedBook,
+    InsufficientLiquidity,
+    TooManyOpenOrders,
+}
+
+impl BookError {
+    /// Returns whether the error is temporary, such that retrying the same
+    /// operation later may succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            BookError::Web3Error
+            | BookError::SettlementBacklog
+            | BookError::OrderBusy
+            | BookError::MarketHalted
+            | BookError::OneSidedBook
+            | BookError::InsufficientLiquidity => true,
+            BookError::MatchLimitReached
+            | BookError::SweepLimited
+            | BookError::PersistenceError
+            | BookError::SelfTrade
+            | BookError::InvalidSide
+            | BookError::OrderNotFound
+            | BookError::InvariantViolation
+            | BookError::InvalidOrder
+            | BookError::NotionalTooLarge
+            | BookError::SpreadTooNarrow
+            | BookError::FillPriceInvariant
+            | BookError::TimestampSkew
+            | BookError::WouldMatch
+            | BookError::TooManyOpenOrders => false,
+        }
+    }
+
+    /// Returns whether the error was caused by the caller's request, such
+    /// that retrying the same request will fail again
+    pub fn is_client_error(&self) -> bool {
+        match self {
+            BookError::SelfTrade
+            | BookError::InvalidSide
+            | BookError::OrderNotFound
+            | BookError::InvalidOrder
+            | BookError::NotionalTooLarge
+            | BookError::SpreadTooNarrow
+            | BookError::TimestampSkew
+            | BookError::WouldMatch
+            | BookError::TooManyOpenOrders => true,
+            BookError::Web3Error
+            | BookError::SettlementBacklog
+            | BookError::MatchLimitReached
+            | BookError::SweepLimited
+            | BookError::PersistenceError
+            | BookError::InvariantViolation
+            | BookError::FillPriceInvariant
+            | BookError::OrderBusy
+            | BookError::MarketHalted
+            | BookError::OneSidedBook
+            | BookError::InsufficientLiquidity => false,
+        }
+    }
 }
 
 impl From<web3::Error> for BookError {
@@ -54,6 +305,18 @@ impl From<ethabi::Error> for BookError {
     }
 }
 
+impl From<io::Error> for BookError {
+    fn from(_error: io::Error) -> Self {
+        BookError::PersistenceError
+    }
+}
+
+impl From<OrderParseError> for BookError {
+    fn from(_error: OrderParseError) -> Self {
+        BookError::PersistenceError
+    }
+}
+
 #[derive(
     Clone, Copy, Debug, Display, Error, Serialize, Deserialize, PartialEq, Eq,
 )]
@@ -61,6 +324,182 @@ pub enum OrderStatus {
     Add,
     PartialMatch,
     FullMatch,
+    Inactive,
+    Queued,
+    Armed,
+}
+
+/// Represents the acknowledgement of an accepted submission
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct SubmitReceipt {
+    pub order_id: OrderId,
+    pub sequence: u64, /* position of the submission in the book's history */
+    pub accepted: DateTime<Utc>,
+    pub status: OrderStatus,
+    pub fills: Vec<Fill>, /* fills (both executed and pending last look) */
+    #[serde(default)]
+    pub limit: Option<BookError>, /* why matching stopped early, if it did */
+}
+
+impl SubmitReceipt {
+    /// Returns the slippage of the order's taker fills against the best
+    /// opposing price before it arrived, in basis points, rounded down
+    ///
+    /// Slippage is the distance from that price to the average price of the
+    /// fills, weighted by quantity. Fills at a better price than it count as
+    /// no slippage at all. Returns `None` if the order took no liquidity.
+    pub fn slippage_bps(&self) -> Option<u32> {
+        let taken: Vec<&Fill> = self
+            .fills
+            .iter()
+            .filter(|t| t.taker.id == self.order_id)
+            .collect();
+        let first: &Fill = taken.first()?;
+        let best: U256 = if first.is_taker_buy() {
+            first.reference_ask?
+        } else {
+            first.reference_bid?
+        };
+
+        let (quantity, value): (U256, U256) =
+            taken.iter().fold((U256::zero(), U256::zero()), |acc, t| {
+                (
+                    acc.0.saturating_add(t.quantity),
+                    acc.1.saturating_add(t.price.saturating_mul(t.quantity)),
+                )
+            });
+        let expected: U256 = best.saturating_mul(quantity);
+
+        /* compare total values rather than round the average price */
+        let slipped: U256 = if first.is_taker_buy() {
+            value.saturating_sub(expected)
+        } else {
+            expected.saturating_sub(value)
+        };
+        let bps: U256 = slipped
+            .saturating_mul(BPS_DENOMINATOR.into())
+            .checked_div(expected)?;
+
+        Some(if bps > u32::MAX.into() {
+            u32::MAX
+        } else {
+            bps.as_u32()
+        })
+    }
+}
+
+/// Represents why the matching engine passed over a resting order
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SkipReason {
+    SelfTrade(OrderId), /* the maker's trader is related to the taker's */
+    BelowMinimumFill(OrderId), /* the maker is smaller than the taker needs */
+    FailedRevalidation(OrderId), /* the aged maker is no longer valid */
+}
+
+/// Represents the outcome of matching an order without mutating the book
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Simulation {
+    pub fills: Vec<(OrderId, U256, U256)>, /* maker, price and quantity */
+    pub skipped: Vec<SkipReason>, /* in the order the makers were reached */
+}
+
+/// Represents the top of an order book at a point in time
+struct TopOfBook {
+    prices: (Option<U256>, Option<U256>), /* best bid and best ask */
+    orders: (Option<OrderId>, Option<OrderId>), /* front of each */
+}
+
+/// Represents how the matching engine handles orders that would trade with
+/// other orders from the same trader
+#[derive(
+    Clone, Copy, Debug, Default, Display, Serialize, Deserialize, PartialEq, Eq,
+)]
+pub enum SelfTradePrevention {
+    /// Skip over the trader's own resting orders and continue matching
+    #[default]
+    Skip,
+    /// Reject the incoming order outright, leaving the book untouched
+    Reject,
+}
+
+/// Represents how incoming orders are allocated among resting orders of the
+/// same price
+#[derive(
+    Clone, Copy, Debug, Default, Display, Serialize, Deserialize, PartialEq, Eq,
+)]
+pub enum Allocation {
+    /// Fill resting orders strictly in order of arrival
+    #[default]
+    Fifo,
+    /// Rotate which resting order fills first across successive incoming
+    /// orders, moving the first maker filled to the back of its level
+    RoundRobin,
+    /// Fill the largest resting order first, breaking ties by arrival
+    SizePriority,
+}
+
+/// Represents how the matching engine handles a taker left with less than
+/// a lot once it has matched some of its quantity
+#[derive(
+    Clone, Copy, Debug, Default, Display, Serialize, Deserialize, PartialEq, Eq,
+)]
+pub enum DustPolicy {
+    /// Cancel the remainder rather than match or rest it
+    #[default]
+    DropRemainder,
+    /// Round the remainder up to a whole lot for one last fill, which may
+    /// overfill the taker by less than a lot
+    ///
+    /// Both orders trade the rounded quantity, which is what the fill
+    /// records: the taker's quantity is grown by the overfill, so that it is
+    /// exhausted by exactly what the maker gives up.
+    RoundLastFill,
+}
+
+/// Represents how the matching engine handles a market order arriving while
+/// the opposing side is empty
+#[derive(
+    Clone, Copy, Debug, Default, Display, Serialize, Deserialize, PartialEq, Eq,
+)]
+pub enum MarketOrderFallback {
+    /// Reject the order outright
+    #[default]
+    Reject,
+    /// Rest the order as a limit order at the last traded price, rejecting
+    /// it as above if nothing has traded yet
+    RestAtReference,
+}
+
+/// Represents how fills are reported back to the submitter of an order
+///
+/// This only affects the fills returned, never those taken to settlement.
+#[derive(
+    Clone, Copy, Debug, Default, Display, Serialize, Deserialize, PartialEq, Eq,
+)]
+pub enum FillGranularity {
+    /// Report each fill separately, one per resting order or iceberg slice
+    #[default]
+    PerSlice,
+    /// Consolidate fills against the same counterparty at the same price and
+    /// in the same role, such as those against successive iceberg slices
+    PerMaker,
+}
+
+/* defaults of fields missing from state dumps that predate them */
+fn default_max_cascade_iterations() -> usize {
+    16
+}
+
+fn default_reopening_fills() -> usize {
+    1
+}
+
+fn default_contract_size() -> U256 {
+    U256::one()
+}
+
+fn default_created_at() -> ClockReading<DateTime<Utc>> {
+    ClockReading(Utc::now())
 }
 
 impl Book {
@@ -75,8 +514,316 @@ impl Book {
             asks: BTreeMap::new(),
             ltp: Default::default(),
             depth: (0, 0),
+            volume: (U256::zero(), U256::zero()),
+            open_orders: HashMap::new(),
             crossed: false,
             spread: Default::default(),
+            pending_fills: Vec::new(),
+            inactive: Vec::new(),
+            halted: false,
+            emergency_stopped: false,
+            halt_queue: Vec::new(),
+            quarantined: Vec::new(),
+            quotes: Vec::new(),
+            quote_round: 0,
+            stops: Vec::new(),
+            depth_triggered: Vec::new(),
+            icebergs: Vec::new(),
+            max_cascade_iterations: default_max_cascade_iterations(),
+            reopening_fills: default_reopening_fills(),
+            reopening_price: None,
+            reopening_fills_left: 0,
+            tape: Vec::new(),
+            sequence: 0,
+            order_counts: HashMap::new(),
+            contract_size: default_contract_size(),
+            settlements: VecDeque::new(),
+            settlement_capacity: None,
+            awaiting_confirmation: Vec::new(),
+            settlement_timeout: None,
+            max_match_steps: None,
+            max_sweep_levels: None,
+            level_capacity: 0,
+            max_notional: None,
+            max_open_orders_per_trader: None,
+            market_order_protection: None,
+            market_order_fallback: MarketOrderFallback::Reject,
+            min_self_spread: None,
+            max_timestamp_skew: None,
+            tick_grid: None,
+            price_bucket: None,
+            log_path: None,
+            self_trade_prevention: SelfTradePrevention::Skip,
+            allocation: Allocation::Fifo,
+            priority_decay: None,
+            lot_size: None,
+            dust_policy: DustPolicy::DropRemainder,
+            fill_granularity: FillGranularity::PerSlice,
+            return_ioc_remainder: false,
+            account_groups: Vec::new(),
+            priority_boosts: HashMap::new(),
+            merge_duplicate_levels: false,
+            reject_if_one_sided: false,
+            rfq: false,
+            fee_schedule: FeeSchedule::default(),
+            fee_tiers: None,
+            incentive: None,
+            created_at: default_created_at(),
+            last_update: ClockReading(None),
+            subscribers: Subscribers::default(),
+            replicas: Subscribers::default(),
+            delta_feed: Subscribers::default(),
+            delta_sequence: 0,
+            published: BookSnapshot::default(),
+            clock: SharedClock::default(),
+            settlement: None,
+            revalidation: None,
+            match_limit: None,
+            held_journal: None,
+        }
+    }
+
+    /// Constructor for the `Book` type, preallocating for a busy market
+    ///
+    /// The price levels themselves live in B-trees, which allocate a node at
+    /// a time and cannot be reserved ahead of time. Instead, every level
+    /// opened reserves room for `orders_per_level` orders in its queue. The
+    /// book otherwise behaves exactly as one from `Book::new`.
+    pub fn with_capacity(market: Address, orders_per_level: usize) -> Self {
+        Book {
+            level_capacity: orders_per_level,
+            ..Book::new(market)
+        }
+    }
+
+    /// Reconstructs an order book from the order log at `path`
+    ///
+    /// The log is memory-mapped and replayed record by record. The returned
+    /// book continues to append its mutations to the same log.
+    pub fn open_mmap(path: &Path, market: Address) -> Result<Self, BookError> {
+        let file: File = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut book: Book = Book::new(market);
+
+        /* empty files cannot be mapped */
+        if file.metadata()?.len() > 0 {
+            /* safety: the log is only ever appended to, never truncated */
+            let map: Mmap = unsafe { Mmap::map(&file)? };
+
+            for order in journal::decode(&map)? {
+                book.restore(order);
+            }
+        }
+
+        book.update();
+        book.log_path = Some(path.to_path_buf());
+
+        info!("Restored book {} from {}", market, path.display());
+
+        Ok(book)
+    }
+
+    /// Drives a fresh order book through the recorded market data at `path`
+    ///
+    /// Each recorded order is matched as though newly submitted, and each
+    /// recorded cancel is applied, in order. Operations the book refuses are
+    /// skipped. Fills are recorded on the tape and queued for settlement, but
+    /// are never forwarded.
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(book)` upon success, where `book` is the book as it stands
+    /// after the final operation.
+    ///
+    /// Returns `BookError::PersistenceError` if the recording cannot be read
+    pub async fn replay_file(path: &Path) -> Result<Self, BookError> {
+        let (market, ops) = replay::parse(&fs::read_to_string(path)?)?;
+        let mut book: Book = Book::new(market);
+
+        for op in ops {
+            let top: TopOfBook = book.capture_top();
+
+            match op {
+                ReplayOp::Submit(order) => match book.admit(*order).await {
+                    Ok(_) => {
+                        book.sequence += 1;
+                        book.trigger_stops().await;
+                    }
+                    Err(e) => warn!("Skipping recorded order: {}", e),
+                },
+                ReplayOp::Cancel(id) => {
+                    if let Err(e) = book.cancel(id) {
+                        warn!("Skipping recorded cancel of {}: {}", id, e);
+                    }
+                }
+            }
+
+            book.publish_top_change(top);
+        }
+
+        info!("Replayed {} into book {}", path.display(), market);
+
+        Ok(book)
+    }
+
+    /// Writes every resting order to a fresh order log at `path`
+    ///
+    /// Subsequent mutations of the book are appended to this log.
+    pub fn persist(&mut self, path: &Path) -> Result<(), BookError> {
+        let orders: Vec<Order> = self
+            .bids
+            .values()
+            .chain(self.asks.values())
+            .flatten()
+            .cloned()
+            .collect();
+
+        journal::write(path, &orders)?;
+        self.log_path = Some(path.to_path_buf());
+
+        info!("Persisted book {} to {}", self.market, path.display());
+
+        Ok(())
+    }
+
+    /// Captures the state of the book for a graceful shutdown
+    ///
+    /// Unsettled fills are captured alongside the resting orders and the
+    /// tape, so that `Book::resume_from` can retry their settlement.
+    pub fn graceful_shutdown(&self) -> ShutdownState {
+        info!(
+            "Shutting down book {} with {} fills awaiting settlement",
+            self.market,
+            self.settlements.len()
+        );
+
+        ShutdownState {
+            market: self.market,
+            orders: self
+                .levels(OrderSide::Bid)
+                .chain(self.levels(OrderSide::Ask))
+                .flat_map(|(_, orders)| orders.iter())
+                .cloned()
+                .collect(),
+            inactive: self.inactive.clone(),
+            pending_fills: self.pending_fills.clone(),
+            settlements: self.settlements.iter().cloned().collect(),
+            tape: self.tape.clone(),
+            ltp: self.ltp,
+        }
+    }
+
+    /// Reconstructs an order book from the state captured at shutdown
+    ///
+    /// Queued fills are retried the next time the book settles. Book
+    /// configuration is not part of the shutdown state and starts from its
+    /// defaults.
+    #[allow(unused_must_use)]
+    pub fn resume_from(state: ShutdownState) -> Self {
+        let mut book: Book = Book::new(state.market);
+
+        for order in state.orders {
+            book.add_order(order);
+        }
+
+        book.inactive = state.inactive;
+        book.pending_fills = state.pending_fills;
+        book.settlements = state.settlements.into_iter().collect();
+        book.tape = state.tape;
+        book.ltp = state.ltp;
+        book.update();
+
+        info!(
+            "Resumed book {} with {} fills awaiting settlement",
+            book.market,
+            book.settlements.len()
+        );
+
+        book
+    }
+
+    /// Rebuilds a book from an aggregated (L2) snapshot, such as one taken
+    /// from an external feed
+    ///
+    /// Each level becomes a single synthetic order for its total quantity,
+    /// so depth and prices are preserved but individual orders are not. The
+    /// synthetic orders belong to the zero address, carry no signature and
+    /// are timestamped with the current time.
+    #[allow(unused_must_use)]
+    pub fn from_snapshot(market: Address, snapshot: BookSnapshot) -> Self {
+        let mut book: Book = Book::new(market);
+        let now: DateTime<Utc> = book.now();
+
+        for side in [OrderSide::Bid, OrderSide::Ask].iter().cloned() {
+            for (price, quantity) in snapshot.side(side) {
+                if quantity.is_zero() {
+                    continue;
+                }
+
+                book.add_order(Order::new(
+                    Address::zero(),
+                    market,
+                    side,
+                    *price,
+                    *quantity,
+                    now,
+                    now,
+                    vec![],
+                ));
+            }
+        }
+
+        book.update();
+        book
+    }
+
+    /// Returns a new, empty book configured exactly as this one
+    ///
+    /// Pricing, risk, fee and matching configuration are carried over, as
+    /// are the clock and settlement venue. Orders, fills, statistics and
+    /// subscribers are not, and neither is the order log, which belongs to
+    /// this book alone.
+    pub fn clone_empty(&self) -> Book {
+        Book {
+            max_cascade_iterations: self.max_cascade_iterations,
+            reopening_fills: self.reopening_fills,
+            contract_size: self.contract_size,
+            settlement_capacity: self.settlement_capacity,
+            settlement_timeout: self.settlement_timeout,
+            max_match_steps: self.max_match_steps,
+            max_sweep_levels: self.max_sweep_levels,
+            max_notional: self.max_notional,
+            max_open_orders_per_trader: self.max_open_orders_per_trader,
+            level_capacity: self.level_capacity,
+            market_order_protection: self.market_order_protection,
+            market_order_fallback: self.market_order_fallback,
+            min_self_spread: self.min_self_spread,
+            max_timestamp_skew: self.max_timestamp_skew,
+            tick_grid: self.tick_grid,
+            price_bucket: self.price_bucket,
+            self_trade_prevention: self.self_trade_prevention,
+            allocation: self.allocation,
+            priority_decay: self.priority_decay,
+            lot_size: self.lot_size,
+            dust_policy: self.dust_policy,
+            fill_granularity: self.fill_granularity,
+            return_ioc_remainder: self.return_ioc_remainder,
+            account_groups: self.account_groups.clone(),
+            priority_boosts: self.priority_boosts.clone(),
+            merge_duplicate_levels: self.merge_duplicate_levels,
+            reject_if_one_sided: self.reject_if_one_sided,
+            rfq: self.rfq,
+            fee_schedule: self.fee_schedule,
+            fee_tiers: self.fee_tiers.clone(),
+            incentive: self.incentive,
+            created_at: ClockReading(self.clock.now()),
+            clock: self.clock.clone(),
+            settlement: self.settlement.clone(),
+            revalidation: self.revalidation.clone(),
+            ..Book::new(self.market)
         }
     }
 
@@ -131,272 +878,3729 @@ impl Book {
         None
     }
 
+    /// Returns how long the order matching the provided order ID has been
+    /// resting in the book as of `now`
+    ///
+    /// Resting time is measured from the order's creation time, and is zero
+    /// for orders created after `now`.
+    pub fn time_in_book(
+        &self,
+        order_id: OrderId,
+        now: DateTime<Utc>,
+    ) -> Option<Duration> {
+        self.order(order_id)
+            .map(|t| std::cmp::max(now - t.created, Duration::zero()))
+    }
+
     /// Returns the last traded price of the order book
     pub fn ltp(&self) -> U256 {
         self.ltp
     }
 
-    /// Returns a pair (2-tuple) containing the depths of each side of the book
-    pub fn depth(&self) -> (usize, usize) {
-        (
-            self.bids
-                .values()
-                .flatten()
-                .filter(|order| !order.remaining.is_zero())
-                .count(),
-            self.asks
-                .values()
-                .flatten()
-                .filter(|order| !order.remaining.is_zero())
-                .count(),
-        )
+    /// Returns when the book was constructed, according to the book's clock
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at.0
     }
 
-    /// Returns whether the order book is currently crossed or not
-    pub fn crossed(&self) -> bool {
-        self.crossed
+    /// Returns the time of the most recent mutation of the order book,
+    /// according to the book's clock
+    ///
+    /// Returns `None` if the book has never been mutated.
+    pub fn last_update(&self) -> Option<DateTime<Utc>> {
+        self.last_update.0
     }
 
-    /// Returns the bid-ask spread of the book
-    pub fn spread(&self) -> U256 {
-        self.spread
+    /// Returns the fills executed against this book, oldest first
+    ///
+    /// The fills of a single order appear in the order they were executed:
+    /// best price first and, within a price level, in queue order. Fills held
+    /// for last look join the tape once confirmed. Settlement is forwarded in
+    /// this same order.
+    pub fn tape(&self) -> &[Fill] {
+        &self.tape
     }
 
-    pub fn top(&self) -> (Option<U256>, Option<U256>) {
-        (
-            self.bids.last_key_value().map(|t| *t.0),
-            self.asks.first_key_value().map(|t| *t.0),
-        )
-    }
+    /// Renders the tape as CSV, with a header row followed by one row per
+    /// fill, oldest first
+    ///
+    /// Timestamps are in RFC 3339 format, traders are hex addresses and the
+    /// side is that of the taker.
+    pub fn tape_to_csv(&self) -> String {
+        let mut csv: String =
+            "timestamp,price,quantity,maker,taker,side\n".to_string();
 
-    fn price_viable(
-        opposite: U256,
-        incoming: U256,
-        incoming_side: OrderSide,
-    ) -> bool {
-        match incoming_side {
-            OrderSide::Bid => opposite <= incoming,
-            OrderSide::Ask => opposite >= incoming,
+        for fill in &self.tape {
+            csv.push_str(&format!(
+                "{},{},{},{:?},{:?},{}\n",
+                fill.timestamp.to_rfc3339(),
+                fill.price,
+                fill.quantity,
+                fill.maker.trader,
+                fill.taker.trader,
+                fill.taker.side
+            ));
         }
+
+        csv
     }
 
-    #[allow(unused_must_use)]
-    async fn r#match(
+    /// Returns the fee schedule applied to new fills
+    pub fn fee_schedule(&self) -> FeeSchedule {
+        self.fee_schedule
+    }
+
+    /// Replaces the fee schedule applied to new fills
+    ///
+    /// Fills already executed keep the fees they were charged, and resting
+    /// orders keep their priority.
+    pub fn set_fee_schedule(&mut self, schedule: FeeSchedule) {
+        let old: FeeSchedule = self.fee_schedule;
+        self.fee_schedule = schedule;
+
+        info!("Fee schedule changed from {:?} to {:?}", old, schedule);
+        self.subscribers
+            .publish(BookEvent::FeeScheduleChanged { old, new: schedule });
+    }
+
+    /// Replaces the volume-tiered fee program applied to new fills
+    ///
+    /// While a tiered program is set, each counterparty to a fill is charged
+    /// according to its own tier, falling back to the flat fee schedule for
+    /// traders who qualify for no tier. `None` reverts to the flat schedule.
+    pub fn set_tiered_fee_schedule(
         &mut self,
-        mut order: Order,
-        executioner_address: String,
-        opposing_top: Option<U256>,
-    ) -> Result<OrderStatus, BookError> {
-        info!("Matching {}...", order);
+        tiers: Option<TieredFeeSchedule>,
+    ) {
+        self.fee_tiers = tiers;
+    }
 
-        let opposing_side: &mut BTreeMap<U256, VecDeque<Order>> =
-            match order.side {
-                OrderSide::Bid => &mut self.asks,
-                OrderSide::Ask => &mut self.bids,
-            };
-        let mut running_total: U256 = order.remaining;
-        let mut done: bool = false;
+    /// Returns the notional volume the trader has traded on this book
+    ///
+    /// Only volume within the window of the tiered fee program (if any)
+    /// counts.
+    pub fn trader_volume(&self, trader: Address) -> U256 {
+        fill::trader_volume(&self.tape, trader, self.volume_since())
+    }
 
-        /* if we haven't crossed the spread, we're not going to match */
-        if opposing_top.is_none()
-            || !Book::price_viable(
-                opposing_top.unwrap(),
-                order.price,
-                order.side,
-            )
-        {
-            info!("{} does not cross, adding...", order);
-            self.add_order(order);
-            return Ok(OrderStatus::Add);
+    /// Returns the total fees the trader has been charged on this book
+    ///
+    /// Fills held for last look do not count until confirmed.
+    pub fn fees_paid(&self, trader: Address) -> U256 {
+        self.tape.iter().fold(U256::zero(), |acc, t| {
+            acc.saturating_add(t.fees_paid_by(trader))
+        })
+    }
+
+    /// Returns the number of fills executed within the `window` up to `now`
+    ///
+    /// Fills timestamped at the start of the window count, as do fills held
+    /// for last look once confirmed. Fills after `now` do not.
+    pub fn trade_velocity(&self, window: Duration, now: DateTime<Utc>) -> u64 {
+        let since: DateTime<Utc> = now - window;
+
+        self.tape
+            .iter()
+            .filter(|t| t.timestamp >= since && t.timestamp <= now)
+            .count() as u64
+    }
+
+    /// Returns the start of the window volume is counted over, if bounded
+    fn volume_since(&self) -> Option<DateTime<Utc>> {
+        match &self.fee_tiers {
+            Some(TieredFeeSchedule {
+                window: Some(window),
+                ..
+            }) => Some(self.now() - *window),
+            _ => None,
         }
+    }
 
-        let opposing_side_iterator = match order.side {
-            OrderSide::Bid => Either::Left(opposing_side.iter_mut()),
-            OrderSide::Ask => Either::Right(opposing_side.iter_mut().rev()),
+    /// Returns the trader's net filled quantity across executed fills
+    ///
+    /// Buys count positively and sells negatively. Fills held for last look
+    /// do not count until confirmed.
+    pub fn realized_position(&self, trader: Address) -> i128 {
+        /* quantities beyond the range of `i128` saturate */
+        let signed = |quantity: U256| {
+            if quantity > U256::from(i128::MAX as u128) {
+                i128::MAX
+            } else {
+                quantity.as_u128() as i128
+            }
         };
 
-        for (price, opposites) in opposing_side_iterator {
-            /* if we've run out of viable prices or we're done, halt */
-            if done || !Book::price_viable(*price, order.price, order.side) {
-                break;
+        self.tape.iter().fold(0, |acc: i128, t| {
+            if t.buyer().trader == trader {
+                acc.saturating_add(signed(t.quantity))
+            } else if t.seller().trader == trader {
+                acc.saturating_sub(signed(t.quantity))
+            } else {
+                acc
             }
+        })
+    }
 
-            for opposite in opposites {
-                /* no self-trading allowed */
-                if opposite.trader == order.trader {
-                    info!("Self-trade, skipping...");
-                    continue;
-                }
+    /// Returns the execution statistics of the provided trader over the life
+    /// of the book
+    ///
+    /// Submissions count once accepted, and cancellations once the order has
+    /// left the book. Fills held for last look do not count until confirmed.
+    pub fn fill_stats(&self, trader: Address) -> FillStats {
+        let (submitted, cancelled): (u64, u64) =
+            self.order_counts.get(&trader).copied().unwrap_or_default();
+        let (filled_qty, value): (U256, U256) = self
+            .tape
+            .iter()
+            .filter(|t| t.involves(trader))
+            .fold((U256::zero(), U256::zero()), |(qty, value), t| {
+                (
+                    qty.saturating_add(t.quantity),
+                    value.saturating_add(t.price.saturating_mul(t.quantity)),
+                )
+            });
 
-                /* determine how much to match */
-                let amount: U256 =
-                    match opposite.remaining.cmp(&order.remaining) {
-                        Ordering::Greater => order.remaining,
-                        _ => opposite.remaining,
-                    };
-                info!("Matching with amount of {}...", amount);
+        FillStats {
+            submitted,
+            filled_qty,
+            cancelled,
+            avg_fill_price: value.checked_div(filled_qty),
+        }
+    }
 
-                /* match */
-                order = Book::fill(order, amount);
-                *opposite = Book::fill(opposite.clone(), amount);
+    /// Returns each trader's unrealized profit on their resting orders were
+    /// they to fill and be marked at `mark_price`
+    ///
+    /// Resting bids gain where they lie below the mark and resting asks where
+    /// they lie above it. Values are notional, and saturate at the bounds of
+    /// `i128`.
+    pub fn mark_to_market(&self, mark_price: U256) -> HashMap<Address, i128> {
+        let mut marks: HashMap<Address, i128> = HashMap::new();
 
-                self.ltp = *price;
-                info!("LTP updated, is now {}", self.ltp);
+        let signed = |value: U256| {
+            if value > U256::from(i128::MAX as u128) {
+                i128::MAX
+            } else {
+                value.as_u128() as i128
+            }
+        };
 
-                info!("Forwarding {} and {}...", order, opposite);
-                rpc::send_matched_orders(
-                    order.clone(),
-                    opposite.clone(),
-                    executioner_address.clone(),
+        for order in self.bids.values().chain(self.asks.values()).flatten() {
+            let (above, below): (U256, U256) = match order.side {
+                OrderSide::Bid => (mark_price, order.price),
+                OrderSide::Ask => (order.price, mark_price),
+            };
+
+            let pnl: i128 = if above >= below {
+                signed(self.notional(above - below, order.remaining))
+            } else {
+                signed(self.notional(below - above, order.remaining))
+                    .saturating_neg()
+            };
+
+            let entry: &mut i128 = marks.entry(order.trader).or_insert(0);
+            *entry = entry.saturating_add(pnl);
+        }
+
+        marks
+    }
+
+    /// Returns the fee schedule the trader is currently charged under
+    pub fn trader_fee_schedule(&self, trader: Address) -> FeeSchedule {
+        Book::tier_schedule(
+            &self.fee_tiers,
+            &self.tape,
+            self.volume_since(),
+            trader,
+        )
+        .unwrap_or(self.fee_schedule)
+    }
+
+    /// Returns the schedule of the tier the trader's volume qualifies for
+    ///
+    /// Takes the relevant fields individually so that it can be used while
+    /// the book's levels are borrowed for matching.
+    fn tier_schedule(
+        tiers: &Option<TieredFeeSchedule>,
+        tape: &[Fill],
+        since: Option<DateTime<Utc>>,
+        trader: Address,
+    ) -> Option<FeeSchedule> {
+        tiers
+            .as_ref()?
+            .schedule_for(fill::trader_volume(tape, trader, since))
+    }
+
+    /// Subscribes to the events emitted by this book
+    ///
+    /// Subscriptions are not carried over when the book is cloned.
+    pub fn subscribe(&mut self) -> Receiver<BookEvent> {
+        self.subscribers.subscribe()
+    }
+
+    /// Streams every subsequent mutation of the book, for replaying onto a
+    /// hot-standby with `Book::apply_replicated`
+    ///
+    /// The standby must start from the same resting orders as this book.
+    /// Only resting orders are replicated: the tape and settlement queue are
+    /// left to the primary.
+    pub fn replication_stream(&mut self) -> Receiver<ReplicatedOp> {
+        self.replicas.subscribe()
+    }
+
+    /// Streams incremental changes to the aggregated (L2) view of the book
+    ///
+    /// Each delta is the change since the one before it, the first being the
+    /// change since the call to this method. A client keeping an L2 view
+    /// should start from `Book::snapshot` taken immediately after
+    /// subscribing and apply every delta in sequence. Should the sequence
+    /// skip a number, the client must start again from a fresh snapshot,
+    /// which reflects every delta up to `delta_sequence`.
+    pub fn delta_feed(&mut self) -> Receiver<SequencedDelta> {
+        self.published = self.snapshot();
+        self.delta_feed.subscribe()
+    }
+
+    /// Applies a mutation streamed from a primary book
+    #[allow(unused_must_use)]
+    pub fn apply_replicated(&mut self, op: ReplicatedOp) {
+        match op {
+            ReplicatedOp::Restate(order) => self.restore(*order),
+            ReplicatedOp::Remove(id) => {
+                self.remove_order(id);
+            }
+        }
+
+        self.update();
+    }
+
+    /// Bounds the number of price levels a single order can match across
+    ///
+    /// Whatever remains of an order once the limit is reached is cancelled,
+    /// and its receipt carries `BookError::SweepLimited` alongside the fills
+    /// it made. `None` lifts the limit.
+    pub fn set_max_sweep_levels(&mut self, levels: Option<usize>) {
+        self.max_sweep_levels = levels;
+    }
+
+    /// Enables approximate matching, comparing prices only by the bucket of
+    /// `width` they fall in
+    ///
+    /// This is not exact: an order may fill at a resting order's price up to
+    /// a bucket's width beyond its own limit, in exchange for cheaper price
+    /// comparisons across a very large number of levels. Fills are still
+    /// made at the resting order's price. `None` restores exact matching,
+    /// which is the default.
+    pub fn set_price_bucket(&mut self, width: Option<U256>) {
+        self.price_bucket = width;
+    }
+
+    /// Bounds how far market orders without a limit can fill from the
+    /// reference price, in basis points
+    ///
+    /// `None` lets such orders sweep the entire opposing side.
+    pub fn set_market_order_protection(&mut self, bps: Option<u32>) {
+        self.market_order_protection = bps;
+    }
+
+    /// Registers a group of related traders that must not trade with each
+    /// other
+    ///
+    /// Self-trade prevention treats every trader in the group as the same
+    /// trader.
+    pub fn set_account_group(&mut self, traders: Vec<Address>) {
+        self.account_groups.push(traders);
+    }
+
+    /// Grants the trader a boost to the time priority of their orders
+    ///
+    /// Orders the trader rests from now on rank at their price as though
+    /// created `boost` earlier. A zero or negative boost removes it again.
+    pub fn set_priority_boost(&mut self, trader: Address, boost: Duration) {
+        if boost > Duration::zero() {
+            self.priority_boosts
+                .insert(trader, boost.num_milliseconds());
+        } else {
+            self.priority_boosts.remove(&trader);
+        }
+    }
+
+    /// Returns every trader related to `trader`, including itself
+    fn related_traders(&self, trader: Address) -> Vec<Address> {
+        let mut related: Vec<Address> = vec![trader];
+
+        for group in self.account_groups.iter().filter(|t| t.contains(&trader))
+        {
+            related.extend(group.iter().filter(|t| **t != trader));
+        }
+
+        related
+    }
+
+    /// Re-validates resting orders at least `max_age` old whenever they are
+    /// about to match, quarantining any that fail
+    ///
+    /// Quarantined orders are withdrawn from the book, unfilled, and held in
+    /// `quarantined` for their traders to resolve.
+    pub fn set_revalidation(
+        &mut self,
+        max_age: Duration,
+        revalidator: Arc<dyn Revalidator>,
+    ) {
+        self.revalidation = Some(RevalidationPolicy::new(max_age, revalidator));
+    }
+
+    /// Replaces the clock the book reads the current time from
+    ///
+    /// A book that has never been mutated is treated as constructed at the
+    /// current time of the new clock.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = SharedClock(clock);
+
+        if self.last_update.0.is_none() {
+            self.created_at = ClockReading(self.clock.now());
+        }
+    }
+
+    /// Settles fills in two phases through the provided venue, rather than
+    /// forwarding them to the executioner
+    pub fn set_settlement(&mut self, settlement: Arc<dyn Settlement>) {
+        self.settlement = Some(SharedSettlement(settlement));
+    }
+
+    /// Returns the current time according to the book's clock
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Returns every executed fill the order matching the provided order ID
+    /// took part in (as either maker or taker), oldest first
+    pub fn fills_for_order(&self, order_id: OrderId) -> Vec<Fill> {
+        self.tape
+            .iter()
+            .filter(|t| t.maker.id == order_id || t.taker.id == order_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the number of base units each contract represents
+    pub fn contract_size(&self) -> U256 {
+        self.contract_size
+    }
+
+    /// Returns the notional value of the provided quantity of contracts
+    ///
+    /// Quantities are denominated in contracts, so the notional value is
+    /// scaled by the contract size of the book.
+    pub fn notional(&self, price: U256, quantity: U256) -> U256 {
+        price
+            .saturating_mul(quantity)
+            .saturating_mul(self.contract_size)
+    }
+
+    /// Returns the total quantity resting on the bid-side of the book
+    pub fn total_bid_volume(&self) -> U256 {
+        self.volume.0
+    }
+
+    /// Returns the total quantity resting on the ask-side of the book
+    pub fn total_ask_volume(&self) -> U256 {
+        self.volume.1
+    }
+
+    /// Sums the quantity resting on each side of the book from its levels
+    fn resting_volume(&self) -> (U256, U256) {
+        let total = |side: &BTreeMap<U256, VecDeque<Order>>| {
+            side.values()
+                .flatten()
+                .fold(U256::zero(), |acc, t| acc + t.remaining)
+        };
+
+        (total(&self.bids), total(&self.asks))
+    }
+
+    /// Adds to the running total of quantity resting on `side`
+    fn grow_volume(&mut self, side: OrderSide, amount: U256) {
+        match side {
+            OrderSide::Bid => self.volume.0 += amount,
+            OrderSide::Ask => self.volume.1 += amount,
+        }
+    }
+
+    /// Takes from the running total of quantity resting on `side`
+    fn shrink_volume(&mut self, side: OrderSide, amount: U256) {
+        match side {
+            OrderSide::Bid => self.volume.0 -= amount,
+            OrderSide::Ask => self.volume.1 -= amount,
+        }
+    }
+
+    /// Counts another order resting in the book for `trader`
+    fn open_order(&mut self, trader: Address) {
+        *self.open_orders.entry(trader).or_default() += 1;
+    }
+
+    /// Counts an order of `trader` as having left the book
+    fn close_order(&mut self, trader: Address) {
+        if let Some(count) = self.open_orders.get_mut(&trader) {
+            *count -= 1;
+            if *count == 0 {
+                self.open_orders.remove(&trader);
+            }
+        }
+    }
+
+    /// Returns the number of orders each trader has resting in the book
+    fn open_orders_by_trader(&self) -> HashMap<Address, usize> {
+        let mut counts: HashMap<Address, usize> = HashMap::new();
+
+        for order in self.bids.values().chain(self.asks.values()).flatten() {
+            *counts.entry(order.trader).or_default() += 1;
+        }
+
+        counts
+    }
+
+    /// Returns a pair (2-tuple) containing the depths of each side of the book
+    pub fn depth(&self) -> (usize, usize) {
+        (
+            self.bids
+                .values()
+                .flatten()
+                .filter(|order| !order.remaining.is_zero())
+                .count(),
+            self.asks
+                .values()
+                .flatten()
+                .filter(|order| !order.remaining.is_zero())
+                .count(),
+        )
+    }
+
+    /// Returns every trader with at least one resting order, in ascending
+    /// order of address
+    pub fn traders(&self) -> Vec<Address> {
+        let mut traders: Vec<Address> = self
+            .open_orders
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(trader, _)| *trader)
+            .collect();
+        traders.sort();
+        traders
+    }
+
+    /// Returns the total number of resting orders across both sides of the
+    /// book
+    pub fn order_count(&self) -> usize {
+        let (bids, asks): (usize, usize) = self.depth();
+        bids + asks
+    }
+
+    /// Returns whether neither side of the book has any resting orders
+    pub fn is_empty(&self) -> bool {
+        self.order_count() == 0
+    }
+
+    /// Returns whether the order book is currently crossed or not
+    pub fn crossed(&self) -> bool {
+        self.crossed
+    }
+
+    /// Returns the bid-ask spread of the book
+    pub fn spread(&self) -> U256 {
+        self.spread
+    }
+
+    /// Returns the bid-ask spread in basis points of the mid price, rounded
+    /// down
+    ///
+    /// A crossed book has a spread of zero. Returns `None` if either side is
+    /// empty, or if both best prices are zero.
+    pub fn spread_bps(&self) -> Option<u32> {
+        let (bid, ask): (U256, U256) = match self.top() {
+            (Some(bid), Some(ask)) => (bid, ask),
+            _ => return None,
+        };
+
+        /* dividing by the mid is multiplying by two over the sum */
+        let bps: U256 = ask
+            .saturating_sub(bid)
+            .saturating_mul((2 * BPS_DENOMINATOR).into())
+            .checked_div(bid.saturating_add(ask))?;
+
+        Some(if bps > u32::MAX.into() {
+            u32::MAX
+        } else {
+            bps.as_u32()
+        })
+    }
+
+    /// Returns the best bid and ask as tick offsets from the reference price
+    /// of the book's tick grid
+    ///
+    /// Both are `None` if the book has no tick grid.
+    pub fn top_ticks(&self) -> (Option<i64>, Option<i64>) {
+        let (bid, ask) = self.top();
+
+        match self.tick_grid {
+            Some(grid) => (
+                bid.and_then(|t| grid.to_ticks(t)),
+                ask.and_then(|t| grid.to_ticks(t)),
+            ),
+            None => (None, None),
+        }
+    }
+
+    pub fn top(&self) -> (Option<U256>, Option<U256>) {
+        (
+            self.bids.last_key_value().map(|t| *t.0),
+            self.asks.first_key_value().map(|t| *t.0),
+        )
+    }
+
+    /// Returns the microprice of the book, the mid weighted by the quantity
+    /// resting at the top of each side
+    ///
+    /// Each best price is weighted by the quantity at the opposite best price,
+    /// so the microprice leans towards the thinner side. The result is rounded
+    /// down, and is `None` while either side is empty.
+    pub fn microprice(&self) -> Option<U256> {
+        let (bid, ask): (U256, U256) = match self.top() {
+            (Some(bid), Some(ask)) => (bid, ask),
+            _ => return None,
+        };
+
+        let bid_qty: U256 = self.quantity_at(OrderSide::Bid, bid);
+        let ask_qty: U256 = self.quantity_at(OrderSide::Ask, ask);
+        let total: U256 = bid_qty.saturating_add(ask_qty);
+
+        if total.is_zero() {
+            return None;
+        }
+
+        Some(
+            bid.saturating_mul(ask_qty)
+                .saturating_add(ask.saturating_mul(bid_qty))
+                / total,
+        )
+    }
+
+    /// Captures the best prices and best orders of the book, for comparing
+    /// against once a mutation has completed
+    fn capture_top(&self) -> TopOfBook {
+        TopOfBook {
+            prices: self.top(),
+            orders: (
+                self.next_to_fill(OrderSide::Bid).map(|t| t.id),
+                self.next_to_fill(OrderSide::Ask).map(|t| t.id),
+            ),
+        }
+    }
+
+    /// Emits events describing how the top of the book has changed since it
+    /// was `top`
+    fn publish_top_change(&mut self, top: TopOfBook) {
+        let after: TopOfBook = self.capture_top();
+
+        let (old_best, new_best) = (top.orders, after.orders);
+        let (before, after) = (top.prices, after.prices);
+
+        let crossed = |top: (Option<U256>, Option<U256>)| match top {
+            (Some(bid), Some(ask)) => bid >= ask,
+            _ => false,
+        };
+        let spread = |top: (Option<U256>, Option<U256>)| match top {
+            (Some(bid), Some(ask)) => Some(ask.saturating_sub(bid)),
+            _ => None,
+        };
+
+        match (crossed(before), crossed(after)) {
+            (false, true) => self.subscribers.publish(BookEvent::Crossed),
+            (true, false) => self.subscribers.publish(BookEvent::Uncrossed),
+            _ => {}
+        }
+
+        if spread(before) != spread(after) {
+            self.subscribers.publish(BookEvent::SpreadChanged {
+                old: spread(before),
+                new: spread(after),
+            });
+        }
+
+        for (side, old, new) in [
+            (OrderSide::Bid, old_best.0, new_best.0),
+            (OrderSide::Ask, old_best.1, new_best.1),
+        ]
+        .iter()
+        {
+            if let Some(order_id) = new.filter(|t| Some(*t) != *old) {
+                self.subscribers.publish(BookEvent::BecameBest {
+                    order_id,
+                    side: *side,
+                });
+            }
+        }
+    }
+
+    /// Returns the total remaining quantity resting at exactly `price` on
+    /// `side`, which is zero if there is no such level
+    pub fn quantity_at(&self, side: OrderSide, price: U256) -> U256 {
+        let levels: &BTreeMap<U256, VecDeque<Order>> = match side {
+            OrderSide::Bid => &self.bids,
+            OrderSide::Ask => &self.asks,
+        };
+
+        match levels.get(&price) {
+            Some(orders) => orders
+                .iter()
+                .fold(U256::zero(), |acc, t| acc.saturating_add(t.remaining)),
+            None => U256::zero(),
+        }
+    }
+
+    /// Returns the total remaining quantity resting on `side` within `bps`
+    /// basis points of that side's best price
+    ///
+    /// The band is inclusive and its width is rounded down. An empty side
+    /// has no depth.
+    pub fn depth_within_bps(&self, side: OrderSide, bps: u32) -> U256 {
+        let best: U256 = match side {
+            OrderSide::Bid => self.top().0,
+            OrderSide::Ask => self.top().1,
+        }
+        .unwrap_or_default();
+        let band: U256 = apply_bps(best, bps);
+
+        self.levels(side)
+            .take_while(|(price, _)| match side {
+                OrderSide::Bid => **price >= best.saturating_sub(band),
+                OrderSide::Ask => **price <= best.saturating_add(band),
+            })
+            .fold(U256::zero(), |acc, (price, _)| {
+                acc.saturating_add(self.quantity_at(side, *price))
+            })
+    }
+
+    /// Returns up to the `n` best price levels of `side`, each with its total
+    /// remaining quantity and number of resting orders
+    pub fn best_n_levels(
+        &self,
+        side: OrderSide,
+        n: usize,
+    ) -> Vec<(U256, U256, usize)> {
+        self.levels(side)
+            .map(|(price, orders)| {
+                let resting = orders.iter().filter(|t| !t.remaining.is_zero());
+                let count: usize = resting.clone().count();
+                let quantity: U256 = resting.fold(U256::zero(), |acc, t| {
+                    acc.saturating_add(t.remaining)
+                });
+
+                (*price, quantity, count)
+            })
+            .filter(|(_, _, count)| *count > 0)
+            .take(n)
+            .collect()
+    }
+
+    /// Returns the resting order on `side` that an incoming opposing order
+    /// would match against first
+    ///
+    /// This is the order at the front of the queue at the best price.
+    pub fn next_to_fill(&self, side: OrderSide) -> Option<&Order> {
+        self.levels(side)
+            .flat_map(|(_, orders)| orders.iter())
+            .find(|t| !t.remaining.is_zero())
+    }
+
+    /// Returns the best bid, ignoring every order from `trader`
+    pub fn best_bid_excluding(&self, trader: Address) -> Option<U256> {
+        self.best_excluding(OrderSide::Bid, trader)
+    }
+
+    /// Returns the best ask, ignoring every order from `trader`
+    pub fn best_ask_excluding(&self, trader: Address) -> Option<U256> {
+        self.best_excluding(OrderSide::Ask, trader)
+    }
+
+    fn best_excluding(&self, side: OrderSide, trader: Address) -> Option<U256> {
+        self.levels(side)
+            .find(|(_, orders)| {
+                orders
+                    .iter()
+                    .any(|t| t.trader != trader && !t.remaining.is_zero())
+            })
+            .map(|(price, _)| *price)
+    }
+
+    /// Returns the price at which at least `target_volume` would cross were
+    /// the book uncrossed in a single-price auction
+    ///
+    /// The auction clears at the price executing the most volume, breaking
+    /// ties by the smallest imbalance between the two sides and then by the
+    /// lowest price. Returns `None` if no volume crosses, or if less than
+    /// `target_volume` crosses at the clearing price.
+    pub fn clearing_price_for_volume(
+        &self,
+        target_volume: U256,
+    ) -> Option<U256> {
+        let volume = |orders: &VecDeque<Order>| {
+            orders.iter().fold(U256::zero(), |acc, t| acc + t.remaining)
+        };
+
+        let candidates: Vec<U256> = self
+            .bids
+            .keys()
+            .chain(self.asks.keys())
+            .copied()
+            .collect::<BTreeSet<U256>>()
+            .into_iter()
+            .collect();
+
+        /* the best price so far, with its volume and imbalance */
+        let mut best: Option<(U256, U256, U256)> = None;
+
+        for price in candidates {
+            let demand: U256 = self
+                .bids
+                .range(price..)
+                .fold(U256::zero(), |acc, (_, t)| acc + volume(t));
+            let supply: U256 = self
+                .asks
+                .range(..=price)
+                .fold(U256::zero(), |acc, (_, t)| acc + volume(t));
+
+            let executed: U256 = demand.min(supply);
+            let imbalance: U256 = demand.max(supply) - executed;
+
+            /* candidates ascend, so ties keep the lower price */
+            let better: bool = match best {
+                Some((_, volume, surplus)) => {
+                    executed > volume
+                        || (executed == volume && imbalance < surplus)
+                }
+                None => true,
+            };
+
+            if better {
+                best = Some((price, executed, imbalance));
+            }
+        }
+
+        match best {
+            Some((price, executed, _))
+                if !executed.is_zero() && executed >= target_volume =>
+            {
+                Some(price)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the worst price paid when sweeping `quantity` from `side`
+    ///
+    /// This is the price of the level supplying the last unit, rather than
+    /// the average price of the sweep. Returns `None` if `side` cannot supply
+    /// the whole quantity, or if the quantity is zero.
+    pub fn marginal_price(
+        &self,
+        side: OrderSide,
+        quantity: U256,
+    ) -> Option<U256> {
+        if quantity.is_zero() {
+            return None;
+        }
+
+        let mut remaining: U256 = quantity;
+
+        for (price, orders) in self.levels(side) {
+            let available: U256 = orders
+                .iter()
+                .fold(U256::zero(), |acc, t| acc.saturating_add(t.remaining));
+
+            if available >= remaining {
+                return Some(*price);
+            }
+
+            remaining -= available;
+        }
+
+        None
+    }
+
+    /// Matches the provided order against the book without mutating it
+    ///
+    /// The simulation follows the matching engine's allocation and match and
+    /// sweep limits, but ignores last look, lot sizes and stop triggers. Any
+    /// self-trade reported would cause the order to be rejected outright
+    /// under `SelfTradePrevention::Reject`.
+    ///
+    /// # Returns #
+    ///
+    /// Returns a `Simulation` listing the fills the order would make and
+    /// every resting order skipped along the way, with the reason why.
+    pub fn simulate(&self, order: &Order) -> Simulation {
+        let related: Vec<Address> = self.related_traders(order.trader);
+        let now: DateTime<Utc> = self.now();
+        let mut remaining: U256 = order.remaining;
+        let mut steps: usize = 0;
+        let mut simulation: Simulation = Simulation {
+            fills: vec![],
+            skipped: vec![],
+        };
+
+        for (levels, (price, opposites)) in
+            self.levels(order.side.opposite()).enumerate()
+        {
+            if remaining.is_zero()
+                || !Book::price_viable(
+                    *price,
+                    order.price,
+                    order.side,
+                    self.price_bucket,
+                )
+                || matches!(self.max_sweep_levels, Some(t) if levels >= t)
+            {
+                break;
+            }
+
+            let mut queue: Vec<&Order> = opposites.iter().collect();
+            if self.allocation == Allocation::SizePriority {
+                queue.sort_by_key(|t| Reverse(t.remaining));
+            }
+            if let Some(decay) = self.priority_decay {
+                queue.sort_by_key(|t| now - t.created >= decay);
+            }
+
+            for opposite in queue {
+                if remaining.is_zero() {
+                    break;
+                }
+
+                if related.contains(&opposite.trader) {
+                    simulation.skipped.push(SkipReason::SelfTrade(opposite.id));
+                    continue;
+                }
+
+                if let Some(min) = order.min_fill_qty {
+                    if opposite.remaining < min.min(remaining) {
+                        simulation
+                            .skipped
+                            .push(SkipReason::BelowMinimumFill(opposite.id));
+                        continue;
+                    }
+                }
+
+                let valid: bool = match &self.revalidation {
+                    Some(policy) => policy.permits(opposite, now),
+                    None => true,
+                };
+                if !valid {
+                    simulation
+                        .skipped
+                        .push(SkipReason::FailedRevalidation(opposite.id));
+                    continue;
+                }
+
+                if matches!(self.max_match_steps, Some(t) if steps >= t) {
+                    return simulation;
+                }
+                steps += 1;
+
+                let amount: U256 = opposite.remaining.min(remaining);
+                simulation.fills.push((opposite.id, *price, amount));
+                remaining -= amount;
+            }
+        }
+
+        simulation
+    }
+
+    /// Returns every pair of resting bid and ask order IDs whose prices cross
+    ///
+    /// Pairs are reported best bid first, then by ascending ask price. A
+    /// healthy book never produces any pairs.
+    pub fn find_crossing_orders(&self) -> Vec<(OrderId, OrderId)> {
+        let mut pairs: Vec<(OrderId, OrderId)> = vec![];
+
+        for (bid_price, bids) in self.bids.iter().rev() {
+            for (_, asks) in self.asks.range(..=*bid_price) {
+                for bid in bids.iter().filter(|t| !t.remaining.is_zero()) {
+                    for ask in asks.iter().filter(|t| !t.remaining.is_zero()) {
+                        pairs.push((bid.id, ask.id));
+                    }
+                }
+            }
+        }
+
+        pairs
+    }
+
+    /// Returns whether this book holds the same resting state as `other`
+    ///
+    /// Resting orders are compared by trader, side, price and quantity in
+    /// priority order, ignoring their IDs and timestamps, along with the
+    /// book's pricing, risk and fee configuration. Transient state such as
+    /// the tape, the settlement queue and the last traded price is ignored.
+    pub fn equals_state(&self, other: &Book) -> bool {
+        let resting = |book: &Book| {
+            book.levels(OrderSide::Bid)
+                .chain(book.levels(OrderSide::Ask))
+                .flat_map(|(_, orders)| orders.iter())
+                .filter(|t| !t.remaining.is_zero())
+                .map(|t| (t.trader, t.side, t.price, t.quantity, t.remaining))
+                .collect::<Vec<_>>()
+        };
+
+        self.market == other.market
+            && self.contract_size == other.contract_size
+            && self.tick_grid == other.tick_grid
+            && self.price_bucket == other.price_bucket
+            && self.max_notional == other.max_notional
+            && self.min_self_spread == other.min_self_spread
+            && self.self_trade_prevention == other.self_trade_prevention
+            && self.allocation == other.allocation
+            && self.priority_decay == other.priority_decay
+            && self.fee_schedule == other.fee_schedule
+            && self.fee_tiers == other.fee_tiers
+            && resting(self) == resting(other)
+    }
+
+    /// Returns a checksum of the resting orders of the book
+    ///
+    /// This is the Keccak-256 digest of the market address followed by the
+    /// binary encoding of each resting order in priority order, bids first.
+    pub fn checksum(&self) -> H256 {
+        let mut data: Vec<u8> = self.market.as_ref().to_vec();
+
+        for order in self
+            .bids
+            .values()
+            .chain(self.asks.values())
+            .flatten()
+            .filter(|t| !t.remaining.is_zero())
+        {
+            data.extend(order.to_bytes());
+        }
+
+        web3::signing::keccak256(&data).into()
+    }
+
+    /// Returns the leaves of the Merkle tree over the resting orders of the
+    /// book, in the same order as `Book::checksum`
+    fn merkle_leaves(&self) -> Vec<(OrderId, H256)> {
+        self.bids
+            .values()
+            .chain(self.asks.values())
+            .flatten()
+            .filter(|t| !t.remaining.is_zero())
+            .map(|t| (t.id, merkle::leaf(t)))
+            .collect()
+    }
+
+    /// Returns the root of a Merkle tree committing to every resting order
+    pub fn merkle_root(&self) -> H256 {
+        let leaves: Vec<H256> =
+            self.merkle_leaves().into_iter().map(|(_, t)| t).collect();
+        merkle::root(&leaves)
+    }
+
+    /// Returns a proof that the order with the provided ID is resting in the
+    /// book, checkable with `merkle::verify` against `Book::merkle_root`
+    pub fn merkle_proof(&self, order_id: OrderId) -> Option<Vec<H256>> {
+        let leaves: Vec<(OrderId, H256)> = self.merkle_leaves();
+        let index: usize = leaves.iter().position(|(id, _)| *id == order_id)?;
+        let hashes: Vec<H256> = leaves.into_iter().map(|(_, t)| t).collect();
+
+        merkle::proof(&hashes, index)
+    }
+
+    /// Returns an aggregated (L2) snapshot of the book
+    ///
+    /// Each price level is reported with its total remaining quantity.
+    pub fn snapshot(&self) -> BookSnapshot {
+        let aggregate = |side: &BTreeMap<U256, VecDeque<Order>>| {
+            side.iter()
+                .map(|(price, orders)| {
+                    (
+                        *price,
+                        orders
+                            .iter()
+                            .fold(U256::zero(), |acc, t| acc + t.remaining),
+                    )
+                })
+                .filter(|(_, quantity)| !quantity.is_zero())
+                .collect()
+        };
+
+        BookSnapshot {
+            bids: aggregate(&self.bids),
+            asks: aggregate(&self.asks),
+        }
+    }
+
+    /// Returns the imbalance of resting volume over the best `levels` price
+    /// levels of each side
+    ///
+    /// This is the difference between bid and ask volume as a proportion of
+    /// their total, ranging from -1 (asks only) to 1 (bids only). Returns
+    /// `None` if there is no volume at all.
+    pub fn imbalance(&self, levels: usize) -> Option<f64> {
+        let volume = |side: OrderSide| {
+            u256_to_f64(
+                self.levels(side)
+                    .take(levels)
+                    .flat_map(|(_, orders)| orders.iter())
+                    .fold(U256::zero(), |acc, t| {
+                        acc.saturating_add(t.remaining)
+                    }),
+            )
+        };
+        let bid_volume: f64 = volume(OrderSide::Bid);
+        let ask_volume: f64 = volume(OrderSide::Ask);
+        let total: f64 = bid_volume + ask_volume;
+
+        if total == 0.0 {
+            return None;
+        }
+
+        Some((bid_volume - ask_volume) / total)
+    }
+
+    /// Returns how concentrated the liquidity on the provided side is among
+    /// its resting orders
+    ///
+    /// This is the Herfindahl index of the quantities of the orders resting
+    /// within the best `CONCENTRATION_LEVELS` levels: the sum of the squares
+    /// of each order's share of their total. It ranges from `1 / n` for `n`
+    /// equally sized orders to 1 for a single order, and is zero for an
+    /// empty side.
+    pub fn concentration(&self, side: OrderSide) -> f64 {
+        let quantities: Vec<f64> = self
+            .levels(side)
+            .take(CONCENTRATION_LEVELS)
+            .flat_map(|(_, orders)| orders.iter())
+            .map(|t| u256_to_f64(t.remaining))
+            .collect();
+        let total: f64 = quantities.iter().sum();
+
+        if total == 0.0 {
+            return 0.0;
+        }
+
+        quantities.iter().map(|t| (t / total).powi(2)).sum()
+    }
+
+    /// Returns a market-by-order (L3) snapshot of the book
+    ///
+    /// Every resting order is reported along with its rank in the queue at
+    /// its price level, where the front of the queue has rank zero.
+    pub fn l3_snapshot(&self) -> L3Snapshot {
+        let entries = |side: OrderSide| {
+            self.levels(side)
+                .flat_map(|(price, orders)| {
+                    orders
+                        .iter()
+                        .filter(|t| !t.remaining.is_zero())
+                        .enumerate()
+                        .map(move |(rank, order)| L3Entry {
+                            id: order.id,
+                            trader: order.trader,
+                            price: *price,
+                            remaining: order.remaining,
+                            rank,
+                        })
+                })
+                .collect()
+        };
+
+        L3Snapshot {
+            bids: entries(OrderSide::Bid),
+            asks: entries(OrderSide::Ask),
+        }
+    }
+
+    /// Returns the price levels of `side` in priority order (best first)
+    fn levels(
+        &self,
+        side: OrderSide,
+    ) -> impl Iterator<Item = (&U256, &VecDeque<Order>)> {
+        match side {
+            OrderSide::Bid => Either::Left(self.bids.iter().rev()),
+            OrderSide::Ask => Either::Right(self.asks.iter()),
+        }
+    }
+
+    /// Returns whether the provided order would match against any resting
+    /// order from the same trader
+    fn would_self_trade(&self, order: &Order) -> bool {
+        let mut remaining: U256 = order.remaining;
+        let related: Vec<Address> = self.related_traders(order.trader);
+
+        for (price, opposites) in self.levels(order.side.opposite()) {
+            if !Book::price_viable(
+                *price,
+                order.price,
+                order.side,
+                self.price_bucket,
+            ) {
+                break;
+            }
+
+            for opposite in opposites {
+                if related.contains(&opposite.trader) {
+                    return true;
+                }
+
+                remaining = remaining.saturating_sub(opposite.remaining);
+
+                if remaining.is_zero() {
+                    return false;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Returns whether the provided order would quote a narrower spread than
+    /// `min_self_spread` against its trader's own best opposing order
+    fn narrows_own_spread(&self, order: &Order) -> bool {
+        let min_spread: U256 = match self.min_self_spread {
+            Some(t) => t,
+            None => return false,
+        };
+
+        let own_best: Option<U256> = self
+            .levels(order.side.opposite())
+            .find(|(_, opposites)| {
+                opposites.iter().any(|t| t.trader == order.trader)
+            })
+            .map(|(price, _)| *price);
+
+        match (order.side, own_best) {
+            (OrderSide::Bid, Some(ask)) => {
+                ask < order.price.saturating_add(min_spread)
+            }
+            (OrderSide::Ask, Some(bid)) => {
+                order.price < bid.saturating_add(min_spread)
+            }
+            (_, None) => false,
+        }
+    }
+
+    /// Returns whether a resting order at `opposite` may match an incoming
+    /// order limited to `incoming`
+    ///
+    /// With a bucket width, prices are compared only by the bucket they fall
+    /// in, so an order may match up to a bucket's width beyond its limit.
+    fn price_viable(
+        opposite: U256,
+        incoming: U256,
+        incoming_side: OrderSide,
+        bucket: Option<U256>,
+    ) -> bool {
+        let (opposite, incoming): (U256, U256) = match bucket {
+            Some(width) if !width.is_zero() => {
+                (opposite / width, incoming / width)
+            }
+            _ => (opposite, incoming),
+        };
+
+        match incoming_side {
+            OrderSide::Bid => opposite <= incoming,
+            OrderSide::Ask => opposite >= incoming,
+        }
+    }
+
+    #[allow(unused_must_use)]
+    async fn r#match(
+        &mut self,
+        mut order: Order,
+        opposing_top: Option<U256>,
+    ) -> Result<OrderStatus, BookError> {
+        info!("Matching {}...", order);
+
+        let volume_since: Option<DateTime<Utc>> = self.volume_since();
+        let related: Vec<Address> = self.related_traders(order.trader);
+        /* every fill is referenced against the touch before the taker
+         * arrived, whatever it skips or sweeps on the way */
+        let (reference_bid, reference_ask): (Option<U256>, Option<U256>) =
+            self.top();
+        let opposing_side: &mut BTreeMap<U256, VecDeque<Order>> =
+            match order.side {
+                OrderSide::Bid => &mut self.asks,
+                OrderSide::Ask => &mut self.bids,
+            };
+        let arrived: U256 = order.remaining;
+        let mut running_total: U256 = order.remaining;
+        let mut done: bool = false;
+        let max_steps: Option<usize> = self.max_match_steps;
+        let mut steps: usize = 0;
+        let max_levels: Option<usize> = self.max_sweep_levels;
+        let bucket: Option<U256> = self.price_bucket;
+        let priority_decay: Option<Duration> = self.priority_decay;
+        let now: DateTime<Utc> = self.clock.now();
+        let mut quarantined: Vec<Order> = vec![];
+        let mut limited: Option<BookError> = None;
+        let mut swept: U256 = U256::zero(); /* taken from resting makers */
+
+        /* if we haven't crossed the spread, we're not going to match */
+        if opposing_top.is_none()
+            || !Book::price_viable(
+                opposing_top.unwrap(),
+                order.price,
+                order.side,
+                bucket,
+            )
+        {
+            info!("{} does not cross, adding...", order);
+            self.rest(order);
+            return Ok(OrderStatus::Add);
+        }
+
+        let opposing_side_iterator = match order.side {
+            OrderSide::Bid => Either::Left(opposing_side.iter_mut()),
+            OrderSide::Ask => Either::Right(opposing_side.iter_mut().rev()),
+        };
+
+        for (levels, (price, opposites)) in opposing_side_iterator.enumerate() {
+            /* if we've run out of viable prices or we're done, halt */
+            if done
+                || !Book::price_viable(*price, order.price, order.side, bucket)
+            {
+                break;
+            }
+
+            /* bound the number of price levels a single order can sweep */
+            if matches!(max_levels, Some(t) if levels >= t) {
+                info!("Sweep limit of {} levels reached", levels);
+                limited = Some(BookError::SweepLimited);
+                break;
+            }
+
+            /* size priority ranks the level by what remains at match time,
+             * with the stable sort keeping arrival order among equals */
+            if self.allocation == Allocation::SizePriority {
+                opposites
+                    .make_contiguous()
+                    .sort_by_key(|t| Reverse(t.remaining));
+            }
+
+            /* orders past the decay age yield to younger ones at the same
+             * price, keeping their order among themselves */
+            if let Some(decay) = priority_decay {
+                opposites
+                    .make_contiguous()
+                    .sort_by_key(|t| now - t.created >= decay);
+            }
+
+            let mut first_filled: Option<usize> = None;
+
+            for (position, opposite) in opposites.iter_mut().enumerate() {
+                /* no self-trading allowed, even between related traders */
+                if related.contains(&opposite.trader) {
+                    info!("Self-trade, skipping...");
+                    continue;
+                }
+
+                /* skip makers too small for the taker's minimum fill,
+                 * unless the taker itself needs less than that */
+                if let Some(min) = order.min_fill_qty {
+                    if opposite.remaining < min.min(order.remaining) {
+                        info!("Maker below minimum fill, skipping...");
+                        continue;
+                    }
+                }
+
+                /* aged makers must pass re-validation, or be quarantined */
+                let valid: bool = match &self.revalidation {
+                    Some(policy) => policy.permits(opposite, now),
+                    None => true,
+                };
+                if !valid {
+                    warn!("{} failed re-validation, quarantining...", opposite);
+                    quarantined.push(opposite.clone());
+                    swept += opposite.remaining;
+                    opposite.remaining = U256::zero();
+                    continue;
+                }
+
+                /* bound the number of makers a single order can sweep */
+                if matches!(max_steps, Some(t) if steps >= t) {
+                    info!("Match limit of {} reached", steps);
+                    limited = Some(BookError::MatchLimitReached);
+                    done = true;
+                    break;
+                }
+                steps += 1;
+                first_filled.get_or_insert(position);
+
+                /* determine how much to match */
+                let mut amount: U256 =
+                    match opposite.remaining.cmp(&order.remaining) {
+                        Ordering::Greater => order.remaining,
+                        _ => opposite.remaining,
+                    };
+
+                /* once a taker has matched, a sub-lot remainder is dust */
+                if let Some(lot) = self.lot_size {
+                    if order.remaining < lot && order.remaining < order.quantity
+                    {
+                        match self.dust_policy {
+                            DustPolicy::DropRemainder => {
+                                info!("Dropping dust of {}", order.remaining);
+                                done = true;
+                                break;
+                            }
+                            DustPolicy::RoundLastFill => {
+                                amount = lot.min(opposite.remaining);
+                                info!("Rounding dust up to {}", amount);
+
+                                if amount > order.remaining {
+                                    order.quantity += amount - order.remaining;
+                                    order.remaining = amount;
+                                }
+                            }
+                        }
+                    }
+                }
+                info!("Matching with amount of {}...", amount);
+
+                /* match */
+                order = Book::fill(order, amount);
+                *opposite = Book::fill(opposite.clone(), amount);
+                swept += amount;
+
+                /* reopening fills trade at the reference price, within
+                 * the limits of both orders */
+                let execution: U256 = match self.reopening_price {
+                    Some(reference) if self.reopening_fills_left > 0 => {
+                        self.reopening_fills_left -= 1;
+                        reference
+                            .max(std::cmp::min(*price, order.price))
+                            .min(std::cmp::max(*price, order.price))
+                    }
+                    _ => *price,
+                };
+
+                let fill: Fill = Fill::new(
+                    opposite.clone(),
+                    order.clone(),
+                    execution,
+                    amount,
+                    execution
+                        .saturating_mul(amount)
+                        .saturating_mul(self.contract_size),
+                    self.clock.now(),
+                )
+                .with_fees(
+                    &Book::tier_schedule(
+                        &self.fee_tiers,
+                        &self.tape,
+                        volume_since,
+                        opposite.trader,
+                    )
+                    .unwrap_or(self.fee_schedule),
+                    &Book::tier_schedule(
+                        &self.fee_tiers,
+                        &self.tape,
+                        volume_since,
+                        order.trader,
+                    )
+                    .unwrap_or(self.fee_schedule),
+                );
+                let fill: Fill = match self.incentive {
+                    Some(program) => {
+                        let rested: Duration =
+                            fill.timestamp - opposite.created;
+                        let rebate: U256 =
+                            program.rebate(fill.notional, rested);
+                        fill.with_incentive_rebate(rebate)
+                    }
+                    None => fill,
+                };
+
+                let fill: Fill =
+                    fill.with_reference(reference_bid, reference_ask);
+
+                /* makers with a last look window get to reject the match */
+                match opposite.last_look {
+                    Some(window) => {
+                        info!("Holding {} for last look...", fill);
+                        self.pending_fills.push(PendingFill {
+                            deadline: fill.timestamp + window,
+                            fill,
+                            withdrawn: false,
+                        });
+                    }
+                    None => {
+                        self.ltp = execution;
+                        info!("LTP updated, is now {}", self.ltp);
+                        self.tape.push(fill.clone());
+                        self.settlements.push_back(fill);
+                    }
+                }
+
+                running_total = running_total.saturating_sub(amount);
+
+                /* check if we've totally matched our incoming order */
+                if running_total.is_zero() {
+                    info!("Totally matched {}", order);
+                    done = true;
+                    break;
+                }
+            }
+
+            /* under round-robin allocation, the first maker filled gives
+             * up its turn to the rest of the level */
+            if self.allocation == Allocation::RoundRobin {
+                if let Some(position) = first_filled {
+                    if let Some(maker) = opposites.remove(position) {
+                        opposites.push_back(maker);
+                    }
+                }
+            }
+        }
+
+        self.shrink_volume(order.side.opposite(), swept);
+
+        if !quarantined.is_empty() {
+            self.journal(
+                &quarantined
+                    .iter()
+                    .map(|t| Order {
+                        remaining: U256::zero(),
+                        ..t.clone()
+                    })
+                    .collect::<Vec<Order>>(),
+            );
+            self.quarantined.append(&mut quarantined);
+        }
+
+        /* a limited order has its remaining volume cancelled, as resting it
+         * would cross the book, and only fails if it never matched */
+        if let Some(e) = limited {
+            warn!("Discarding remaining {} of {}", running_total, order);
+            if running_total == arrived {
+                return Err(e);
+            }
+
+            self.match_limit = Some(e);
+            return Ok(OrderStatus::PartialMatch);
+        }
+
+        /* a taker left with dust never rests it */
+        let dust: bool = matches!(
+            self.lot_size,
+            Some(lot) if running_total < lot && order.remaining < order.quantity
+        );
+        if dust && !running_total.is_zero() {
+            warn!("Discarding dust of {} of {}", running_total, order);
+            return Ok(OrderStatus::PartialMatch);
+        }
+
+        /* if our incoming order has any volume left, add it to the book */
+        if running_total > U256::zero() {
+            self.rest(order);
+            Ok(OrderStatus::PartialMatch)
+        } else {
+            Ok(OrderStatus::FullMatch)
+        }
+    }
+
+    /// Rests the remainder of an incoming order in the book
+    ///
+    /// If `merge_duplicate_levels` is set and the trader already has an order
+    /// resting at the same price, the incoming quantity is merged into that
+    /// order, which keeps its ID and priority.
+    #[allow(unused_must_use)]
+    fn rest(&mut self, order: Order) {
+        if self.merge_duplicate_levels {
+            let level: Option<&mut VecDeque<Order>> = match order.side {
+                OrderSide::Bid => self.bids.get_mut(&order.price),
+                OrderSide::Ask => self.asks.get_mut(&order.price),
+            };
+
+            if let Some(existing) = level.and_then(|orders| {
+                orders.iter_mut().find(|t| t.trader == order.trader)
+            }) {
+                existing.quantity += order.quantity;
+                existing.remaining += order.remaining;
+                info!("Merged {} into {}", order, existing);
+
+                let merged: Order = existing.clone();
+                self.grow_volume(order.side, order.remaining);
+                self.journal(&[merged]);
+                return;
+            }
+        }
+
+        self.add_order(order);
+    }
+
+    fn fill(order: Order, amount: U256) -> Order {
+        info!("Filling {} of {}...", amount, order);
+        match amount.cmp(&order.remaining) {
+            Ordering::Greater => order,
+            _ => Order {
+                remaining: order.remaining - amount,
+                ..order
+            },
+        }
+    }
+
+    fn prune(&mut self) {
+        let mut exhausted: Vec<Address> = vec![];
+        let mut keep = |order: &Order| {
+            if order.remaining.is_zero() {
+                exhausted.push(order.trader);
+            }
+            !order.remaining.is_zero()
+        };
+
+        for (_price, orders) in self.bids.iter_mut() {
+            orders.retain(&mut keep);
+        }
+
+        for (_price, orders) in self.asks.iter_mut() {
+            orders.retain(&mut keep);
+        }
+
+        for trader in exhausted {
+            self.close_order(trader);
+        }
+
+        self.bids.retain(|_price, orders| !orders.is_empty());
+        self.asks.retain(|_price, orders| !orders.is_empty());
+    }
+
+    /// Submits an order to the matching engine
+    ///
+    /// In the event the order cannot be (fully) matched, it will be stored
+    /// in the order book for future matching.
+    pub async fn submit(
+        &mut self,
+        order: Order,
+        executioner_address: String,
+    ) -> Result<OrderStatus, BookError> {
+        info!("Submitting {}...", order);
+
+        self.prepare_matching(executioner_address.clone()).await;
+        self.check_settlement_capacity()?;
+        self.check_timestamp_skew(&order)?;
+
+        let top: TopOfBook = self.capture_top();
+        let incoming: Order = order.clone();
+        let fills_before: usize = self.tape.len();
+        let pending_before: usize = self.pending_fills.len();
+
+        self.match_limit = None;
+        let match_result: Result<OrderStatus, BookError> =
+            self.admit(order).await;
+        /* orders triggered below must not overwrite this order's limit */
+        let match_limit: Option<BookError> = self.match_limit.take();
+
+        if match_result.is_ok() {
+            self.sequence += 1;
+            self.order_counts.entry(incoming.trader).or_default().0 += 1;
+        }
+
+        self.journal_submission(
+            std::slice::from_ref(&incoming),
+            fills_before,
+            pending_before,
+        );
+        self.follow_matching().await;
+        self.publish_top_change(top);
+        self.settle(executioner_address).await;
+        self.match_limit = match_limit;
+
+        match_result
+    }
+
+    /// Brings the book up to date before an incoming order is matched
+    ///
+    /// Elapsed last look windows are confirmed, orders due are activated,
+    /// lapsed orders and settlements are expired and queued fills are
+    /// forwarded to the executioner.
+    async fn prepare_matching(&mut self, executioner_address: String) {
+        self.confirm_elapsed_fills(executioner_address.clone())
+            .await;
+        self.activate_pending(self.now()).await;
+        self.expire_orders(self.now()).await;
+        self.sweep_settlement_timeouts(self.now());
+        self.settle(executioner_address).await;
+    }
+
+    /// Places whatever the fills of an incoming order have set off: stops
+    /// triggered by the last traded price, orders held for depth triggers
+    /// and the next slices of icebergs
+    async fn follow_matching(&mut self) {
+        self.trigger_stops().await;
+        self.check_depth_triggers().await;
+        self.refresh_icebergs().await;
+    }
+
+    /// Submits an order to the matching engine, acknowledging it with a
+    /// receipt
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(receipt)` upon success, where `receipt` holds the order's
+    /// ID, the submission's sequence number, the time it was accepted, every
+    /// fill the order took part in and, if a matching limit cut it short,
+    /// the limit reached.
+    ///
+    /// Returns a `BookError` if there is an error condition
+    pub async fn submit_with_receipt(
+        &mut self,
+        order: Order,
+        executioner_address: String,
+    ) -> Result<SubmitReceipt, BookError> {
+        let order_id: OrderId = order.id;
+        let fills_before: usize = self.tape.len();
+
+        let status: OrderStatus =
+            self.submit(order, executioner_address).await?;
+
+        /* the tape only grows, but pending fills may have been confirmed */
+        let fills: Vec<Fill> = self.tape[fills_before..]
+            .iter()
+            .chain(self.pending_fills.iter().map(|t| &t.fill))
+            .filter(|t| t.maker.id == order_id || t.taker.id == order_id)
+            .cloned()
+            .collect();
+        let fills: Vec<Fill> = match self.fill_granularity {
+            FillGranularity::PerSlice => fills,
+            FillGranularity::PerMaker => Book::coalesce_fills(fills, order_id),
+        };
+
+        Ok(SubmitReceipt {
+            order_id,
+            sequence: self.sequence,
+            accepted: self.now(),
+            status,
+            fills,
+            limit: self.match_limit,
+        })
+    }
+
+    /// Consolidates the provided fills of an order against the same
+    /// counterparty, at the same price and in the same role, into one
+    ///
+    /// Each consolidated fill keeps the ID of the first fill it covers and
+    /// the order states and timestamp of the last, with quantities, notional
+    /// values, fees and rebates summed.
+    fn coalesce_fills(fills: Vec<Fill>, order_id: OrderId) -> Vec<Fill> {
+        let counterparty = |fill: &Fill| {
+            if fill.maker.id == order_id {
+                fill.taker.trader
+            } else {
+                fill.maker.trader
+            }
+        };
+        let mut coalesced: Vec<Fill> = vec![];
+
+        for fill in fills {
+            match coalesced.iter_mut().find(|t| {
+                counterparty(t) == counterparty(&fill)
+                    && t.price == fill.price
+                    && t.maker_side == fill.maker_side
+            }) {
+                Some(existing) => {
+                    *existing = Fill {
+                        id: existing.id,
+                        quantity: existing.quantity + fill.quantity,
+                        notional: existing.notional + fill.notional,
+                        maker_fee: existing.maker_fee + fill.maker_fee,
+                        taker_fee: existing.taker_fee + fill.taker_fee,
+                        incentive_rebate: existing.incentive_rebate
+                            + fill.incentive_rebate,
+                        ..fill
+                    }
+                }
+                None => coalesced.push(fill),
+            }
+        }
+
+        coalesced
+    }
+
+    /// Submits a market order to the matching engine
+    ///
+    /// An order priced at zero has no explicit limit, so its limit is set by
+    /// `market_order_protection` relative to the reference price: the last
+    /// traded price or, before any trades, the best opposing price. Without
+    /// protection, the limit is the worst opposing price. As the ID of an
+    /// order is derived from its price, an order given a limit this way is
+    /// identified by `Order::with_price` from then on. Any unfilled
+    /// remainder is cancelled rather than left resting.
+    ///
+    /// Should the opposing side be empty, `market_order_fallback` decides
+    /// whether the order is rejected or rests at the last traded price. An
+    /// order with an explicit limit never rests beyond it, so it rests at
+    /// whichever of the two is less aggressive.
+    ///
+    /// # Returns #
+    ///
+    /// Returns the outcome of matching the order, as for `Book::submit`.
+    ///
+    /// Returns `BookError::OneSidedBook` if the opposing side is empty and
+    /// `reject_if_one_sided` is set, `BookError::InsufficientLiquidity` if
+    /// the opposing side is empty and the order cannot rest in its place,
+    /// `BookError::InvalidOrder`
+    /// if there is no price to derive a limit from, or a `BookError` from
+    /// submission otherwise
+    pub async fn submit_market(
+        &mut self,
+        mut order: Order,
+        executioner_address: String,
+    ) -> Result<OrderStatus, BookError> {
+        self.check_one_sided(&order)?;
+
+        if self.levels(order.side.opposite()).next().is_none() {
+            if self.market_order_fallback == MarketOrderFallback::Reject
+                || self.tape.is_empty()
+            {
+                warn!("Rejecting {} as there is nothing to fill", order);
+                return Err(BookError::InsufficientLiquidity);
+            }
+
+            let reference: U256 = if order.price.is_zero() {
+                self.ltp
+            } else {
+                match order.side {
+                    OrderSide::Bid => std::cmp::min(self.ltp, order.price),
+                    OrderSide::Ask => std::cmp::max(self.ltp, order.price),
+                }
+            };
+
+            if reference != order.price {
+                order = order.with_price(reference);
+            }
+
+            info!("Resting market order {} at the reference price", order);
+            return self.submit(order, executioner_address).await;
+        }
+
+        if order.price.is_zero() {
+            order = match self.market_order_limit(order.side) {
+                Some(t) => order.with_price(t),
+                None => {
+                    warn!(
+                        "Rejecting {} as there is no price to fill at",
+                        order
+                    );
+                    return Err(BookError::InvalidOrder);
+                }
+            };
+            info!("Limiting market order {}", order);
+        }
+
+        let id: OrderId = order.id;
+        let status: OrderStatus =
+            self.submit(order, executioner_address).await?;
+
+        if self.order(id).is_some() {
+            self.cancel(id)?;
+        }
+
+        Ok(status)
+    }
+
+    /// Checks that a taker order has an opposing side to match against, if
+    /// the book must stay two-sided
+    ///
+    /// Only market and immediate-or-cancel orders are checked. A limit order
+    /// cannot cross an empty side, so it rests as a maker instead.
+    fn check_one_sided(&self, order: &Order) -> Result<(), BookError> {
+        if self.reject_if_one_sided
+            && self.levels(order.side.opposite()).next().is_none()
+        {
+            warn!("Rejecting {} as the opposing side is empty", order);
+            return Err(BookError::OneSidedBook);
+        }
+
+        Ok(())
+    }
+
+    /// Submits an immediate-or-cancel order, which matches what it can on
+    /// arrival and never rests
+    ///
+    /// Any unfilled remainder is cancelled. If `return_ioc_remainder` is set,
+    /// it is also handed back as a fresh order the caller may resubmit.
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok((status, remainder))` upon success, where `status` is the
+    /// outcome of matching the order, as for `Book::submit`, and `remainder`
+    /// is the unfilled quantity as a new order, if requested and any is left.
+    ///
+    /// Returns `BookError::OneSidedBook` if the opposing side is empty and
+    /// `reject_if_one_sided` is set, or a `BookError` if there is an error
+    /// condition otherwise
+    pub async fn submit_ioc(
+        &mut self,
+        order: Order,
+        executioner_address: String,
+    ) -> Result<(OrderStatus, Option<Order>), BookError> {
+        self.check_one_sided(&order)?;
+
+        let id: OrderId = order.id;
+        let status: OrderStatus =
+            self.submit(order, executioner_address).await?;
+
+        let unfilled: Option<Order> = self.order(id).cloned();
+        if unfilled.is_some() {
+            self.cancel(id)?;
+        }
+
+        let remainder: Option<Order> = unfilled
+            .filter(|_| self.return_ioc_remainder)
+            .map(|t| t.remainder(self.now()));
+
+        Ok((status, remainder))
+    }
+
+    /// Submits a post-only order, which may only ever rest as a maker
+    ///
+    /// An order priced at exactly the opposing touch would match, so it is
+    /// rejected just as one priced through the touch is. One tick short of
+    /// the touch, it rests.
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(OrderStatus::Add)` if the order rests, or its status as
+    /// for `Book::submit` if it is held back from the book.
+    ///
+    /// Returns `BookError::WouldMatch` if the order would match on arrival,
+    /// or a `BookError` from submission otherwise
+    pub async fn submit_post_only(
+        &mut self,
+        order: Order,
+        executioner_address: String,
+    ) -> Result<OrderStatus, BookError> {
+        let opposing_top: Option<U256> = match order.side {
+            OrderSide::Bid => self.top().1,
+            OrderSide::Ask => self.top().0,
+        };
+
+        if let Some(touch) = opposing_top {
+            if Book::price_viable(
+                touch,
+                order.price,
+                order.side,
+                self.price_bucket,
+            ) {
+                warn!("Rejecting post-only {} as it would match", order);
+                return Err(BookError::WouldMatch);
+            }
+        }
+
+        self.submit(order, executioner_address).await
+    }
+
+    /// Returns the implicit limit price of a market order on `side`
+    fn market_order_limit(&self, side: OrderSide) -> Option<U256> {
+        let opposing: (Option<U256>, Option<U256>) = match side {
+            OrderSide::Bid => {
+                (self.top().1, self.asks.keys().next_back().copied())
+            }
+            OrderSide::Ask => (self.top().0, self.bids.keys().next().copied()),
+        };
+
+        let bps: u32 = match self.market_order_protection {
+            Some(t) => t,
+            None => return opposing.1,
+        };
+
+        let reference: U256 = if self.tape.is_empty() {
+            opposing.0?
+        } else {
+            self.ltp
+        };
+
+        match side {
+            OrderSide::Bid => {
+                Some(reference.saturating_add(apply_bps(reference, bps)))
+            }
+            OrderSide::Ask => {
+                Some(reference.saturating_sub(apply_bps(reference, bps)))
+                    .filter(|t| !t.is_zero())
+            }
+        }
+    }
+
+    /// Submits an order held back from the book until the depth described by
+    /// `trigger` has built up
+    ///
+    /// An order whose trigger is already met is placed immediately.
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(OrderStatus::Armed)` if the order is awaiting its trigger,
+    /// or the outcome of placing it otherwise.
+    ///
+    /// Returns a `BookError` if the order is invalid
+    pub async fn submit_depth_triggered(
+        &mut self,
+        order: Order,
+        trigger: DepthTrigger,
+        executioner_address: String,
+    ) -> Result<OrderStatus, BookError> {
+        info!("Submitting {} held until {:?}...", order, trigger);
+
+        self.validate(&order)?;
+        self.check_timestamp_skew(&order)?;
+        let id: OrderId = order.id;
+        self.depth_triggered.push((trigger, order));
+
+        let top: TopOfBook = self.capture_top();
+        self.check_depth_triggers().await;
+        self.publish_top_change(top);
+        self.settle(executioner_address).await;
+
+        if self.depth_triggered.iter().any(|(_, t)| t.id == id) {
+            return Ok(OrderStatus::Armed);
+        }
+
+        Ok(self.placement_status(id))
+    }
+
+    /// Activates every held order whose depth trigger is met
+    ///
+    /// Activated orders are matched as though newly submitted, oldest first.
+    /// As matching changes the depth of the book, the triggers of the
+    /// remaining held orders are checked again until none are met. Any
+    /// resulting fills are queued for settlement.
+    ///
+    /// # Returns #
+    ///
+    /// Returns the outcome of matching each activated order, in order.
+    pub async fn check_depth_triggers(
+        &mut self,
+    ) -> Vec<Result<OrderStatus, BookError>> {
+        let mut results: Vec<Result<OrderStatus, BookError>> = vec![];
+
+        loop {
+            let index: Option<usize> =
+                self.depth_triggered.iter().position(|(trigger, _)| {
+                    self.quantity_at(trigger.side, trigger.price)
+                        >= trigger.min_qty
+                });
+
+            let order: Order = match index {
+                Some(t) => self.depth_triggered.remove(t).1,
+                None => break,
+            };
+
+            info!("Activating depth-triggered {}...", order);
+            let fills_before: usize = self.tape.len();
+            let pending_before: usize = self.pending_fills.len();
+
+            results.push(self.admit(order.clone()).await);
+            self.journal_submission(
+                std::slice::from_ref(&order),
+                fills_before,
+                pending_before,
+            );
+        }
+
+        results
+    }
+
+    /// Submits an iceberg order, displaying only a slice of around `display`
+    /// of its quantity at a time
+    ///
+    /// If `jitter` is provided, the size of each slice varies randomly about
+    /// `display`, which makes the iceberg harder to detect.
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(OrderStatus::Add)` if the first slice rests untouched,
+    /// `Ok(OrderStatus::PartialMatch)` if the iceberg is partially filled, or
+    /// `Ok(OrderStatus::FullMatch)` if it has been filled entirely.
+    ///
+    /// Returns `BookError::InvalidOrder` if `display` is zero or `jitter`
+    /// varies by more than `BPS_DENOMINATOR` basis points, or a `BookError`
+    /// if the order is otherwise invalid
+    pub async fn submit_iceberg(
+        &mut self,
+        order: Order,
+        display: U256,
+        jitter: Option<SliceJitter>,
+        executioner_address: String,
+    ) -> Result<OrderStatus, BookError> {
+        info!("Submitting iceberg {} displaying {}...", order, display);
+
+        self.validate(&order)?;
+        self.check_timestamp_skew(&order)?;
+
+        if display.is_zero() {
+            warn!("Rejecting iceberg {} as it displays nothing", order);
+            return Err(BookError::InvalidOrder);
+        }
+
+        if let Some(jitter) = jitter {
+            if jitter.bps > BPS_DENOMINATOR {
+                warn!("Rejecting iceberg {} as its jitter is too wide", order);
+                return Err(BookError::InvalidOrder);
+            }
+        }
+
+        let id: OrderId = order.id;
+        self.icebergs
+            .push(IcebergOrder::new(order, display, jitter));
+
+        let top: TopOfBook = self.capture_top();
+        self.refresh_icebergs().await;
+        self.publish_top_change(top);
+        self.settle(executioner_address).await;
+
+        match self.icebergs.iter().find(|t| t.order.id == id) {
+            Some(iceberg) => match iceberg.slice.and_then(|t| self.order(t)) {
+                Some(slice)
+                    if iceberg.refreshes == 1
+                        && slice.remaining == slice.quantity =>
+                {
+                    Ok(OrderStatus::Add)
+                }
+                _ => Ok(OrderStatus::PartialMatch),
+            },
+            None => Ok(OrderStatus::FullMatch),
+        }
+    }
+
+    /// Refreshes every iceberg whose displayed slice has filled
+    ///
+    /// New slices join the back of the queue at their price and are matched
+    /// as though newly submitted, so a slice may itself fill and be refreshed
+    /// in turn. Icebergs are dropped once their reserve is exhausted, or if a
+    /// slice is rejected. Fills are queued for settlement.
+    pub async fn refresh_icebergs(&mut self) {
+        loop {
+            let index: Option<usize> =
+                self.icebergs.iter().position(|t| match t.slice {
+                    Some(id) => !self.displays(id),
+                    None => true,
+                });
+
+            let index: usize = match index {
+                Some(t) => t,
+                None => break,
+            };
+
+            let slice: Order = match self.icebergs[index].next_slice() {
+                Some(t) => t,
+                None => {
+                    info!("Iceberg {} exhausted", self.icebergs[index].order);
+                    self.icebergs.remove(index);
+                    continue;
+                }
+            };
+
+            info!("Refreshing iceberg with {}...", slice);
+            let fills_before: usize = self.tape.len();
+            let pending_before: usize = self.pending_fills.len();
+
+            if let Err(e) = self.admit(slice.clone()).await {
+                warn!("Withdrawing iceberg after rejected slice: {}", e);
+                self.icebergs.remove(index);
+            }
+
+            self.journal_submission(
+                std::slice::from_ref(&slice),
+                fills_before,
+                pending_before,
+            );
+        }
+    }
+
+    /// Returns whether the order is resting, or held to rest later
+    fn displays(&self, id: OrderId) -> bool {
+        self.order(id).is_some()
+            || self
+                .inactive
+                .iter()
+                .chain(self.halt_queue.iter())
+                .any(|t| t.id == id)
+    }
+
+    /// Submits a stop order, held back from the book until the last traded
+    /// price reaches `trigger`
+    ///
+    /// A stop that has already been triggered is placed immediately.
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(OrderStatus::Armed)` if the stop is awaiting its trigger,
+    /// or the outcome of placing it otherwise.
+    ///
+    /// Returns a `BookError` if the order is invalid
+    pub async fn submit_stop(
+        &mut self,
+        order: Order,
+        trigger: U256,
+        executioner_address: String,
+    ) -> Result<OrderStatus, BookError> {
+        info!("Submitting stop {} triggering at {}...", order, trigger);
+
+        self.validate(&order)?;
+        self.check_timestamp_skew(&order)?;
+        let id: OrderId = order.id;
+        self.stops.push(StopOrder::new(order, trigger));
+
+        let top: TopOfBook = self.capture_top();
+        self.trigger_stops().await;
+        self.publish_top_change(top);
+        self.settle(executioner_address).await;
+
+        if self.stops.iter().any(|t| t.order.id == id) {
+            return Ok(OrderStatus::Armed);
+        }
+
+        Ok(self.placement_status(id))
+    }
+
+    /// Returns the status of a just-placed order from what rests of it
+    fn placement_status(&self, id: OrderId) -> OrderStatus {
+        match self.order(id) {
+            Some(t) if t.remaining == t.quantity => OrderStatus::Add,
+            Some(_) => OrderStatus::PartialMatch,
+            None => OrderStatus::FullMatch,
+        }
+    }
+
+    /// Places every stop triggered by the last traded price
+    ///
+    /// The fills of triggered stops move the last traded price and so may
+    /// trigger further stops. The cascade is followed for at most
+    /// `max_cascade_iterations` rounds, after which remaining stops stay
+    /// armed until the next trade. Fills are queued for settlement.
+    ///
+    /// # Returns #
+    ///
+    /// Returns every fill (both executed and pending last look) produced by
+    /// the cascade, in order.
+    pub async fn trigger_stops(&mut self) -> Vec<Fill> {
+        let fills_before: usize = self.tape.len();
+        let pending_before: usize = self.pending_fills.len();
+
+        for _ in 0..self.max_cascade_iterations {
+            /* nothing has traded yet, so there is no price to trigger on */
+            if self.tape.is_empty() {
+                break;
+            }
+
+            let ltp: U256 = self.ltp;
+            let (triggered, armed): (Vec<StopOrder>, Vec<StopOrder>) =
+                std::mem::take(&mut self.stops)
+                    .into_iter()
+                    .partition(|t| t.is_triggered(ltp));
+            self.stops = armed;
+
+            if triggered.is_empty() {
+                break;
+            }
+
+            let round_fills: usize = self.tape.len();
+            let round_pending: usize = self.pending_fills.len();
+            let orders: Vec<Order> =
+                triggered.into_iter().map(|t| t.order).collect();
+
+            for order in orders.iter().cloned() {
+                info!("Triggering stop {}...", order);
+
+                if let Err(e) = self.admit(order).await {
+                    warn!("Failed to place triggered stop: {}", e);
+                }
+            }
+
+            self.journal_submission(&orders, round_fills, round_pending);
+        }
+
+        if self.stops.iter().any(|t| t.is_triggered(self.ltp)) {
+            warn!(
+                "Stop cascade halted after {} rounds",
+                self.max_cascade_iterations
+            );
+        }
+
+        self.tape[fills_before..]
+            .iter()
+            .cloned()
+            .chain(
+                self.pending_fills[pending_before..]
+                    .iter()
+                    .map(|t| t.fill.clone()),
+            )
+            .collect()
+    }
+
+    /// Submits several orders to the matching engine as a single unit
+    ///
+    /// Either every order is applied or none are: should any order fail
+    /// validation or matching, every mutation made by the transaction is
+    /// rolled back. Fills are only forwarded to the executioner once the
+    /// whole transaction has succeeded.
+    ///
+    /// The book is brought up to date beforehand, and stops, depth triggers
+    /// and icebergs set off by the transaction's fills are only placed once
+    /// it has committed, exactly as for `Book::submit`.
+    ///
+    /// A rollback completes before the transaction returns, and the
+    /// transaction holds the book exclusively throughout. Submissions from
+    /// other tasks are therefore serialised after it (by whatever lock the
+    /// book is shared through) and never observe a partially rolled back
+    /// book.
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(fills)` upon success, where `fills` holds the fills (both
+    /// executed and pending last look) produced by each order, in order.
+    ///
+    /// Returns the `BookError` of the first failing order otherwise
+    pub async fn transaction(
+        &mut self,
+        orders: Vec<Order>,
+        executioner_address: String,
+    ) -> Result<Vec<Vec<Fill>>, BookError> {
+        info!("Submitting transaction of {} orders...", orders.len());
+
+        self.prepare_matching(executioner_address.clone()).await;
+        self.check_settlement_capacity()?;
+
+        for order in orders.iter() {
+            self.validate(order)?;
+            self.check_timestamp_skew(order)?;
+        }
+
+        let top: TopOfBook = self.capture_top();
+        let backup: Book = self.clone();
+        let fills_before: usize = self.tape.len();
+        let pending_before: usize = self.pending_fills.len();
+        let mut fills: Vec<Vec<Fill>> = vec![];
+
+        /* nothing leaves the book until the transaction commits */
+        self.held_journal = Some(vec![]);
+
+        for order in orders.iter().cloned() {
+            let tape_start: usize = self.tape.len();
+            let pending_start: usize = self.pending_fills.len();
+
+            if let Err(e) = self.admit(order).await {
+                warn!("Rolling back transaction: {}", e);
+                self.roll_back_transaction(backup);
+                return Err(e);
+            }
+
+            fills.push(
+                self.tape[tape_start..]
+                    .iter()
+                    .cloned()
+                    .chain(
+                        self.pending_fills[pending_start..]
+                            .iter()
+                            .map(|t| t.fill.clone()),
+                    )
+                    .collect(),
+            );
+        }
+
+        /* every order was admitted, so the transaction commits */
+        for order in orders.iter() {
+            self.sequence += 1;
+            self.order_counts.entry(order.trader).or_default().0 += 1;
+        }
+
+        let held: Vec<Order> = self.held_journal.take().unwrap_or_default();
+        self.journal(&held);
+        self.journal_submission(&orders, fills_before, pending_before);
+        self.publish_delta();
+        self.follow_matching().await;
+        self.publish_top_change(top);
+        self.settle(executioner_address).await;
+
+        Ok(fills)
+    }
+
+    /// Restores the state of the book from before a transaction, discarding
+    /// the order log records and deltas it held back
+    ///
+    /// Fields that are not part of the book's state, such as subscribers and
+    /// the delta sequence already published, are kept as they are.
+    fn roll_back_transaction(&mut self, backup: Book) {
+        let live: Book = std::mem::replace(self, backup);
+
+        self.subscribers = live.subscribers;
+        self.replicas = live.replicas;
+        self.delta_feed = live.delta_feed;
+        self.delta_sequence = live.delta_sequence;
+        self.published = live.published;
+        self.clock = live.clock;
+        self.settlement = live.settlement;
+        self.revalidation = live.revalidation;
+        self.match_limit = live.match_limit;
+    }
+
+    /// Checks that the provided order is fit to be submitted to this book
+    pub fn validate(&self, order: &Order) -> Result<(), BookError> {
+        if order.market != self.market
+            || order.price.is_zero()
+            || order.remaining.is_zero()
+            || order.remaining > order.quantity
+        {
+            warn!("Rejecting invalid {}", order);
+            return Err(BookError::InvalidOrder);
+        }
+
+        if let Some(grid) = self.tick_grid {
+            if grid.to_ticks(order.price).is_none() {
+                warn!("Rejecting {} as its price is off the tick grid", order);
+                return Err(BookError::InvalidOrder);
+            }
+        }
+
+        if let Some(cap) = self.max_notional {
+            if self.notional(order.price, order.quantity) > cap {
+                warn!("Rejecting {} as its notional exceeds {}", order, cap);
+                return Err(BookError::NotionalTooLarge);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Refuses newly submitted orders created too long before or after the
+    /// current time
+    ///
+    /// Held orders are only checked on submission, not again when placed.
+    fn check_timestamp_skew(&self, order: &Order) -> Result<(), BookError> {
+        if let Some(skew) = self.max_timestamp_skew {
+            let now: DateTime<Utc> = self.now();
+
+            if order.created > now + skew || order.created < now - skew {
+                warn!(
+                    "Rejecting {} as it was created at {}",
+                    order, order.created
+                );
+                return Err(BookError::TimestampSkew);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Refuses new order flow while settlement has fallen behind
+    fn check_settlement_capacity(&self) -> Result<(), BookError> {
+        if let Some(capacity) = self.settlement_capacity {
+            if self.settlements.len() >= capacity {
+                warn!(
+                    "Rejecting order flow as {} fills are awaiting settlement",
+                    self.settlements.len()
+                );
+                return Err(BookError::SettlementBacklog);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Cancels every resting order whose TTL has lapsed as of `now`, placing
+    /// whatever replacement its re-quote callback returns
+    ///
+    /// Replacements are matched as though newly submitted. Orders that are
+    /// part of a fill held for last look are left until the fill resolves.
+    /// Any resulting fills are queued for settlement.
+    ///
+    /// # Returns #
+    ///
+    /// Returns the outcome of placing each replacement, in order.
+    pub async fn expire_orders(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> Vec<Result<OrderStatus, BookError>> {
+        let expired: Vec<Order> = self
+            .levels(OrderSide::Bid)
+            .chain(self.levels(OrderSide::Ask))
+            .flat_map(|(_, orders)| orders.iter())
+            .filter(|t| t.is_expired(now))
+            .cloned()
+            .collect();
+        let mut results: Vec<Result<OrderStatus, BookError>> = vec![];
+
+        for order in expired {
+            match self.cancel(order.id) {
+                Ok(Some(_)) => info!("TTL of {} lapsed", order),
+                _ => continue,
+            }
+
+            let replacement: Order =
+                match order.on_expire.as_ref().and_then(|t| (t.0)(&order)) {
+                    Some(t) => t,
+                    None => continue,
+                };
+
+            info!("Re-quoting {} as {}...", order, replacement);
+            let fills_before: usize = self.tape.len();
+            let pending_before: usize = self.pending_fills.len();
+            let top: TopOfBook = self.capture_top();
+
+            results.push(self.admit(replacement.clone()).await);
+            self.journal_submission(
+                std::slice::from_ref(&replacement),
+                fills_before,
+                pending_before,
+            );
+            self.publish_top_change(top);
+        }
+
+        results
+    }
+
+    /// Activates every held order whose activation time is at or before `now`
+    ///
+    /// Orders are activated oldest first and matched as though newly
+    /// submitted. Any resulting fills are queued for settlement.
+    ///
+    /// # Returns #
+    ///
+    /// Returns the outcome of matching each activated order, in order.
+    pub async fn activate_pending(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> Vec<Result<OrderStatus, BookError>> {
+        if self.halted {
+            return vec![];
+        }
+
+        let (held, due): (Vec<Order>, Vec<Order>) =
+            std::mem::take(&mut self.inactive)
+                .into_iter()
+                .partition(|t| t.is_inactive(now));
+        self.inactive = held;
+
+        let top: TopOfBook = self.capture_top();
+        let fills_before: usize = self.tape.len();
+        let pending_before: usize = self.pending_fills.len();
+        let mut results: Vec<Result<OrderStatus, BookError>> = vec![];
+
+        for order in due.iter().cloned() {
+            info!("Activating {}...", order);
+            results.push(self.place(order).await);
+        }
+
+        if !due.is_empty() {
+            self.journal_submission(&due, fills_before, pending_before);
+            self.publish_top_change(top);
+        }
+
+        results
+    }
+
+    /// Suspends matching on the book
+    ///
+    /// Orders submitted while the book is halted are queued, unmatched, until
+    /// the book is resumed.
+    pub fn halt(&mut self) {
+        info!("Halting book {}", self.market);
+        self.halted = true;
+    }
+
+    /// Halts the book and cancels every order it holds in one step, for
+    /// emergencies
+    ///
+    /// Unlike `Book::halt`, submissions are rejected with
+    /// `BookError::MarketHalted` rather than queued, until the book is
+    /// resumed. Subscribers are sent a single `BookEvent::EmergencyStop`.
+    ///
+    /// Fills held for last look are rejected first, so that their makers are
+    /// cancelled in full. Fills awaiting confirmation of their settlement
+    /// are left to be confirmed, but rolling one back no longer rests its
+    /// maker again.
+    ///
+    /// # Returns #
+    ///
+    /// Returns the cancelled orders for handling off the book: resting orders
+    /// in priority order, bids first, followed by queued, inactive and held
+    /// orders, the hidden reserves of icebergs and the takers of rejected
+    /// fills.
+    pub fn emergency_stop(&mut self) -> Vec<Order> {
+        warn!("Emergency stop of book {}", self.market);
+        self.halted = true;
+        self.emergency_stopped = true;
+
+        let takers: Vec<Order> = std::mem::take(&mut self.pending_fills)
+            .into_iter()
+            .map(|pending| {
+                info!("Rejected {}", pending.fill);
+                self.unwind(&pending.fill)
+            })
+            .collect();
+        for pending in self.awaiting_confirmation.iter_mut() {
+            pending.withdrawn = true;
+        }
+
+        let mut cancelled: Vec<Order> = self
+            .levels(OrderSide::Bid)
+            .chain(self.levels(OrderSide::Ask))
+            .flat_map(|(_, orders)| orders.iter())
+            .filter(|t| !t.remaining.is_zero())
+            .cloned()
+            .collect();
+        self.bids.clear();
+        self.asks.clear();
+        self.volume = (U256::zero(), U256::zero());
+        self.open_orders.clear();
+
+        cancelled.append(&mut self.halt_queue);
+        cancelled.append(&mut self.inactive);
+        cancelled.extend(self.stops.drain(..).map(|t| t.order));
+        cancelled.extend(self.depth_triggered.drain(..).map(|(_, t)| t));
+        cancelled.extend(self.icebergs.drain(..).map(|t| t.order));
+        cancelled.extend(takers.into_iter().filter(|t| !t.remaining.is_zero()));
+
+        for order in cancelled.iter() {
+            self.order_counts.entry(order.trader).or_default().1 += 1;
+        }
+
+        self.update();
+        self.journal(
+            &cancelled
+                .iter()
+                .map(|t| Order {
+                    remaining: U256::zero(),
+                    ..t.clone()
+                })
+                .collect::<Vec<Order>>(),
+        );
+        self.subscribers.publish(BookEvent::EmergencyStop);
+
+        cancelled
+    }
+
+    /// Resumes matching on a halted book, working through the queued orders
+    ///
+    /// If a reopening price is provided, the first `reopening_fills` fills
+    /// after resumption execute at that price (as near as the limits of both
+    /// orders allow) rather than at the maker's price. Any resulting fills
+    /// are queued for settlement.
+    ///
+    /// # Returns #
+    ///
+    /// Returns the outcome of matching each queued order, in order.
+    pub async fn resume(
+        &mut self,
+        reopening_price: Option<U256>,
+    ) -> Vec<Result<OrderStatus, BookError>> {
+        info!("Resuming book {}", self.market);
+        self.halted = false;
+        self.emergency_stopped = false;
+        self.reopening_price = reopening_price;
+        self.reopening_fills_left = match reopening_price {
+            Some(_) => self.reopening_fills,
+            None => 0,
+        };
+
+        let queued: Vec<Order> = std::mem::take(&mut self.halt_queue);
+        let top: TopOfBook = self.capture_top();
+        let fills_before: usize = self.tape.len();
+        let pending_before: usize = self.pending_fills.len();
+        let mut results: Vec<Result<OrderStatus, BookError>> = vec![];
+
+        for order in queued.iter().cloned() {
+            results.push(self.admit(order).await);
+        }
+
+        self.journal_submission(&queued, fills_before, pending_before);
+        self.publish_top_change(top);
+
+        results
+    }
+
+    /// Holds orders that are not yet active, otherwise places them
+    async fn admit(&mut self, order: Order) -> Result<OrderStatus, BookError> {
+        if self.emergency_stopped {
+            warn!("Rejecting {} as the book is stopped", order);
+            return Err(BookError::MarketHalted);
+        }
+
+        if self.halted {
+            self.validate(&order)?;
+            info!("Queueing {} until the book resumes", order);
+            self.halt_queue.push(order);
+            return Ok(OrderStatus::Queued);
+        }
+
+        if order.is_inactive(self.now()) {
+            self.validate(&order)?;
+            info!("Holding {} until activation", order);
+            self.inactive.push(order);
+            return Ok(OrderStatus::Inactive);
+        }
+
+        self.place(order).await
+    }
+
+    /// Validates and matches a single order, without settling its fills
+    async fn place(&mut self, order: Order) -> Result<OrderStatus, BookError> {
+        self.validate(&order)?;
+
+        if self.self_trade_prevention == SelfTradePrevention::Reject
+            && self.would_self_trade(&order)
+        {
+            warn!("Rejecting {} as it would trade with itself", order);
+            return Err(BookError::SelfTrade);
+        }
+
+        if self.narrows_own_spread(&order) {
+            warn!("Rejecting {} as it narrows its trader's spread", order);
+            return Err(BookError::SpreadTooNarrow);
+        }
+
+        if let Some(cap) = self.max_open_orders_per_trader {
+            if self.open_orders.get(&order.trader).copied().unwrap_or(0) >= cap
+            {
+                warn!("Rejecting {} as its trader has {} open", order, cap);
+                return Err(BookError::TooManyOpenOrders);
+            }
+        }
+
+        /* under RFQ, takers may only match by accepting a quote */
+        if self.rfq {
+            let opposing_top: Option<U256> = match order.side {
+                OrderSide::Bid => self.top().1,
+                OrderSide::Ask => self.top().0,
+            };
+            let crosses: bool = match opposing_top {
+                Some(touch) => Book::price_viable(
+                    touch,
+                    order.price,
+                    order.side,
+                    self.price_bucket,
+                ),
+                None => false,
+            };
+
+            if crosses {
+                warn!("Rejecting {} as it would match outside RFQ", order);
+                return Err(BookError::WouldMatch);
+            }
+        }
+
+        let fills_before: usize = self.tape.len();
+        let pending_before: usize = self.pending_fills.len();
+
+        let match_result: Result<OrderStatus, BookError> = match order.side {
+            OrderSide::Bid => self.r#match(order, self.top().1).await,
+            OrderSide::Ask => self.r#match(order, self.top().0).await,
+        };
+
+        self.update();
+
+        let fills: Vec<Fill> = self.tape[fills_before..]
+            .iter()
+            .chain(self.pending_fills[pending_before..].iter().map(|t| &t.fill))
+            .cloned()
+            .collect();
+
+        if let Err(e) = Book::validate_fill_prices(&fills, self.price_bucket) {
+            /* never forward a fill the engine should not have produced */
+            error!("Withholding {} fills from settlement", fills.len());
+            self.settlements
+                .retain(|t| !fills.iter().any(|f| f.id == t.id));
+            self.pending_fills.truncate(pending_before);
+            return Err(e);
+        }
+
+        match_result
+    }
+
+    /// Checks that every provided fill executed within the limit prices of
+    /// both of its counterparties
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(())` if every fill price lies between the seller's limit
+    /// and the buyer's limit.
+    ///
+    /// Returns `BookError::FillPriceInvariant` otherwise
+    pub fn validate_fills(fills: &[Fill]) -> Result<(), BookError> {
+        Book::validate_fill_prices(fills, None)
+    }
+
+    /// Checks that every provided fill executed within the limit prices of
+    /// both of its counterparties, comparing buckets of the provided width
+    /// under approximate matching
+    fn validate_fill_prices(
+        fills: &[Fill],
+        bucket: Option<U256>,
+    ) -> Result<(), BookError> {
+        let bucketed = |price: U256| match bucket {
+            Some(width) if !width.is_zero() => price / width,
+            _ => price,
+        };
+
+        for fill in fills {
+            if bucketed(fill.price) < bucketed(fill.seller().price)
+                || bucketed(fill.price) > bucketed(fill.buyer().price)
+            {
+                error!("{} executed outside of its limit prices", fill);
+                return Err(BookError::FillPriceInvariant);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records the final state of every order touched since the tape and
+    /// pending fills had the provided lengths, including `incoming`
+    fn journal_submission(
+        &mut self,
+        incoming: &[Order],
+        fills_before: usize,
+        pending_before: usize,
+    ) {
+        if self.log_path.is_none() && self.replicas.is_empty() {
+            return;
+        }
+
+        let mut touched: Vec<Order> = self.tape[fills_before..]
+            .iter()
+            .map(|t| t.maker.clone())
+            .chain(
+                self.pending_fills[pending_before..]
+                    .iter()
+                    .map(|t| t.fill.maker.clone()),
+            )
+            .collect();
+
+        for order in incoming {
+            touched.push(
+                match self
+                    .order(order.id)
+                    .or_else(|| self.inactive.iter().find(|t| t.id == order.id))
+                {
+                    Some(t) => t.clone(),
+                    None => Order {
+                        remaining: U256::zero(),
+                        ..order.clone()
+                    },
+                },
+            );
+        }
+
+        self.journal(&touched);
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn add_order(&mut self, order: Order) -> Result<(), BookError> {
+        info!("Adding {}...", order);
+
+        let tmp_order: Order = order.clone();
+        let order_side = order.side;
+        let order_price = order.price;
+        let capacity: usize = self.level_capacity;
+        let orders = || VecDeque::with_capacity(capacity);
+        self.grow_volume(order_side, order.remaining);
+        self.open_order(order.trader);
+
+        match order_side {
+            OrderSide::Bid => {
+                Book::enqueue(
+                    self.bids.entry(order_price).or_insert_with(orders),
+                    order,
+                    &self.priority_boosts,
+                );
+                info!("Added to bid-side");
+            }
+            OrderSide::Ask => {
+                Book::enqueue(
+                    self.asks.entry(order_price).or_insert_with(orders),
+                    order,
+                    &self.priority_boosts,
+                );
+                info!("Added to ask-side");
+            }
+        }
+
+        info!("Added {}", tmp_order);
+
+        Ok(())
+    }
+
+    /// Queues an order at the back of its level, unless its trader's
+    /// priority boost carries it ahead of later-created orders
+    ///
+    /// A boosted order is ranked as though created `boost` earlier than it
+    /// was. Orders of unboosted traders always join the back of the queue.
+    fn enqueue(
+        level: &mut VecDeque<Order>,
+        order: Order,
+        boosts: &HashMap<Address, i64>,
+    ) {
+        let priority = |t: &Order| {
+            t.created
+                - Duration::milliseconds(
+                    boosts.get(&t.trader).copied().unwrap_or(0),
                 )
-                .await;
+        };
+
+        let mut index: usize = level.len();
+
+        if boosts.contains_key(&order.trader) {
+            let own: DateTime<Utc> = priority(&order);
+            while index > 0 && priority(&level[index - 1]) > own {
+                index -= 1;
+            }
+        }
+
+        level.insert(index, order);
+    }
+
+    /*******************HELPER FUNCTIONS FOR TESTING END************************/
+
+    /// Cancels the open order currently in the order book with the matching ID
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(Some(dt))` upon success, where `dt` is a `DateTime<Utc>`
+    /// type representing the time of successful cancellation of the order.
+    ///
+    /// Returns `Ok(None)` if there is no such order currently in the book.
+    ///
+    /// Returns `BookError::OrderBusy` if the order is part of a fill held for
+    /// last look, as rejecting that fill would restore the order. The cancel
+    /// may be retried once the fill has been confirmed or rejected.
+    ///
+    /// Returns a `BookError` if there is an error condition
+    #[allow(unused_variables)] /* TODO: remove when cancel is implemented */
+    pub fn cancel(
+        &mut self,
+        order_id: OrderId,
+    ) -> Result<Option<DateTime<Utc>>, BookError> {
+        if self.is_matching(order_id) {
+            warn!("Refusing to cancel {} while it is matching", order_id);
+            return Err(BookError::OrderBusy);
+        }
+
+        let top: TopOfBook = self.capture_top();
+
+        match self.remove_order(order_id) {
+            Some(order) => {
+                info!("Cancelled {}", order);
+                self.order_counts.entry(order.trader).or_default().1 += 1;
+                self.update();
+                self.journal(&[Order {
+                    remaining: U256::zero(),
+                    ..order
+                }]);
+                self.publish_top_change(top);
+                Ok(Some(self.now()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Requests quotes from every resting maker able to fill the entire
+    /// quantity for a taker on `side`, best first
+    ///
+    /// Requesting quotes withdraws any outstanding from the previous request.
+    /// Quotes are recorded so that they can be accepted with
+    /// `Book::accept_quote`.
+    pub fn request_quotes(
+        &mut self,
+        side: OrderSide,
+        quantity: U256,
+    ) -> Vec<Quote> {
+        self.quote_round += 1;
+
+        let round: u64 = self.quote_round;
+        self.quotes = self
+            .levels(side.opposite())
+            .flat_map(|(_, orders)| orders.iter())
+            .filter(|t| !quantity.is_zero() && t.remaining >= quantity)
+            .map(|t| Quote::new(t, quantity, round))
+            .collect();
+
+        info!(
+            "Issued {} quotes for {} {}",
+            self.quotes.len(),
+            side,
+            quantity
+        );
+        self.quotes.clone()
+    }
+
+    /// Accepts the outstanding quote with the provided ID on behalf of
+    /// `taker`, filling the quoting maker directly
+    ///
+    /// The fill is priced, charged and settled as any other, but against the
+    /// quoting maker alone rather than in priority order. The taker's order
+    /// takes the ID of the accepted quote, expires with the quoting maker and
+    /// is counted as a submission to the book.
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(fill)` upon success, where `fill` is the fill against the
+    /// quoting maker.
+    ///
+    /// Returns `BookError::OrderNotFound` if there is no such outstanding
+    /// quote, `BookError::InsufficientLiquidity` if the maker can no longer
+    /// honour it, `BookError::SelfTrade` if the taker is related to the maker
+    /// or `BookError::MarketHalted` if matching is suspended. Returns
+    /// `BookError::OrderBusy`, leaving the quote outstanding, if the maker is
+    /// part of a fill held for last look or awaiting confirmation.
+    pub fn accept_quote(
+        &mut self,
+        quote_id: QuoteId,
+        taker: Address,
+    ) -> Result<Fill, BookError> {
+        if self.halted || self.emergency_stopped {
+            warn!("Rejecting acceptance of {} while halted", quote_id);
+            return Err(BookError::MarketHalted);
+        }
 
-                running_total -= amount;
+        let index: usize =
+            match self.quotes.iter().position(|t| t.id == quote_id) {
+                Some(t) => t,
+                None => return Err(BookError::OrderNotFound),
+            };
 
-                /* check if we've totally matched our incoming order */
-                if running_total.is_zero() {
-                    info!("Totally matched {}", order);
-                    done = true;
-                    break;
+        if self.is_matching(self.quotes[index].maker) {
+            warn!("Refusing acceptance of {} as its maker is busy", quote_id);
+            return Err(BookError::OrderBusy);
+        }
+        let quote: Quote = self.quotes.remove(index);
+
+        let (maker_trader, expiration): (Address, DateTime<Utc>) =
+            match self.order(quote.maker) {
+                Some(maker)
+                    if maker.price == quote.price
+                        && maker.remaining >= quote.quantity =>
+                {
+                    (maker.trader, maker.expiration)
                 }
+                _ => {
+                    warn!("{} can no longer be honoured", quote_id);
+                    return Err(BookError::InsufficientLiquidity);
+                }
+            };
+
+        if self.related_traders(taker).contains(&maker_trader) {
+            warn!("Rejecting acceptance of {} as a self-trade", quote_id);
+            return Err(BookError::SelfTrade);
+        }
+
+        let top: TopOfBook = self.capture_top();
+        let (bid, ask): (Option<U256>, Option<U256>) = self.top();
+        let now: DateTime<Utc> = self.now();
+        let volume_since: Option<DateTime<Utc>> = self.volume_since();
+
+        let order: Order = Book::fill(
+            Order {
+                id: quote.id,
+                ..Order::new(
+                    taker,
+                    self.market,
+                    quote.side,
+                    quote.price,
+                    quote.quantity,
+                    expiration,
+                    now,
+                    vec![],
+                )
+            },
+            quote.quantity,
+        );
+        let opposite: Order = {
+            let maker: &mut Order = self.order_mut(quote.maker).unwrap();
+            *maker = Book::fill(maker.clone(), quote.quantity);
+            maker.clone()
+        };
+        self.shrink_volume(opposite.side, quote.quantity);
+
+        let fill: Fill = Fill::new(
+            opposite.clone(),
+            order.clone(),
+            quote.price,
+            quote.quantity,
+            self.notional(quote.price, quote.quantity),
+            now,
+        )
+        .with_fees(
+            &Book::tier_schedule(
+                &self.fee_tiers,
+                &self.tape,
+                volume_since,
+                opposite.trader,
+            )
+            .unwrap_or(self.fee_schedule),
+            &Book::tier_schedule(
+                &self.fee_tiers,
+                &self.tape,
+                volume_since,
+                order.trader,
+            )
+            .unwrap_or(self.fee_schedule),
+        )
+        .with_reference(bid, ask);
+        let fill: Fill = match self.incentive {
+            Some(program) => {
+                let rested: Duration = fill.timestamp - opposite.created;
+                let rebate: U256 = program.rebate(fill.notional, rested);
+                fill.with_incentive_rebate(rebate)
+            }
+            None => fill,
+        };
+
+        /* makers with a last look window still get to reject the match */
+        match opposite.last_look {
+            Some(window) => {
+                info!("Holding {} for last look...", fill);
+                self.pending_fills.push(PendingFill {
+                    deadline: fill.timestamp + window,
+                    fill: fill.clone(),
+                    withdrawn: false,
+                });
+            }
+            None => {
+                self.ltp = quote.price;
+                info!("LTP updated, is now {}", self.ltp);
+                self.tape.push(fill.clone());
+                self.settlements.push_back(fill.clone());
             }
         }
 
-        /* if our incoming order has any volume left, add it to the book */
-        if running_total > U256::zero() {
-            self.add_order(order);
-            Ok(OrderStatus::PartialMatch)
-        } else {
-            Ok(OrderStatus::FullMatch)
+        self.sequence += 1;
+        self.order_counts.entry(taker).or_default().0 += 1;
+
+        self.update();
+        self.journal(&[opposite]);
+        self.publish_top_change(top);
+
+        Ok(fill)
+    }
+
+    /// Returns whether the order with the provided ID is part of a fill held
+    /// for last look, or awaiting confirmation of its settlement
+    pub fn is_matching(&self, order_id: OrderId) -> bool {
+        self.pending_fills
+            .iter()
+            .chain(self.awaiting_confirmation.iter())
+            .any(|t| t.fill.maker.id == order_id || t.fill.taker.id == order_id)
+    }
+
+    /// Seeds the book with resting orders approximating the liquidity of
+    /// the provided constant-product curve
+    ///
+    /// Up to `levels` orders are placed on each side of the curve's price,
+    /// owned by the curve's owner. The orders rest without matching, so any
+    /// that would cross the opposing side of the book are left out.
+    pub fn seed_from_amm(&mut self, curve: AmmCurve, levels: usize) {
+        let top: TopOfBook = self.capture_top();
+        let (best_bid, best_ask): (Option<U256>, Option<U256>) = self.top();
+        let mut seeded: Vec<Order> = vec![];
+
+        for side in [OrderSide::Bid, OrderSide::Ask].iter().cloned() {
+            for (price, quantity) in curve.levels(side, levels) {
+                let crosses: bool = match side {
+                    OrderSide::Bid => matches!(best_ask, Some(t) if price >= t),
+                    OrderSide::Ask => matches!(best_bid, Some(t) if price <= t),
+                };
+
+                if crosses {
+                    warn!("Not seeding {} {} as it would cross", side, price);
+                    continue;
+                }
+
+                let order: Order = Order::new(
+                    curve.owner,
+                    self.market,
+                    side,
+                    price,
+                    quantity,
+                    curve.expiration,
+                    self.now(),
+                    vec![],
+                );
+                self.rest(order.clone());
+                seeded.push(order);
+            }
         }
+
+        info!("Seeded {} orders from {:?}", seeded.len(), curve);
+        self.update();
+        self.journal(&seeded);
+        self.publish_top_change(top);
     }
 
-    fn fill(order: Order, amount: U256) -> Order {
-        info!("Filling {} of {}...", amount, order);
-        match amount.cmp(&order.remaining) {
-            Ordering::Greater => order,
-            _ => Order {
-                id: order.id,
-                trader: order.trader,
-                market: order.market,
-                side: order.side,
-                price: order.price,
-                quantity: order.quantity,
-                remaining: order.remaining - amount,
-                expiration: order.expiration,
-                created: order.created,
-                signed_data: order.signed_data,
-            },
+    /// Cancels every order resting at exactly `price` on `side`
+    ///
+    /// Orders that are part of a fill held for last look or awaiting
+    /// confirmation of its settlement are left resting, as `Book::cancel`
+    /// would refuse them. Cancelling an iceberg's slice withdraws its hidden
+    /// reserve too.
+    ///
+    /// # Returns #
+    ///
+    /// Returns the cancelled orders in queue order, which is empty if there
+    /// is no such price level.
+    pub fn cancel_level(&mut self, side: OrderSide, price: U256) -> Vec<Order> {
+        let top: TopOfBook = self.capture_top();
+        let orders: VecDeque<Order> = match side {
+            OrderSide::Bid => self.bids.remove(&price),
+            OrderSide::Ask => self.asks.remove(&price),
+        }
+        .unwrap_or_default();
+
+        let (busy, cancelled): (VecDeque<Order>, VecDeque<Order>) =
+            orders.into_iter().partition(|t| self.is_matching(t.id));
+        if !busy.is_empty() {
+            warn!("Leaving {} busy orders at {} {}", busy.len(), side, price);
+            match side {
+                OrderSide::Bid => self.bids.insert(price, busy),
+                OrderSide::Ask => self.asks.insert(price, busy),
+            };
+        }
+
+        if cancelled.is_empty() {
+            return vec![];
+        }
+        let cancelled: Vec<Order> = cancelled.into_iter().collect();
+        for order in cancelled.iter() {
+            self.shrink_volume(side, order.remaining);
+            self.close_order(order.trader);
+        }
+        self.icebergs.retain(|t| match t.slice {
+            Some(id) => !cancelled.iter().any(|order| order.id == id),
+            None => true,
+        });
+
+        info!("Cancelled {} orders at {} {}", cancelled.len(), side, price);
+        for order in cancelled.iter() {
+            self.order_counts.entry(order.trader).or_default().1 += 1;
         }
+        self.update();
+        self.journal(
+            &cancelled
+                .iter()
+                .map(|t| Order {
+                    remaining: U256::zero(),
+                    ..t.clone()
+                })
+                .collect::<Vec<Order>>(),
+        );
+        self.publish_top_change(top);
+
+        cancelled
     }
 
-    fn prune(&mut self) {
-        for (_price, orders) in self.bids.iter_mut() {
-            orders.retain(|order| !order.remaining.is_zero());
+    /// Reduces the remaining quantity of a resting order by `reduce_by`
+    ///
+    /// The order keeps its priority in the queue. Reducing an order by at
+    /// least its remaining quantity cancels it outright.
+    ///
+    /// Returns `BookError::OrderNotFound` if there is no such order currently
+    /// in the book, or `BookError::OrderBusy` if it is part of a fill held
+    /// for last look or awaiting confirmation, as for `Book::cancel`.
+    pub fn reduce(
+        &mut self,
+        order_id: OrderId,
+        reduce_by: U256,
+    ) -> Result<(), BookError> {
+        if self.is_matching(order_id) {
+            warn!("Refusing to reduce {} while it is matching", order_id);
+            return Err(BookError::OrderBusy);
         }
 
-        for (_price, orders) in self.asks.iter_mut() {
-            orders.retain(|order| !order.remaining.is_zero());
+        let top: TopOfBook = self.capture_top();
+        let order: &mut Order = match self.order_mut(order_id) {
+            Some(t) => t,
+            None => return Err(BookError::OrderNotFound),
+        };
+
+        if reduce_by >= order.remaining {
+            self.cancel(order_id)?;
+            return Ok(());
         }
 
-        self.bids.retain(|_price, orders| !orders.is_empty());
-        self.asks.retain(|_price, orders| !orders.is_empty());
+        order.remaining -= reduce_by;
+        info!("Reduced {} by {}", order, reduce_by);
+
+        let reduced: Order = order.clone();
+        self.shrink_volume(reduced.side, reduce_by);
+        self.update();
+        self.journal(&[reduced]);
+        self.publish_top_change(top);
+        Ok(())
     }
 
-    /// Submits an order to the matching engine
+    /// Confirms a fill currently held for last look by its maker
     ///
-    /// In the event the order cannot be (fully) matched, it will be stored
-    /// in the order book for future matching.
-    pub async fn submit(
+    /// The matched pair is queued for settlement as normal.
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(Some(fill))` upon success, where `fill` is the confirmed
+    /// fill.
+    ///
+    /// Returns `Ok(None)` if there is no such fill currently pending.
+    ///
+    /// Returns a `BookError` if there is an error condition
+    pub async fn confirm_fill(
         &mut self,
-        order: Order,
+        id: FillId,
         executioner_address: String,
-    ) -> Result<OrderStatus, BookError> {
-        info!("Submitting {}...", order);
+    ) -> Result<Option<Fill>, BookError> {
+        let index: usize =
+            match self.pending_fills.iter().position(|t| t.fill.id == id) {
+                Some(t) => t,
+                None => return Ok(None),
+            };
+        let fill: Fill = self.pending_fills.remove(index).fill;
 
-        let match_result: Result<OrderStatus, BookError> = match order.side {
-            OrderSide::Bid => {
-                self.r#match(order, executioner_address, self.top().1).await
+        self.ltp = fill.price;
+        info!("LTP updated, is now {}", self.ltp);
+        self.tape.push(fill.clone());
+
+        info!("Confirmed {}", fill);
+        self.settlements.push_back(fill.clone());
+        self.settle(executioner_address).await;
+
+        Ok(Some(fill))
+    }
+
+    /// Rejects a fill currently held for last look by its maker
+    ///
+    /// The maker has the filled quantity restored at its existing priority.
+    /// The taker is pulled from the book (if any of it is resting) and handed
+    /// back to the caller with the rejected quantity restored, so that the
+    /// book is never left crossed.
+    ///
+    /// # Returns #
+    ///
+    /// Returns `Ok(Some(order))` upon success, where `order` is the taker
+    /// order with the rejected quantity returned to it.
+    ///
+    /// Returns `Ok(None)` if there is no such fill currently pending.
+    ///
+    /// Returns a `BookError` if there is an error condition
+    pub fn reject_fill(
+        &mut self,
+        id: FillId,
+    ) -> Result<Option<Order>, BookError> {
+        let index: usize =
+            match self.pending_fills.iter().position(|t| t.fill.id == id) {
+                Some(t) => t,
+                None => return Ok(None),
+            };
+        let fill: Fill = self.pending_fills.remove(index).fill;
+        let top: TopOfBook = self.capture_top();
+
+        info!("Rejected {}", fill);
+        let taker: Order = self.unwind(&fill);
+        self.publish_top_change(top);
+
+        Ok(Some(taker))
+    }
+
+    /// Undoes the effect of a fill on the resting orders of the book
+    ///
+    /// The maker has the filled quantity restored at its existing priority,
+    /// or at the front of its level if it was exhausted. The taker is pulled
+    /// from the book and returned with the filled quantity restored.
+    fn unwind(&mut self, fill: &Fill) -> Order {
+        /* hand the reserved quantity back to the maker */
+        match self.order_mut(fill.maker.id) {
+            Some(maker) => {
+                maker.remaining += fill.quantity;
+                self.grow_volume(fill.maker.side, fill.quantity);
             }
-            OrderSide::Ask => {
-                self.r#match(order, executioner_address, self.top().0).await
+            None => {
+                /* the maker was exhausted, so restore it to the front */
+                let maker: Order = Order {
+                    remaining: fill.quantity,
+                    ..fill.maker.clone()
+                };
+                let capacity: usize = self.level_capacity;
+                let levels: &mut BTreeMap<U256, VecDeque<Order>> =
+                    match maker.side {
+                        OrderSide::Bid => &mut self.bids,
+                        OrderSide::Ask => &mut self.asks,
+                    };
+                levels
+                    .entry(maker.price)
+                    .or_insert_with(|| VecDeque::with_capacity(capacity))
+                    .push_front(maker);
+                self.grow_volume(fill.maker.side, fill.quantity);
+                self.open_order(fill.maker.trader);
             }
+        }
+
+        /* pull whatever is left of the taker out of the book */
+        let resting: U256 = match self.remove_order(fill.taker.id) {
+            Some(t) => t.remaining,
+            None => U256::zero(),
+        };
+        let taker: Order = Order {
+            remaining: resting + fill.quantity,
+            ..fill.taker.clone()
         };
 
         self.update();
 
-        match_result
+        if let Some(maker) = self.order(fill.maker.id).cloned() {
+            self.journal(&[
+                maker,
+                Order {
+                    remaining: U256::zero(),
+                    ..taker.clone()
+                },
+            ]);
+        }
+
+        taker
     }
 
-    #[allow(clippy::unnecessary_wraps)]
-    fn add_order(&mut self, order: Order) -> Result<(), BookError> {
-        info!("Adding {}...", order);
+    /// Confirms all pending fills whose last look window has elapsed
+    #[allow(unused_must_use)]
+    async fn confirm_elapsed_fills(&mut self, executioner_address: String) {
+        let now: DateTime<Utc> = self.now();
+        let elapsed: Vec<FillId> = self
+            .pending_fills
+            .iter()
+            .filter(|t| t.deadline <= now)
+            .map(|t| t.fill.id)
+            .collect();
 
-        let tmp_order: Order = order.clone();
-        let order_side = order.side;
-        let order_price = order.price;
-        let orders = VecDeque::new();
+        for id in elapsed {
+            info!("Last look window elapsed for {}", id);
+            self.confirm_fill(id, executioner_address.clone()).await;
+        }
+    }
 
-        match order_side {
-            OrderSide::Bid => {
-                self.bids
-                    .entry(order_price)
-                    .or_insert(orders)
-                    .push_back(order);
-                info!("Added to bid-side");
+    /// Returns the number of fills currently awaiting settlement
+    pub fn settlement_backlog(&self) -> usize {
+        self.settlements.len()
+    }
+
+    /// Forwards queued fills to the executioner, oldest first
+    ///
+    /// Forwarding halts at the first fill the executioner cannot be reached
+    /// for, leaving it and every later fill queued for the next attempt.
+    ///
+    /// If the book has a two-phase settlement venue, fills are settled
+    /// through it instead of being forwarded.
+    async fn settle(&mut self, executioner_address: String) {
+        if let Some(settlement) = self.settlement.clone() {
+            self.settle_two_phase(&settlement);
+            return;
+        }
+
+        while let Some(fill) = self.settlements.front().cloned() {
+            info!("Forwarding {} and {}...", fill.taker, fill.maker);
+            match rpc::send_matched_orders(
+                fill.taker,
+                fill.maker,
+                executioner_address.clone(),
+            )
+            .await
+            {
+                Err(RpcError::HttpError) => {
+                    warn!(
+                        "Executioner unreachable, {} fills awaiting settlement",
+                        self.settlements.len()
+                    );
+                    break;
+                }
+                _ => {
+                    let forwarded: Option<Fill> = self.settlements.pop_front();
+
+                    if let (Some(timeout), Some(fill)) =
+                        (self.settlement_timeout, forwarded)
+                    {
+                        self.awaiting_confirmation.push(PendingFill {
+                            deadline: self.now() + timeout,
+                            fill,
+                            withdrawn: false,
+                        });
+                    }
+                }
             }
-            OrderSide::Ask => {
-                self.asks
-                    .entry(order_price)
-                    .or_insert(orders)
-                    .push_back(order);
-                info!("Added to ask-side");
+        }
+    }
+
+    /// Settles queued fills through a two-phase settlement venue, oldest
+    /// first
+    ///
+    /// A fill that fails to settle is rolled back: it is struck from the
+    /// tape, its maker is rested again and whatever is left of its taker is
+    /// cancelled.
+    fn settle_two_phase(&mut self, settlement: &SharedSettlement) {
+        while let Some(fill) = self.settlements.pop_front() {
+            info!("Settling {} in two phases...", fill);
+
+            if let Err(e) = settlement.settle(&fill) {
+                warn!("Rolling back {} after failed settlement: {}", fill, e);
+                let taker: Order = self.roll_back(&fill);
+                warn!("Cancelled {} of taker {}", taker.remaining, taker);
             }
         }
+    }
 
-        info!("Added {}", tmp_order);
+    /// Rolls back an executed fill that failed to settle
+    ///
+    /// The fill is struck from the tape and unwound, returning whatever was
+    /// left of its taker.
+    fn roll_back(&mut self, fill: &Fill) -> Order {
+        let top: TopOfBook = self.capture_top();
 
-        Ok(())
+        self.strike(fill);
+        let taker: Order = self.unwind(fill);
+        self.publish_top_change(top);
+
+        taker
     }
 
-    /*******************HELPER FUNCTIONS FOR TESTING END************************/
+    /// Strikes an executed fill from the tape
+    fn strike(&mut self, fill: &Fill) {
+        self.tape.retain(|t| t.id != fill.id);
+        self.ltp = self.tape.last().map(|t| t.price).unwrap_or_default();
+    }
 
-    /// Cancels the open order currently in the order book with the matching ID
+    /// Marks a forwarded fill as confirmed by the executioner
     ///
     /// # Returns #
     ///
-    /// Returns `Ok(Some(dt))` upon success, where `dt` is a `DateTime<Utc>`
-    /// type representing the time of successful cancellation of the order.
+    /// Returns `Some(fill)` upon success, where `fill` is the confirmed fill.
     ///
-    /// Returns `Ok(None)` if there is no such order currently in the book.
+    /// Returns `None` if no such fill is awaiting confirmation.
+    pub fn confirm_settlement(&mut self, id: FillId) -> Option<Fill> {
+        let index: usize = self
+            .awaiting_confirmation
+            .iter()
+            .position(|t| t.fill.id == id)?;
+
+        info!("Settlement of {} confirmed", id);
+        Some(self.awaiting_confirmation.remove(index).fill)
+    }
+
+    /// Rolls back every forwarded fill whose settlement has not been
+    /// confirmed by `now`
     ///
-    /// Returns a `BookError` if there is an error condition
-    #[allow(unused_variables)] /* TODO: remove when cancel is implemented */
-    pub fn cancel(
+    /// Each maker is rested again at its prior priority. Each taker has what
+    /// is left of it pulled from the book, and is notified through
+    /// `BookEvent::SettlementTimedOut`. Fills whose orders were cancelled by
+    /// `Book::emergency_stop` are only struck from the tape.
+    ///
+    /// # Returns #
+    ///
+    /// Returns the taker of each rolled back fill, with the unsettled
+    /// quantity restored to it.
+    pub fn sweep_settlement_timeouts(
         &mut self,
-        order_id: OrderId,
-    ) -> Result<Option<DateTime<Utc>>, BookError> {
-        for (_, orders) in self.bids.iter_mut() {
-            for (index, order) in orders.iter_mut().enumerate() {
-                if order.id == order_id {
-                    info!("Cancelled {}", order.clone());
-                    orders.remove(index);
-                    return Ok(Some(Utc::now()));
-                }
+        now: DateTime<Utc>,
+    ) -> Vec<Order> {
+        let (lapsed, awaiting): (Vec<PendingFill>, Vec<PendingFill>) =
+            std::mem::take(&mut self.awaiting_confirmation)
+                .into_iter()
+                .partition(|t| t.deadline <= now);
+        self.awaiting_confirmation = awaiting;
+
+        lapsed
+            .into_iter()
+            .map(|pending| {
+                warn!("Settlement of {} timed out", pending.fill);
+                let taker: Order = if pending.withdrawn {
+                    self.strike(&pending.fill);
+                    Order {
+                        remaining: pending.fill.quantity,
+                        ..pending.fill.taker.clone()
+                    }
+                } else {
+                    self.roll_back(&pending.fill)
+                };
+                self.subscribers.publish(BookEvent::SettlementTimedOut {
+                    fill_id: pending.fill.id,
+                    taker_id: taker.id,
+                });
+                taker
+            })
+            .collect()
+    }
+
+    /// Applies an order record from an order log to the book
+    ///
+    /// Orders already in the book are updated in place, preserving their
+    /// priority. New orders join the back of their price level.
+    #[allow(unused_must_use)]
+    fn restore(&mut self, order: Order) {
+        self.inactive.retain(|t| t.id != order.id);
+
+        if order.is_inactive(self.now()) {
+            if !order.remaining.is_zero() {
+                self.inactive.push(order);
             }
+            return;
         }
 
-        for (_, orders) in self.asks.iter_mut() {
-            for (index, order) in orders.iter_mut().enumerate() {
-                if order.id == order_id {
-                    info!("Cancelled {}", order.clone());
-                    orders.remove(index);
-                    return Ok(Some(Utc::now()));
+        let (side, remaining): (OrderSide, U256) =
+            (order.side, order.remaining);
+        match self.order_mut(order.id) {
+            Some(existing) => {
+                let before: U256 = std::mem::replace(existing, order).remaining;
+                self.shrink_volume(side, before);
+                self.grow_volume(side, remaining);
+            }
+            None if !order.remaining.is_zero() => {
+                self.add_order(order);
+            }
+            None => {}
+        }
+    }
+
+    /// Appends the provided order states to the order log, if there is one,
+    /// and replicates them to any standbys
+    ///
+    /// While a transaction is open, the records are held back until it
+    /// commits.
+    fn journal(&mut self, orders: &[Order]) {
+        if let Some(held) = &mut self.held_journal {
+            held.extend_from_slice(orders);
+            return;
+        }
+
+        if let Some(path) = &self.log_path {
+            if let Err(e) = journal::append(path, orders) {
+                warn!("Failed to append to {}: {}", path.display(), e);
+            }
+        }
+
+        for order in orders {
+            self.replicas.publish(ReplicatedOp::from(order));
+        }
+    }
+
+    /// Removes the order matching the provided order ID from the book
+    ///
+    /// Orders awaiting activation, queued during a halt or held for a stop or
+    /// depth trigger are removed too.
+    fn remove_order(&mut self, mut id: OrderId) -> Option<Order> {
+        /* withdrawing an iceberg withdraws its hidden reserve too */
+        if let Some(index) = self
+            .icebergs
+            .iter()
+            .position(|t| t.order.id == id || t.slice == Some(id))
+        {
+            let iceberg: IcebergOrder = self.icebergs.remove(index);
+            match iceberg.slice {
+                Some(slice) if self.displays(slice) => id = slice,
+                _ => return Some(iceberg.order),
+            }
+        }
+
+        let mut removed: Option<Order> = None;
+        for (_, orders) in self.bids.iter_mut().chain(self.asks.iter_mut()) {
+            if let Some(index) = orders.iter().position(|t| t.id == id) {
+                removed = orders.remove(index);
+                break;
+            }
+        }
+        if let Some(order) = removed {
+            self.shrink_volume(order.side, order.remaining);
+            self.close_order(order.trader);
+            return Some(order);
+        }
+
+        for orders in [&mut self.inactive, &mut self.halt_queue].iter_mut() {
+            if let Some(index) = orders.iter().position(|t| t.id == id) {
+                return Some(orders.remove(index));
+            }
+        }
+
+        if let Some(index) = self.stops.iter().position(|t| t.order.id == id) {
+            return Some(self.stops.remove(index).order);
+        }
+
+        if let Some(index) =
+            self.depth_triggered.iter().position(|(_, t)| t.id == id)
+        {
+            return Some(self.depth_triggered.remove(index).1);
+        }
+
+        None
+    }
+
+    /// Checks that the internal state of the book is consistent
+    ///
+    /// Every order must rest on its own side at its own price with some
+    /// quantity remaining, order IDs must be unique, no level may be empty,
+    /// the cached depth must be accurate and the book must not be crossed.
+    pub fn check_invariants(&self) -> Result<(), BookError> {
+        let mut ids: HashSet<OrderId> = HashSet::new();
+
+        for side in [OrderSide::Bid, OrderSide::Ask].iter() {
+            for (price, orders) in self.levels(*side) {
+                if orders.is_empty() {
+                    warn!("Empty {} level at {}", side, price);
+                    return Err(BookError::InvariantViolation);
+                }
+
+                for order in orders {
+                    if order.side != *side
+                        || order.price != *price
+                        || order.remaining.is_zero()
+                        || !ids.insert(order.id)
+                    {
+                        warn!("Misplaced {} at {} {}", order, side, price);
+                        return Err(BookError::InvariantViolation);
+                    }
                 }
             }
         }
 
-        Ok(None)
+        if self.depth != self.depth()
+            || self.volume != self.resting_volume()
+            || self.open_orders != self.open_orders_by_trader()
+            || !self.find_crossing_orders().is_empty()
+        {
+            warn!("Book metadata is inconsistent with its levels");
+            return Err(BookError::InvariantViolation);
+        }
+
+        Ok(())
+    }
+
+    /// Regenerates the internal structure of the book from its orders
+    ///
+    /// Every order is refiled under its own side and price, keeping the
+    /// relative priority of orders at each level. Exhausted orders and
+    /// duplicate IDs are dropped, and cached metadata is recomputed.
+    #[allow(unused_must_use)]
+    pub fn rebuild_index(&mut self) {
+        let orders: Vec<Order> = self
+            .bids
+            .values()
+            .chain(self.asks.values())
+            .flatten()
+            .cloned()
+            .collect();
+        let mut ids: HashSet<OrderId> = HashSet::new();
+
+        self.bids.clear();
+        self.asks.clear();
+
+        for order in orders {
+            if !order.remaining.is_zero() && ids.insert(order.id) {
+                self.add_order(order);
+            }
+        }
+
+        self.recompute_cache();
+        self.update();
+        warn!("Rebuilt book {} with {} orders", self.market, ids.len());
     }
 
     /// Updates internal metadata of the order book
@@ -406,8 +4610,44 @@ impl Book {
     fn update(&mut self) {
         self.prune();
         self.depth = self.depth();
+        self.last_update = ClockReading(Some(self.clock.now()));
+        self.publish_delta();
         info!("Updated book metadata");
     }
+
+    /// Recomputes the metadata cached from the resting orders of the book,
+    /// such as for books restored from state dumps that predate it
+    ///
+    /// Mutations keep the running totals of resting volume and open orders
+    /// up to date themselves, so this is only needed when the levels have
+    /// been changed by other means.
+    pub fn recompute_cache(&mut self) {
+        self.depth = self.depth();
+        self.volume = self.resting_volume();
+        self.open_orders = self.open_orders_by_trader();
+    }
+
+    /// Publishes the change to the aggregated view of the book since the
+    /// last delta, if there is any change and anyone listening
+    ///
+    /// While a transaction is open, deltas are held back until it commits.
+    fn publish_delta(&mut self) {
+        if self.delta_feed.is_empty() || self.held_journal.is_some() {
+            return;
+        }
+
+        let snapshot: BookSnapshot = self.snapshot();
+        let delta = self.published.diff(&snapshot);
+
+        if !delta.is_empty() {
+            self.delta_sequence += 1;
+            self.delta_feed.publish(SequencedDelta {
+                sequence: self.delta_sequence,
+                delta,
+            });
+            self.published = snapshot;
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -1,12 +1,44 @@
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex as StdMutex};
 
-use chrono::{DateTime, NaiveDateTime, Utc};
-use ethereum_types::{Address, U256};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use ethereum_types::{Address, H256, U256};
+use tokio::sync::Mutex;
 
-use crate::book::{Book, BookError, OrderStatus};
-use crate::order::{Order, OrderSide};
+use crate::amm::AmmCurve;
+use crate::book::{
+    Allocation, Book, BookError, DustPolicy, FillGranularity,
+    MarketOrderFallback, OrderStatus, SelfTradePrevention, SkipReason,
+    SubmitReceipt,
+};
+use crate::clock::{Clock, MockClock};
+use crate::event::{BookEvent, ReplicatedOp};
+use crate::fee::{
+    FeeSchedule, FeeTier, IncentiveProgram, SizeDiscount, TieredFeeSchedule,
+};
+use crate::fill::Fill;
+use crate::merkle;
+use crate::order::{
+    order_id, DepthTrigger, Order, OrderId, OrderSide, RequoteFn, SliceJitter,
+};
+use crate::quote::Quote;
+use crate::replay::{self, ReplayOp};
+use crate::revalidation::Revalidator;
+use crate::settlement::{Settlement, SettlementLeg};
+use crate::shutdown::ShutdownState;
+use crate::snapshot::{
+    BookDelta, BookSnapshot, DisplayConfig, L3Snapshot, LevelChange,
+    SequencedDelta,
+};
+use crate::state::OmeState;
+use crate::tick::TickGrid;
 
 pub const TEST_RPC_ADDRESS: &str = "http://localhost:3000";
+pub const BLOCKED_RPC_ADDRESS: &str = "http://127.0.0.1:0";
 
 async fn submit_orders(
     market: Address,
@@ -104,7 +136,7 @@ pub async fn test_simple_buy() {
     );
 
     let submit_res: Result<OrderStatus, BookError> =
-        book.submit(bid, TEST_RPC_ADDRESS.to_string()).await;
+        book.submit(bid.clone(), TEST_RPC_ADDRESS.to_string()).await;
 
     let (bid_length, ask_length) = book.depth();
 
@@ -114,6 +146,17 @@ pub async fn test_simple_buy() {
 
     assert_eq!(bid_length, 5);
     assert_eq!(ask_length, 4);
+
+    /* the incoming bid took trader 4's resting ask */
+    let fill: &Fill = &book.tape()[0];
+    assert_eq!(fill.taker.id, bid.id);
+    assert_eq!(fill.maker.trader, Address::from_low_u64_be(4));
+    assert_eq!(fill.maker_side, OrderSide::Ask);
+    assert!(fill.is_taker_buy());
+
+    /* the touch before the bid arrived */
+    assert_eq!(fill.reference_bid, Some(95.into()));
+    assert_eq!(fill.reference_ask, Some(96.into()));
 }
 
 #[tokio::test]
@@ -230,6 +273,16 @@ pub async fn test_deep_buy() {
     // Ensure the depths are correct
     assert_eq!(bid_length, 5);
     assert_eq!(ask_length, 3);
+
+    /* fills are recorded in execution order, best price first */
+    let actual_fills: Vec<(U256, U256)> =
+        book.tape().iter().map(|t| (t.price, t.quantity)).collect();
+    let expected_fills: Vec<(U256, U256)> = vec![
+        (96.into(), 5.into()),
+        (97.into(), 15.into()),
+        (98.into(), 22.into()),
+    ];
+    assert_eq!(actual_fills, expected_fills);
 }
 
 #[tokio::test]
@@ -455,9 +508,4512 @@ pub async fn test_partial_matching_mutability() {
         asks: BTreeMap::new(),
         ltp: orders[0].price, // trade price is whichever order came first eg make
         depth: (1, 0),
+        volume: (
+            U256::from_dec_str("0800000000000000000").unwrap(),
+            U256::zero(),
+        ),
         crossed: false,
         spread: U256::from_dec_str("0").unwrap(), // todo check how this is calculated
+        tape: actual_book.tape.clone(), // fill timestamps are checked below
+        sequence: 3,
+        order_counts: actual_book.order_counts.clone(), // per trader
+        open_orders: vec![(traders[2], 1)].into_iter().collect(),
+        settlements: actual_book.settlements.clone(), // depends on executioner
+        ..Book::new(market)
     };
 
     assert_eq!(actual_book, expected_book);
+
+    /* both bids lift the same ask, the first in full */
+    let fills: Vec<(OrderId, OrderId, U256, U256)> = actual_book
+        .tape()
+        .iter()
+        .map(|t| (t.maker.id, t.taker.id, t.price, t.quantity))
+        .collect();
+    assert_eq!(
+        fills,
+        vec![
+            (
+                orders[0].id,
+                orders[1].id,
+                orders[0].price,
+                U256::from_dec_str("1000000000000000000").unwrap()
+            ),
+            (
+                orders[0].id,
+                orders[2].id,
+                orders[0].price,
+                U256::from_dec_str("0200000000000000000").unwrap()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+pub async fn test_last_look_rejection_returns_taker_quantity() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+
+    let mut ask: Order = Order::new(
+        Address::from_low_u64_be(1),
+        market,
+        OrderSide::Ask,
+        96.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    ask.last_look = Some(Duration::seconds(60));
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(2),
+        market,
+        OrderSide::Bid,
+        96.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    book.submit(ask.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .unwrap();
+    book.submit(bid.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .unwrap();
+
+    /* the match is held pending the maker's decision */
+    assert_eq!(book.pending_fills.len(), 1);
+    assert_eq!(book.ltp(), U256::zero());
+
+    let fill_id = book.pending_fills[0].fill.id;
+    let returned: Order = book.reject_fill(fill_id).unwrap().unwrap();
+
+    assert_eq!(returned.id, bid.id);
+    assert_eq!(returned.remaining, bid.quantity);
+    assert!(book.pending_fills.is_empty());
+    assert_eq!(book.order(ask.id).unwrap().remaining, ask.quantity);
+    assert_eq!(book.depth(), (0, 1));
+}
+
+#[tokio::test]
+pub async fn test_snapshot_diff_after_cancel() {
+    let mut book = setup().await;
+    let before: BookSnapshot = book.snapshot();
+
+    /* trader 4's ask is the only order at 96 */
+    let price: U256 = 96.into();
+    let id = book.asks.get(&price).unwrap()[0].id;
+    book.cancel(id).unwrap();
+
+    let after: BookSnapshot = book.snapshot();
+    let delta: BookDelta = before.diff(&after);
+
+    assert_eq!(
+        delta.changes,
+        vec![LevelChange::Remove {
+            side: OrderSide::Ask,
+            price,
+        }]
+    );
+
+    let mut applied: BookSnapshot = before.clone();
+    applied.apply(&delta);
+    assert_eq!(applied, after);
+    assert!(after.diff(&book.snapshot()).is_empty());
+}
+
+#[tokio::test]
+pub async fn test_notional_scaled_by_contract_size() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+    book.contract_size = 1000.into();
+
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(4),
+        market,
+        OrderSide::Ask,
+        96.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        market,
+        OrderSide::Bid,
+        96.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    book.submit(ask, TEST_RPC_ADDRESS.to_string())
+        .await
+        .unwrap();
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .unwrap();
+
+    let fills: &[Fill] = book.tape();
+
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills[0].quantity, U256::from(5));
+    assert_eq!(fills[0].notional, U256::from(96 * 5 * 1000));
+    assert_eq!(book.notional(96.into(), 5.into()), U256::from(480000));
+}
+
+#[tokio::test]
+pub async fn test_settlement_backlog_rejects_submit() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+    book.settlement_capacity = Some(1);
+
+    let orders: Vec<Order> = vec![
+        Order::new(
+            Address::from_low_u64_be(1),
+            market,
+            OrderSide::Ask,
+            96.into(),
+            10.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        ),
+        Order::new(
+            Address::from_low_u64_be(2),
+            market,
+            OrderSide::Bid,
+            96.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        ),
+        Order::new(
+            Address::from_low_u64_be(3),
+            market,
+            OrderSide::Bid,
+            96.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        ),
+    ];
+
+    book.submit(orders[0].clone(), BLOCKED_RPC_ADDRESS.to_string())
+        .await
+        .unwrap();
+    book.submit(orders[1].clone(), BLOCKED_RPC_ADDRESS.to_string())
+        .await
+        .unwrap();
+
+    /* the executioner is unreachable, so the fill stays queued */
+    assert_eq!(book.settlement_backlog(), 1);
+
+    let actual_res: Result<OrderStatus, BookError> = book
+        .submit(orders[2].clone(), BLOCKED_RPC_ADDRESS.to_string())
+        .await;
+
+    assert_eq!(actual_res, Err(BookError::SettlementBacklog));
+    assert_eq!(book.settlement_backlog(), 1);
+    assert_eq!(book.order(orders[0].id).unwrap().remaining, 5.into());
+    assert!(book.order(orders[2].id).is_none());
+}
+
+#[tokio::test]
+pub async fn test_find_crossing_orders() {
+    let mut book = setup().await;
+
+    assert!(book.find_crossing_orders().is_empty());
+
+    /* bypass matching to force a bid across the 96 and 97 asks */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        10.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.bids
+        .entry(bid.price)
+        .or_default()
+        .push_back(bid.clone());
+
+    let ask_96 = book.asks.get(&96.into()).unwrap()[0].id;
+    let ask_97 = book.asks.get(&97.into()).unwrap()[0].id;
+
+    assert_eq!(
+        book.find_crossing_orders(),
+        vec![(bid.id, ask_96), (bid.id, ask_97)]
+    );
+}
+
+#[tokio::test]
+pub async fn test_metadata_passes_through_to_fills() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+
+    let mut ask: Order = Order::new(
+        Address::from_low_u64_be(4),
+        market,
+        OrderSide::Ask,
+        96.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    ask.metadata
+        .insert("strategy".to_string(), "maker-1".to_string());
+
+    let mut bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        market,
+        OrderSide::Bid,
+        96.into(),
+        2.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    bid.metadata
+        .insert("route".to_string(), "gateway-a".to_string());
+
+    book.submit(ask.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .unwrap();
+    book.submit(bid.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .unwrap();
+
+    let fill: &Fill = &book.tape()[0];
+
+    assert_eq!(fill.maker.metadata, ask.metadata);
+    assert_eq!(fill.taker.metadata, bid.metadata);
+
+    /* the resting remainder keeps its tags */
+    assert_eq!(book.order(ask.id).unwrap().metadata, ask.metadata);
+}
+
+#[tokio::test]
+pub async fn test_match_step_limit() {
+    let market: Address = Address::zero();
+    let asks: Vec<(Address, OrderSide, u64, u64)> = (0..5)
+        .map(|x| (Address::from_low_u64_be(x), OrderSide::Ask, 96 + x, 1))
+        .collect();
+    let mut book: Book = submit_orders(market, asks).await;
+    book.max_match_steps = Some(2);
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        market,
+        OrderSide::Bid,
+        100.into(),
+        10.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    let receipt: SubmitReceipt = book
+        .submit_with_receipt(bid.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    /* the order traded, so it is accepted with the limit as a signal */
+    assert_eq!(receipt.status, OrderStatus::PartialMatch);
+    assert_eq!(receipt.limit, Some(BookError::MatchLimitReached));
+    assert_eq!(receipt.fills.len(), 2);
+    assert_eq!(receipt.sequence, 6);
+    assert_eq!(book.tape().len(), 2);
+    assert_eq!(book.tape()[0].price, 96.into());
+    assert_eq!(book.tape()[1].price, 97.into());
+    assert_eq!(book.fill_stats(bid.trader).submitted, 1);
+
+    /* the remainder would cross the book, so it is not rested */
+    assert!(book.order(bid.id).is_none());
+    assert_eq!(book.depth(), (0, 3));
+    assert!(!book.crossed());
+
+    /* an order the limit stops before it matches at all still fails */
+    book.max_match_steps = Some(0);
+    let actual_res: Result<OrderStatus, BookError> = book
+        .submit(
+            Order::new(
+                Address::from_low_u64_be(10),
+                market,
+                OrderSide::Bid,
+                100.into(),
+                1.into(),
+                Utc::now(),
+                Utc::now(),
+                vec![],
+            ),
+            TEST_RPC_ADDRESS.to_string(),
+        )
+        .await;
+    assert_eq!(actual_res, Err(BookError::MatchLimitReached));
+    assert_eq!(book.tape().len(), 2);
+}
+
+#[tokio::test]
+pub async fn test_reopen_order_log_via_mmap() {
+    let mut book = setup().await;
+    let path = std::env::temp_dir().join(format!("{}.log", Address::random()));
+
+    book.persist(&path).unwrap();
+
+    let reopened: Book = Book::open_mmap(&path, *book.market()).unwrap();
+    assert_eq!(reopened.checksum(), book.checksum());
+    assert_eq!(reopened.depth(), (5, 5));
+
+    /* later mutations are appended to the log */
+    let id = book.asks.get(&96.into()).unwrap()[0].id;
+    book.cancel(id).unwrap();
+    book.submit(
+        Order::new(
+            Address::from_low_u64_be(10),
+            Address::zero(),
+            OrderSide::Bid,
+            97.into(),
+            20.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        ),
+        TEST_RPC_ADDRESS.to_string(),
+    )
+    .await
+    .unwrap();
+
+    let reopened: Book = Book::open_mmap(&path, *book.market()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_ne!(reopened.checksum(), setup().await.checksum());
+    assert_eq!(reopened.checksum(), book.checksum());
+    assert_eq!(reopened.depth(), book.depth());
+}
+
+#[tokio::test]
+pub async fn test_self_trade_rejection() {
+    let mut book = setup().await;
+    book.self_trade_prevention = SelfTradePrevention::Reject;
+    let checksum = book.checksum();
+
+    /* trader 3 has an ask resting at 97 */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        10.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    let actual_res: Result<OrderStatus, BookError> =
+        book.submit(bid, TEST_RPC_ADDRESS.to_string()).await;
+
+    assert_eq!(actual_res, Err(BookError::SelfTrade));
+    assert_eq!(book.depth(), (5, 5));
+    assert_eq!(book.checksum(), checksum);
+    assert!(book.tape().is_empty());
+
+    /* a bid that fills before reaching its own ask is accepted */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    let actual_res: Result<OrderStatus, BookError> =
+        book.submit(bid, TEST_RPC_ADDRESS.to_string()).await;
+
+    assert_eq!(actual_res, Ok(OrderStatus::FullMatch));
+    assert_eq!(book.depth(), (5, 4));
+}
+
+#[tokio::test]
+pub async fn test_fee_schedule_change_applies_to_later_fills() {
+    let mut book = setup().await;
+    book.set_fee_schedule(FeeSchedule::new(1000, 2000));
+
+    /* queue a second ask behind trader 3's at 97 */
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(11),
+        Address::zero(),
+        OrderSide::Ask,
+        97.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(ask, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        96.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let events = book.subscribe();
+    let checksum = book.checksum();
+    book.set_fee_schedule(FeeSchedule::new(500, 1000));
+
+    assert_eq!(book.checksum(), checksum);
+    assert_eq!(
+        events.try_iter().collect::<Vec<BookEvent>>(),
+        vec![BookEvent::FeeScheduleChanged {
+            old: FeeSchedule::new(1000, 2000),
+            new: FeeSchedule::new(500, 1000),
+        }]
+    );
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        20.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let actual_fees: Vec<(Address, U256, U256)> = book
+        .tape()
+        .iter()
+        .map(|t| (t.maker.trader, t.maker_fee, t.taker_fee))
+        .collect();
+    let expected_fees: Vec<(Address, U256, U256)> = vec![
+        (Address::from_low_u64_be(4), 48.into(), 96.into()),
+        (Address::from_low_u64_be(3), 72.into(), 145.into()),
+        (Address::from_low_u64_be(11), 24.into(), 48.into()),
+    ];
+
+    assert_eq!(actual_fees, expected_fees);
+}
+
+#[tokio::test]
+pub async fn test_l3_snapshot() {
+    let book = setup().await;
+    let snapshot: L3Snapshot = book.l3_snapshot();
+
+    assert_eq!(snapshot.len(), 10);
+
+    let actual_bids: Vec<(Address, U256, U256, usize)> = snapshot
+        .bids
+        .iter()
+        .map(|t| (t.trader, t.price, t.remaining, t.rank))
+        .collect();
+    let expected_bids: Vec<(Address, U256, U256, usize)> = vec![
+        (Address::from_low_u64_be(5), 95.into(), 10.into(), 0),
+        (Address::from_low_u64_be(6), 94.into(), 20.into(), 0),
+        (Address::from_low_u64_be(7), 93.into(), 5.into(), 0),
+        (Address::from_low_u64_be(8), 92.into(), 10.into(), 0),
+        (Address::from_low_u64_be(9), 91.into(), 15.into(), 0),
+    ];
+
+    let actual_asks: Vec<(Address, U256, U256, usize)> = snapshot
+        .asks
+        .iter()
+        .map(|t| (t.trader, t.price, t.remaining, t.rank))
+        .collect();
+    let expected_asks: Vec<(Address, U256, U256, usize)> = vec![
+        (Address::from_low_u64_be(4), 96.into(), 5.into(), 0),
+        (Address::from_low_u64_be(3), 97.into(), 15.into(), 0),
+        (Address::from_low_u64_be(2), 98.into(), 35.into(), 0),
+        (Address::from_low_u64_be(1), 99.into(), 2.into(), 0),
+        (Address::from_low_u64_be(0), 100.into(), 10.into(), 0),
+    ];
+
+    assert_eq!(actual_bids, expected_bids);
+    assert_eq!(actual_asks, expected_asks);
+
+    /* every entry references the order it was taken from */
+    for entry in snapshot.bids.iter().chain(snapshot.asks.iter()) {
+        let order: &Order = book.order(entry.id).unwrap();
+        assert_eq!(order.trader, entry.trader);
+        assert_eq!(order.price, entry.price);
+    }
+}
+
+#[tokio::test]
+pub async fn test_sweep_level_limit() {
+    let mut book = setup().await;
+    book.set_max_sweep_levels(Some(1));
+
+    /* enough to sweep every ask, but only the 96 level may be reached */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        100.into(),
+        50.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    let receipt: SubmitReceipt = book
+        .submit_with_receipt(bid.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    assert_eq!(receipt.status, OrderStatus::PartialMatch);
+    assert_eq!(receipt.limit, Some(BookError::SweepLimited));
+    assert_eq!(receipt.fills.len(), 1);
+    assert_eq!(book.tape().len(), 1);
+    assert_eq!(book.tape()[0].price, 96.into());
+    assert_eq!(book.tape()[0].quantity, 5.into());
+    assert_eq!(book.depth(), (5, 4));
+    assert!(book.order(bid.id).is_none());
+    assert!(!book.crossed());
+}
+
+async fn mock_clock_session() -> Book {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let mut book: Book = Book::new(Address::zero());
+    book.set_clock(clock.clone());
+
+    /* both asks are created at the same instant */
+    for trader in [1, 2].iter() {
+        let ask: Order = Order::new(
+            Address::from_low_u64_be(*trader),
+            Address::zero(),
+            OrderSide::Ask,
+            100.into(),
+            10.into(),
+            clock.now() + Duration::days(1),
+            clock.now(),
+            vec![],
+        );
+        book.submit(ask, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+    }
+
+    clock.advance(Duration::seconds(1));
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        Address::zero(),
+        OrderSide::Bid,
+        100.into(),
+        15.into(),
+        clock.now() + Duration::days(1),
+        clock.now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    book
+}
+
+#[tokio::test]
+pub async fn test_mock_clock_reproducible_tie_breaking() {
+    let first: Book = mock_clock_session().await;
+    let second: Book = mock_clock_session().await;
+
+    /* identical timestamps are broken by arrival order */
+    let makers: Vec<Address> =
+        first.tape().iter().map(|t| t.maker.trader).collect();
+    assert_eq!(
+        makers,
+        vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2)]
+    );
+
+    let expected_timestamp: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_001, 0),
+        Utc,
+    );
+    assert!(first
+        .tape()
+        .iter()
+        .all(|t| t.timestamp == expected_timestamp));
+
+    assert_eq!(first.tape(), second.tape());
+    assert_eq!(first.checksum(), second.checksum());
+}
+
+#[tokio::test]
+pub async fn test_reduce_preserves_priority() {
+    let mut book = setup().await;
+
+    /* queue a second ask behind trader 2's ask of 35 at 98 */
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(11),
+        Address::zero(),
+        OrderSide::Ask,
+        98.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(ask, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let price: U256 = 98.into();
+    let id: OrderId = book.asks.get(&price).unwrap()[0].id;
+
+    assert_eq!(book.reduce(id, 20.into()), Ok(()));
+
+    let level: &VecDeque<Order> = book.asks.get(&price).unwrap();
+    assert_eq!(level[0].id, id);
+    assert_eq!(level[0].remaining, 15.into());
+    assert_eq!(level[1].trader, Address::from_low_u64_be(11));
+
+    /* reducing by at least the remainder cancels the order */
+    assert_eq!(book.reduce(id, 15.into()), Ok(()));
+    assert!(book.order(id).is_none());
+    assert_eq!(book.reduce(id, 1.into()), Err(BookError::OrderNotFound));
+}
+
+#[tokio::test]
+pub async fn test_rebuild_index_heals_desync() {
+    let mut book = setup().await;
+    assert_eq!(book.check_invariants(), Ok(()));
+
+    /* misfile trader 2's ask under the wrong level and corrupt the depth */
+    let ask: Order = book.asks.remove(&98.into()).unwrap()[0].clone();
+    book.asks
+        .get_mut(&U256::from(99))
+        .unwrap()
+        .push_back(ask.clone());
+    book.depth = (0, 0);
+
+    assert_eq!(book.check_invariants(), Err(BookError::InvariantViolation));
+
+    book.rebuild_index();
+
+    assert_eq!(book.check_invariants(), Ok(()));
+    assert_eq!(book.depth(), (5, 5));
+    assert_eq!(book.asks.get(&U256::from(98)).unwrap()[0].id, ask.id);
+}
+
+#[tokio::test]
+pub async fn test_tiered_fees_follow_trader_volume() {
+    let mut book = setup().await;
+    book.set_tiered_fee_schedule(Some(TieredFeeSchedule::new(
+        vec![
+            FeeTier {
+                min_volume: 1000.into(),
+                schedule: FeeSchedule::new(500, 1000),
+            },
+            FeeTier {
+                min_volume: 0.into(),
+                schedule: FeeSchedule::new(1000, 2000),
+            },
+        ],
+        Some(Duration::days(30)),
+    )));
+    let taker: Address = Address::from_low_u64_be(10);
+
+    /* 480 then 970 of notional volume, crossing the 1000 threshold */
+    for (price, quantity) in [(96, 5), (97, 10), (97, 5)].iter() {
+        let bid: Order = Order::new(
+            taker,
+            Address::zero(),
+            OrderSide::Bid,
+            (*price).into(),
+            (*quantity).into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit(bid, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+    }
+
+    let actual_fees: Vec<(U256, U256)> = book
+        .tape()
+        .iter()
+        .map(|t| (t.maker_fee, t.taker_fee))
+        .collect();
+    let expected_fees: Vec<(U256, U256)> = vec![
+        (48.into(), 96.into()),
+        (97.into(), 194.into()),
+        /* trader 3 remains in the base tier as a maker */
+        (48.into(), 48.into()),
+    ];
+
+    assert_eq!(actual_fees, expected_fees);
+    assert_eq!(book.trader_volume(taker), 1935.into());
+    assert_eq!(book.trader_fee_schedule(taker), FeeSchedule::new(500, 1000));
+}
+
+#[tokio::test]
+pub async fn test_imbalance() {
+    let book = setup().await;
+
+    /* 35 bid against 55 ask over the top three levels */
+    let actual_imbalance: f64 = book.imbalance(3).unwrap();
+    let expected_imbalance: f64 = -20.0 / 90.0;
+
+    assert!((actual_imbalance - expected_imbalance).abs() < 1e-12);
+    assert_eq!(book.imbalance(0), None);
+    assert_eq!(Book::new(Address::zero()).imbalance(3), None);
+}
+
+#[tokio::test]
+pub async fn test_transaction_rolls_back_entirely() {
+    let mut book = setup().await;
+    let checksum = book.checksum();
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        96.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    /* targets a different market */
+    let invalid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::from_low_u64_be(1),
+        OrderSide::Ask,
+        101.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    let actual_res: Result<Vec<Vec<Fill>>, BookError> = book
+        .transaction(vec![bid.clone(), invalid], TEST_RPC_ADDRESS.to_string())
+        .await;
+
+    assert_eq!(actual_res, Err(BookError::InvalidOrder));
+    assert_eq!(book.checksum(), checksum);
+    assert!(book.tape().is_empty());
+
+    /* a leg failing during matching undoes the legs before it */
+    book.self_trade_prevention = SelfTradePrevention::Reject;
+    let self_trade: Order = Order::new(
+        Address::from_low_u64_be(3),
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        10.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    let actual_res: Result<Vec<Vec<Fill>>, BookError> = book
+        .transaction(
+            vec![bid.clone(), self_trade],
+            TEST_RPC_ADDRESS.to_string(),
+        )
+        .await;
+
+    assert_eq!(actual_res, Err(BookError::SelfTrade));
+    assert_eq!(book.checksum(), checksum);
+    assert!(book.tape().is_empty());
+    assert!(book.settlements.is_empty());
+
+    /* the valid leg alone goes through */
+    let actual_fills: Vec<Vec<Fill>> = book
+        .transaction(vec![bid], TEST_RPC_ADDRESS.to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(actual_fills.len(), 1);
+    assert_eq!(actual_fills[0].len(), 1);
+    assert_eq!(actual_fills[0][0].price, 96.into());
+    assert_eq!(book.depth(), (5, 4));
+}
+
+#[tokio::test]
+pub async fn test_transaction_triggers_stops() {
+    let mut book = setup().await;
+    let sequence: u64 = book.sequence;
+
+    /* a buy stop placed once the price trades up to 96 */
+    let stop: Order = Order::new(
+        Address::from_low_u64_be(11),
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    assert_eq!(
+        book.submit_stop(stop.clone(), 96.into(), TEST_RPC_ADDRESS.to_string())
+            .await,
+        Ok(OrderStatus::Armed)
+    );
+
+    let legs: Vec<Order> = [(10, 96, 5), (12, 93, 5)]
+        .iter()
+        .map(|(trader, price, quantity)| {
+            Order::new(
+                Address::from_low_u64_be(*trader),
+                Address::zero(),
+                OrderSide::Bid,
+                (*price).into(),
+                (*quantity).into(),
+                Utc::now(),
+                Utc::now(),
+                vec![],
+            )
+        })
+        .collect();
+    book.transaction(legs, TEST_RPC_ADDRESS.to_string())
+        .await
+        .unwrap();
+
+    /* the stop fired off the transaction's fill at 96 and lifted 97 */
+    assert!(book.stops.is_empty());
+    let prices: Vec<U256> = book.tape().iter().map(|t| t.price).collect();
+    assert_eq!(prices, vec![96.into(), 97.into()]);
+    assert_eq!(book.tape()[1].taker.id, stop.id);
+
+    /* both legs count as submissions once the transaction commits */
+    assert_eq!(book.sequence, sequence + 2);
+    assert_eq!(
+        book.order_counts.get(&Address::from_low_u64_be(12)),
+        Some(&(1, 0))
+    );
+}
+
+#[tokio::test]
+pub async fn test_transaction_rollback_keeps_streams() {
+    let mut book = setup().await;
+    book.merge_duplicate_levels = true;
+    book.self_trade_prevention = SelfTradePrevention::Reject;
+    let replica: Receiver<ReplicatedOp> = book.replication_stream();
+    let deltas: Receiver<SequencedDelta> = book.delta_feed();
+    let order = |trader: u64, side: OrderSide, price: u64, quantity: u64| {
+        Order::new(
+            Address::from_low_u64_be(trader),
+            Address::zero(),
+            side,
+            price.into(),
+            quantity.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+
+    /* the first leg merges into a resting bid, the second would self-trade */
+    let actual_res: Result<Vec<Vec<Fill>>, BookError> = book
+        .transaction(
+            vec![
+                order(5, OrderSide::Bid, 95, 5),
+                order(3, OrderSide::Bid, 97, 10),
+            ],
+            TEST_RPC_ADDRESS.to_string(),
+        )
+        .await;
+    assert_eq!(actual_res, Err(BookError::SelfTrade));
+    assert_eq!(book.quantity_at(OrderSide::Bid, 95.into()), 10.into());
+
+    /* nothing from the rolled back legs reached either stream */
+    assert!(replica.try_recv().is_err());
+    assert!(deltas.try_recv().is_err());
+    assert_eq!(book.replicas.len(), 1);
+    assert_eq!(book.delta_feed.len(), 1);
+
+    /* and both streams carry on from where they were */
+    book.transaction(
+        vec![order(5, OrderSide::Bid, 95, 5)],
+        TEST_RPC_ADDRESS.to_string(),
+    )
+    .await
+    .expect("Failed to submit transaction to book");
+
+    match replica.try_recv() {
+        Ok(ReplicatedOp::Restate(order)) => {
+            assert_eq!(order.remaining, 15.into())
+        }
+        other => panic!("Unexpected replicated op {:?}", other),
+    }
+    let delta: SequencedDelta = deltas.try_recv().unwrap();
+    assert_eq!(delta.sequence, 1);
+    assert!(deltas.try_recv().is_err());
+}
+
+#[tokio::test]
+pub async fn test_taker_size_discount_on_eligible_portion() {
+    let market: Address = Address::zero();
+    let mut book: Book = submit_orders(
+        market,
+        vec![(Address::from_low_u64_be(1), OrderSide::Ask, 100, 60)],
+    )
+    .await;
+    book.set_fee_schedule(FeeSchedule::new(0, 1000).with_size_discount(
+        SizeDiscount {
+            threshold: 20.into(),
+            taker_bps: 500,
+            marginal: true,
+        },
+    ));
+
+    for quantity in [42, 10].iter() {
+        let bid: Order = Order::new(
+            Address::from_low_u64_be(2),
+            market,
+            OrderSide::Bid,
+            100.into(),
+            (*quantity).into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit(bid, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+    }
+
+    /* 20 units at 10% and 22 units at 5%, then 10 units below threshold */
+    assert_eq!(book.tape()[0].taker_fee, U256::from(200 + 110));
+    assert_eq!(book.tape()[1].taker_fee, U256::from(100));
+
+    /* without marginal pricing the whole fill is discounted */
+    let schedule: FeeSchedule =
+        FeeSchedule::new(0, 1000).with_size_discount(SizeDiscount {
+            threshold: 20.into(),
+            taker_bps: 500,
+            marginal: false,
+        });
+    assert_eq!(schedule.taker_fee(4200.into(), 42.into()), 210.into());
+}
+
+#[tokio::test]
+pub async fn test_time_in_book() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let mut book: Book = Book::new(Address::zero());
+    book.set_clock(clock.clone());
+
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(1),
+        Address::zero(),
+        OrderSide::Ask,
+        100.into(),
+        10.into(),
+        clock.now() + Duration::days(1),
+        clock.now(),
+        vec![],
+    );
+    book.submit(ask.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    clock.advance(Duration::seconds(90));
+
+    assert_eq!(
+        book.time_in_book(ask.id, book.now()),
+        Some(Duration::seconds(90))
+    );
+    assert_eq!(book.time_in_book(ask.id, start), Some(Duration::zero()));
+    assert_eq!(book.time_in_book(OrderId::zero(), book.now()), None);
+}
+
+#[tokio::test]
+pub async fn test_good_after_time_activation() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let mut book: Book = Book::new(Address::zero());
+    book.set_clock(clock.clone());
+
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(1),
+        Address::zero(),
+        OrderSide::Ask,
+        100.into(),
+        10.into(),
+        start + Duration::days(1),
+        start,
+        vec![],
+    );
+    let mut bid: Order = Order::new(
+        Address::from_low_u64_be(2),
+        Address::zero(),
+        OrderSide::Bid,
+        100.into(),
+        5.into(),
+        start + Duration::days(1),
+        start,
+        vec![],
+    );
+    bid.activate_at = Some(start + Duration::seconds(60));
+
+    book.submit(ask, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    let actual_res: Result<OrderStatus, BookError> =
+        book.submit(bid.clone(), TEST_RPC_ADDRESS.to_string()).await;
+
+    assert_eq!(actual_res, Ok(OrderStatus::Inactive));
+    assert_eq!(book.depth(), (0, 1));
+    assert!(book.order(bid.id).is_none());
+    assert!(book.tape().is_empty());
+
+    clock.advance(Duration::seconds(30));
+    assert!(book.activate_pending(book.now()).await.is_empty());
+    assert!(book.tape().is_empty());
+
+    clock.advance(Duration::seconds(30));
+    assert_eq!(
+        book.activate_pending(book.now()).await,
+        vec![Ok(OrderStatus::FullMatch)]
+    );
+    assert_eq!(book.tape().len(), 1);
+    assert_eq!(book.tape()[0].taker.id, bid.id);
+    assert!(book.inactive.is_empty());
+}
+
+#[tokio::test]
+pub async fn test_spread_change_event_on_cancel() {
+    let mut book = setup().await;
+    let events = book.subscribe();
+
+    /* trader 5's bid is the only order at 95 */
+    let id: OrderId = book.bids.get(&U256::from(95)).unwrap()[0].id;
+    let next: OrderId = book.bids.get(&U256::from(94)).unwrap()[0].id;
+    book.cancel(id).unwrap();
+
+    assert_eq!(
+        events.try_iter().collect::<Vec<BookEvent>>(),
+        vec![
+            BookEvent::SpreadChanged {
+                old: Some(1.into()),
+                new: Some(2.into()),
+            },
+            BookEvent::BecameBest {
+                order_id: next,
+                side: OrderSide::Bid,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+pub async fn test_resume_with_reopening_price() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+    book.reopening_fills = 2;
+    book.halt();
+
+    /* a crossing backlog builds up while halted */
+    let orders: Vec<(u64, OrderSide, u64, u64)> = vec![
+        (1, OrderSide::Ask, 100, 10),
+        (2, OrderSide::Ask, 101, 10),
+        (3, OrderSide::Bid, 105, 30),
+    ];
+    for (trader, side, price, quantity) in orders.iter() {
+        let order: Order = Order::new(
+            Address::from_low_u64_be(*trader),
+            market,
+            *side,
+            (*price).into(),
+            (*quantity).into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        let actual_res: Result<OrderStatus, BookError> =
+            book.submit(order, TEST_RPC_ADDRESS.to_string()).await;
+        assert_eq!(actual_res, Ok(OrderStatus::Queued));
+    }
+    assert_eq!(book.depth(), (0, 0));
+
+    let actual_results: Vec<Result<OrderStatus, BookError>> =
+        book.resume(Some(102.into())).await;
+
+    assert_eq!(
+        actual_results,
+        vec![
+            Ok(OrderStatus::Add),
+            Ok(OrderStatus::Add),
+            Ok(OrderStatus::PartialMatch),
+        ]
+    );
+    let actual_prices: Vec<U256> =
+        book.tape().iter().map(|t| t.price).collect();
+    assert_eq!(actual_prices, vec![U256::from(102), U256::from(102)]);
+    assert_eq!(book.ltp(), 102.into());
+    assert_eq!(book.depth(), (1, 0));
+
+    /* the reopening window has closed, so makers set the price again */
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(4),
+        market,
+        OrderSide::Ask,
+        104.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(ask, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    assert_eq!(book.tape()[2].price, 105.into());
+}
+
+#[tokio::test]
+pub async fn test_next_to_fill() {
+    let book = setup().await;
+
+    let next_ask: &Order = book.next_to_fill(OrderSide::Ask).unwrap();
+    assert_eq!(next_ask.trader, Address::from_low_u64_be(4));
+    assert_eq!(next_ask.price, 96.into());
+
+    let next_bid: &Order = book.next_to_fill(OrderSide::Bid).unwrap();
+    assert_eq!(next_bid.trader, Address::from_low_u64_be(5));
+    assert_eq!(next_bid.price, 95.into());
+
+    assert!(Book::new(Address::zero())
+        .next_to_fill(OrderSide::Ask)
+        .is_none());
+}
+
+#[tokio::test]
+pub async fn test_notional_cap() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+    book.max_notional = Some(1000.into());
+
+    let bid = |quantity: u64| {
+        Order::new(
+            Address::from_low_u64_be(1),
+            market,
+            OrderSide::Bid,
+            100.into(),
+            quantity.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+
+    let actual_res: Result<OrderStatus, BookError> =
+        book.submit(bid(11), TEST_RPC_ADDRESS.to_string()).await;
+    assert_eq!(actual_res, Err(BookError::NotionalTooLarge));
+    assert_eq!(book.depth(), (0, 0));
+
+    let actual_res: Result<OrderStatus, BookError> =
+        book.submit(bid(10), TEST_RPC_ADDRESS.to_string()).await;
+    assert_eq!(actual_res, Ok(OrderStatus::Add));
+    assert_eq!(book.depth(), (1, 0));
+}
+
+#[tokio::test]
+pub async fn test_quantity_at() {
+    let book = setup().await;
+
+    assert_eq!(book.quantity_at(OrderSide::Ask, 96.into()), 5.into());
+    assert_eq!(book.quantity_at(OrderSide::Bid, 94.into()), 20.into());
+
+    /* no orders rest at 96 on the bid-side */
+    assert_eq!(book.quantity_at(OrderSide::Bid, 96.into()), U256::zero());
+}
+
+#[tokio::test]
+pub async fn test_cancel_level() {
+    let market: Address = Address::zero();
+    let asks: Vec<(Address, OrderSide, u64, u64)> = vec![
+        (Address::from_low_u64_be(1), OrderSide::Ask, 96, 5),
+        (Address::from_low_u64_be(2), OrderSide::Ask, 96, 10),
+        (Address::from_low_u64_be(3), OrderSide::Ask, 96, 15),
+        (Address::from_low_u64_be(4), OrderSide::Ask, 97, 20),
+    ];
+    let mut book: Book = submit_orders(market, asks).await;
+
+    let cancelled: Vec<Order> = book.cancel_level(OrderSide::Ask, 96.into());
+
+    let actual_traders: Vec<Address> =
+        cancelled.iter().map(|t| t.trader).collect();
+    assert_eq!(
+        actual_traders,
+        vec![
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(2),
+            Address::from_low_u64_be(3),
+        ]
+    );
+    assert_eq!(book.depth(), (0, 1));
+    assert!(!book.asks.contains_key(&U256::from(96)));
+    assert!(book.cancel_level(OrderSide::Ask, 96.into()).is_empty());
+}
+
+#[tokio::test]
+pub async fn test_cancel_level_withdraws_iceberg() {
+    let mut book: Book = Book::new(Address::zero());
+    let iceberg: Order = Order::new(
+        Address::from_low_u64_be(1),
+        Address::zero(),
+        OrderSide::Ask,
+        100.into(),
+        100.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit_iceberg(iceberg, 10.into(), None, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit iceberg to book");
+
+    assert_eq!(book.cancel_level(OrderSide::Ask, 100.into()).len(), 1);
+
+    /* no fresh slice is cut from the withdrawn reserve */
+    book.refresh_icebergs().await;
+    assert!(book.icebergs.is_empty());
+    assert_eq!(book.depth(), (0, 0));
+}
+
+#[tokio::test]
+pub async fn test_cancel_level_skips_busy_orders() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+
+    let mut held: Order = Order::new(
+        Address::from_low_u64_be(1),
+        market,
+        OrderSide::Ask,
+        100.into(),
+        10.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    held.last_look = Some(Duration::seconds(60));
+    let other: Order = Order::new(
+        Address::from_low_u64_be(2),
+        market,
+        OrderSide::Ask,
+        100.into(),
+        10.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        market,
+        OrderSide::Bid,
+        100.into(),
+        4.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    for order in [held.clone(), other.clone(), bid].iter() {
+        book.submit(order.clone(), TEST_RPC_ADDRESS.to_string())
+            .await
+            .unwrap();
+    }
+    assert_eq!(book.pending_fills.len(), 1);
+
+    /* the held maker can be neither reduced nor cancelled mid-fill */
+    assert_eq!(book.reduce(held.id, 2.into()), Err(BookError::OrderBusy));
+    assert_eq!(book.reduce(held.id, 10.into()), Err(BookError::OrderBusy));
+
+    let cancelled: Vec<Order> = book.cancel_level(OrderSide::Ask, 100.into());
+    let cancelled_ids: Vec<OrderId> = cancelled.iter().map(|t| t.id).collect();
+    assert_eq!(cancelled_ids, vec![other.id]);
+    assert_eq!(book.order(held.id).unwrap().remaining, U256::from(6));
+
+    /* rejecting the fill restores the maker in full */
+    let fill_id = book.pending_fills[0].fill.id;
+    book.reject_fill(fill_id).unwrap();
+    assert_eq!(book.order(held.id).unwrap().remaining, held.quantity);
+    assert_eq!(book.depth(), (0, 1));
+}
+
+#[tokio::test]
+pub async fn test_realized_position() {
+    let mut book = setup().await;
+    let trader: Address = Address::from_low_u64_be(10);
+
+    /* buy 5 from the 96 ask, then sell 2 to the 95 bid */
+    for (side, price, quantity) in
+        [(OrderSide::Bid, 96, 5), (OrderSide::Ask, 95, 2)].iter()
+    {
+        let order: Order = Order::new(
+            trader,
+            Address::zero(),
+            *side,
+            (*price).into(),
+            (*quantity).into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit(order, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+    }
+
+    assert_eq!(book.realized_position(trader), 3);
+    assert_eq!(book.realized_position(Address::from_low_u64_be(4)), -5);
+    assert_eq!(book.realized_position(Address::from_low_u64_be(5)), 2);
+    assert_eq!(book.realized_position(Address::from_low_u64_be(0)), 0);
+}
+
+#[tokio::test]
+pub async fn test_submit_during_transaction_rollback() {
+    let market: Address = Address::zero();
+    let mut book: Book = setup().await;
+    book.self_trade_prevention = SelfTradePrevention::Reject;
+    let checksum = book.checksum();
+
+    let state: Arc<Mutex<OmeState>> = Arc::new(Mutex::new(OmeState::new()));
+    state.lock().await.add_book(book);
+
+    /* the second leg self-trades, forcing the first to be rolled back */
+    let legs: Vec<Order> = vec![
+        Order::new(
+            Address::from_low_u64_be(10),
+            market,
+            OrderSide::Bid,
+            96.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        ),
+        Order::new(
+            Address::from_low_u64_be(3),
+            market,
+            OrderSide::Bid,
+            97.into(),
+            10.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        ),
+    ];
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(11),
+        market,
+        OrderSide::Ask,
+        101.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    let transaction = {
+        let state: Arc<Mutex<OmeState>> = state.clone();
+        tokio::spawn(async move {
+            let mut state = state.lock().await;
+            let book: &mut Book = state.book_mut(market).unwrap();
+            book.transaction(legs, TEST_RPC_ADDRESS.to_string()).await
+        })
+    };
+    let submission = {
+        let state: Arc<Mutex<OmeState>> = state.clone();
+        let ask: Order = ask.clone();
+        tokio::spawn(async move {
+            let mut state = state.lock().await;
+            let book: &mut Book = state.book_mut(market).unwrap();
+            book.submit(ask, TEST_RPC_ADDRESS.to_string()).await
+        })
+    };
+
+    assert_eq!(transaction.await.unwrap(), Err(BookError::SelfTrade));
+    assert_eq!(submission.await.unwrap(), Ok(OrderStatus::Add));
+
+    let state = state.lock().await;
+    let book: &Book = state.book(market).unwrap();
+
+    /* the rollback is complete and the concurrent ask landed exactly once */
+    assert!(book.tape().is_empty());
+    assert_eq!(book.depth(), (5, 6));
+    assert_eq!(book.quantity_at(OrderSide::Ask, 96.into()), 5.into());
+    assert_eq!(book.asks.get(&U256::from(101)).unwrap().len(), 1);
+    assert_eq!(book.asks.get(&U256::from(101)).unwrap()[0].id, ask.id);
+    assert_ne!(book.checksum(), checksum);
+    assert_eq!(book.check_invariants(), Ok(()));
+}
+
+#[tokio::test]
+pub async fn test_fills_for_order() {
+    let market: Address = Address::zero();
+    let book: Book = submit_orders(
+        market,
+        vec![
+            (Address::from_low_u64_be(1), OrderSide::Ask, 100, 10),
+            (Address::from_low_u64_be(2), OrderSide::Bid, 100, 3),
+            (Address::from_low_u64_be(3), OrderSide::Bid, 100, 4),
+        ],
+    )
+    .await;
+    let maker: OrderId = book.asks.get(&U256::from(100)).unwrap()[0].id;
+
+    let fills: Vec<Fill> = book.fills_for_order(maker);
+    let actual_takers: Vec<(Address, U256)> =
+        fills.iter().map(|t| (t.taker.trader, t.quantity)).collect();
+
+    assert_eq!(
+        actual_takers,
+        vec![
+            (Address::from_low_u64_be(2), 3.into()),
+            (Address::from_low_u64_be(3), 4.into()),
+        ]
+    );
+    assert_eq!(book.fills_for_order(fills[0].taker.id).len(), 1);
+    assert!(book.fills_for_order(OrderId::zero()).is_empty());
+}
+
+#[tokio::test]
+pub async fn test_tick_grid_leaves_matching_unchanged() {
+    let market: Address = Address::zero();
+    let mut absolute: Book = Book::new(market);
+    let mut ticked: Book = Book::new(market);
+    ticked.tick_grid = Some(TickGrid::new(96.into(), 1.into()));
+
+    /* a grid only constrains prices, so on-grid orders match as without */
+    let orders: Vec<Order> = [
+        (5, OrderSide::Bid, 95, 10),
+        (6, OrderSide::Bid, 94, 20),
+        (7, OrderSide::Bid, 93, 5),
+        (4, OrderSide::Ask, 96, 5),
+        (3, OrderSide::Ask, 97, 15),
+        (2, OrderSide::Ask, 98, 35),
+        (10, OrderSide::Bid, 99, 42),
+        (11, OrderSide::Ask, 93, 30),
+    ]
+    .iter()
+    .map(|(trader, side, price, quantity)| {
+        Order::new(
+            Address::from_low_u64_be(*trader),
+            market,
+            *side,
+            (*price).into(),
+            (*quantity).into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    })
+    .collect();
+
+    for order in orders {
+        let expected_res: Result<OrderStatus, BookError> = absolute
+            .submit(order.clone(), TEST_RPC_ADDRESS.to_string())
+            .await;
+        let actual_res: Result<OrderStatus, BookError> =
+            ticked.submit(order, TEST_RPC_ADDRESS.to_string()).await;
+        assert_eq!(actual_res, expected_res);
+    }
+
+    let fills = |book: &Book| -> Vec<(OrderId, OrderId, U256, U256)> {
+        book.tape()
+            .iter()
+            .map(|t| (t.maker.id, t.taker.id, t.price, t.quantity))
+            .collect()
+    };
+
+    assert_eq!(fills(&ticked), fills(&absolute));
+    assert_eq!(ticked.checksum(), absolute.checksum());
+    assert_eq!(ticked.top(), (Some(93.into()), Some(98.into())));
+    assert_eq!(ticked.top_ticks(), (Some(-3), Some(2)));
+
+    /* off-grid prices are refused */
+    ticked.tick_grid = Some(TickGrid::new(96.into(), 2.into()));
+    let off_grid: Order = Order::new(
+        Address::from_low_u64_be(12),
+        market,
+        OrderSide::Bid,
+        91.into(),
+        1.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    assert_eq!(
+        ticked.submit(off_grid, TEST_RPC_ADDRESS.to_string()).await,
+        Err(BookError::InvalidOrder)
+    );
+}
+
+/// Spawns a stand-in executioner that accepts every request, returning its
+/// address and a count of the requests it has received
+fn spawn_executioner() -> (String, Arc<AtomicUsize>) {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address: String = format!("http://{}", listener.local_addr().unwrap());
+    let requests: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+    let counter: Arc<AtomicUsize> = requests.clone();
+
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut buffer: [u8; 4096] = [0; 4096];
+            let _ = stream.read(&mut buffer);
+            counter.fetch_add(1, AtomicOrdering::SeqCst);
+            let _ = stream.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            );
+        }
+    });
+
+    (address, requests)
+}
+
+#[tokio::test]
+pub async fn test_graceful_shutdown_retries_settlement() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+
+    for (trader, side) in [(1, OrderSide::Ask), (2, OrderSide::Bid)].iter() {
+        let order: Order = Order::new(
+            Address::from_low_u64_be(*trader),
+            market,
+            *side,
+            96.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit(order, BLOCKED_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+    }
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        market,
+        OrderSide::Bid,
+        95.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(bid.clone(), BLOCKED_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert_eq!(book.settlement_backlog(), 1);
+
+    /* the captured state survives a round trip through storage */
+    let state: ShutdownState = serde_json::from_str(
+        &serde_json::to_string(&book.graceful_shutdown()).unwrap(),
+    )
+    .unwrap();
+    let mut resumed: Book = Book::resume_from(state);
+
+    assert_eq!(resumed.settlement_backlog(), 1);
+    assert_eq!(resumed.tape(), book.tape());
+    assert_eq!(resumed.checksum(), book.checksum());
+    assert_eq!(resumed.order(bid.id), book.order(bid.id));
+
+    let (executioner, requests) = spawn_executioner();
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(4),
+        market,
+        OrderSide::Ask,
+        100.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    resumed
+        .submit(ask, executioner)
+        .await
+        .expect("Failed to submit order to book");
+
+    assert_eq!(resumed.settlement_backlog(), 0);
+    assert_eq!(requests.load(AtomicOrdering::SeqCst), 1);
+}
+
+#[tokio::test]
+pub async fn test_min_self_spread() {
+    let market: Address = Address::zero();
+    let trader: Address = Address::from_low_u64_be(1);
+    let mut book: Book = Book::new(market);
+    book.min_self_spread = Some(1.into());
+
+    let order = |side: OrderSide, price: u64| {
+        Order::new(
+            trader,
+            market,
+            side,
+            price.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+
+    book.submit(order(OrderSide::Ask, 96), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let actual_res: Result<OrderStatus, BookError> = book
+        .submit(order(OrderSide::Bid, 96), TEST_RPC_ADDRESS.to_string())
+        .await;
+    assert_eq!(actual_res, Err(BookError::SpreadTooNarrow));
+    assert_eq!(book.depth(), (0, 1));
+
+    /* a spread of exactly the minimum is allowed */
+    let actual_res: Result<OrderStatus, BookError> = book
+        .submit(order(OrderSide::Bid, 95), TEST_RPC_ADDRESS.to_string())
+        .await;
+    assert_eq!(actual_res, Ok(OrderStatus::Add));
+    assert_eq!(book.depth(), (1, 1));
+}
+
+#[tokio::test]
+pub async fn test_validate_fills() {
+    let market: Address = Address::zero();
+    let order = |trader: u64, side: OrderSide, price: u64| {
+        Order::new(
+            Address::from_low_u64_be(trader),
+            market,
+            side,
+            price.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+    let bid: Order = order(1, OrderSide::Bid, 97);
+    let ask: Order = order(2, OrderSide::Ask, 95);
+    let fill = |price: u64| {
+        Fill::new(
+            ask.clone(),
+            bid.clone(),
+            price.into(),
+            5.into(),
+            (price * 5).into(),
+            Utc::now(),
+        )
+    };
+
+    assert_eq!(Book::validate_fills(&[fill(95), fill(97)]), Ok(()));
+
+    /* executing through either limit violates the invariant */
+    assert_eq!(
+        Book::validate_fills(&[fill(96), fill(98)]),
+        Err(BookError::FillPriceInvariant)
+    );
+    assert_eq!(
+        Book::validate_fills(&[fill(94)]),
+        Err(BookError::FillPriceInvariant)
+    );
+}
+
+#[tokio::test]
+pub async fn test_replication_stream() {
+    let mut primary: Book = setup().await;
+    let mut standby: Book = primary.clone();
+    let ops: Receiver<ReplicatedOp> = primary.replication_stream();
+    let initial: H256 = primary.checksum();
+
+    /* sweeps the 96 level and partially fills the 97 level */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        12.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    primary
+        .submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let resting: OrderId = primary.bids[&U256::from(94)][0].id;
+    primary.reduce(resting, 5.into()).unwrap();
+    let cancelled: OrderId = primary.asks[&U256::from(100)][0].id;
+    primary.cancel(cancelled).unwrap();
+
+    for op in ops.try_iter() {
+        standby.apply_replicated(op);
+    }
+
+    assert_ne!(standby.checksum(), initial);
+    assert_eq!(standby.checksum(), primary.checksum());
+    assert_eq!(standby.depth, primary.depth);
+}
+
+#[tokio::test]
+pub async fn test_stop_cascade() {
+    let market: Address = Address::zero();
+    let bids: Vec<(Address, OrderSide, u64, u64)> = vec![
+        (Address::from_low_u64_be(1), OrderSide::Bid, 95, 5),
+        (Address::from_low_u64_be(2), OrderSide::Bid, 94, 5),
+        (Address::from_low_u64_be(3), OrderSide::Bid, 90, 5),
+    ];
+    let order = |trader: u64, price: u64| {
+        Order::new(
+            Address::from_low_u64_be(trader),
+            market,
+            OrderSide::Ask,
+            price.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+
+    for cap in [2, 1].iter() {
+        let mut book: Book = submit_orders(market, bids.clone()).await;
+        book.max_cascade_iterations = *cap;
+
+        /* each stop sells through the next bid, triggering the next stop */
+        for (trader, trigger) in [(4, 95), (5, 94)].iter() {
+            let actual_res: Result<OrderStatus, BookError> = book
+                .submit_stop(
+                    order(*trader, 1),
+                    (*trigger).into(),
+                    TEST_RPC_ADDRESS.to_string(),
+                )
+                .await;
+            assert_eq!(actual_res, Ok(OrderStatus::Armed));
+        }
+
+        book.submit(order(6, 95), TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+
+        let prices: Vec<U256> = book.tape().iter().map(|t| t.price).collect();
+
+        if *cap == 2 {
+            assert_eq!(prices, vec![95.into(), 94.into(), 90.into()]);
+            assert!(book.stops.is_empty());
+            assert_eq!(book.depth(), (0, 0));
+        } else {
+            /* the second stop stays armed beyond the cap */
+            assert_eq!(prices, vec![95.into(), 94.into()]);
+            assert_eq!(book.stops.len(), 1);
+            assert_eq!(book.depth(), (1, 0));
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn test_total_volume() {
+    let mut book: Book = setup().await;
+
+    assert_eq!(book.total_bid_volume(), 60.into());
+    assert_eq!(book.total_ask_volume(), 67.into());
+
+    let cancelled: OrderId = book.bids[&U256::from(94)][0].id;
+    book.cancel(cancelled).unwrap();
+    let reduced: OrderId = book.asks[&U256::from(98)][0].id;
+    book.reduce(reduced, 30.into()).unwrap();
+
+    assert_eq!(book.total_bid_volume(), 40.into());
+    assert_eq!(book.total_ask_volume(), 37.into());
+    assert_eq!(book.check_invariants(), Ok(()));
+
+    /* sweeps the 96 and 97 asks, resting the other 5 at 97 */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        25.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    book.cancel_level(OrderSide::Ask, 100.into());
+
+    /* the running totals agree with the levels they were kept for */
+    assert_eq!(book.total_bid_volume(), 45.into());
+    assert_eq!(book.total_ask_volume(), 7.into());
+    assert_eq!(book.check_invariants(), Ok(()));
+}
+
+#[tokio::test]
+pub async fn test_submit_receipt() {
+    let mut book: Book = setup().await;
+    let order = |side: OrderSide, price: u64| {
+        Order::new(
+            Address::from_low_u64_be(10),
+            Address::zero(),
+            side,
+            price.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+
+    let resting: Order = order(OrderSide::Bid, 90);
+    let first: SubmitReceipt = book
+        .submit_with_receipt(resting.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    assert_eq!(first.order_id, resting.id);
+    assert_eq!(first.sequence, 11);
+    assert_eq!(first.status, OrderStatus::Add);
+    assert!(first.fills.is_empty());
+
+    let crossing: Order = order(OrderSide::Bid, 96);
+    let second: SubmitReceipt = book
+        .submit_with_receipt(crossing.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    assert_eq!(second.order_id, crossing.id);
+    assert_eq!(second.sequence, first.sequence + 1);
+    assert_eq!(second.status, OrderStatus::FullMatch);
+    assert_eq!(second.fills.len(), 1);
+    assert_eq!(second.fills[0].taker.id, crossing.id);
+}
+
+#[tokio::test]
+pub async fn test_timestamp_skew() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+    book.max_timestamp_skew = Some(Duration::minutes(5));
+
+    let order = |created: DateTime<Utc>| {
+        Order::new(
+            Address::from_low_u64_be(1),
+            market,
+            OrderSide::Bid,
+            95.into(),
+            5.into(),
+            Utc::now() + Duration::days(1),
+            created,
+            vec![],
+        )
+    };
+
+    let actual_res: Result<OrderStatus, BookError> = book
+        .submit(
+            order(Utc::now() + Duration::hours(1)),
+            TEST_RPC_ADDRESS.to_string(),
+        )
+        .await;
+    assert_eq!(actual_res, Err(BookError::TimestampSkew));
+    assert_eq!(book.depth(), (0, 0));
+
+    let actual_res: Result<OrderStatus, BookError> = book
+        .submit(
+            order(Utc::now() + Duration::minutes(1)),
+            TEST_RPC_ADDRESS.to_string(),
+        )
+        .await;
+    assert_eq!(actual_res, Ok(OrderStatus::Add));
+    assert_eq!(book.depth(), (1, 0));
+}
+
+#[tokio::test]
+pub async fn test_min_fill_qty() {
+    let market: Address = Address::zero();
+    let asks: Vec<(Address, OrderSide, u64, u64)> = vec![
+        (Address::from_low_u64_be(1), OrderSide::Ask, 96, 5),
+        (Address::from_low_u64_be(2), OrderSide::Ask, 97, 15),
+    ];
+    let mut book: Book = submit_orders(market, asks).await;
+    let small: OrderId = book.asks[&U256::from(96)][0].id;
+
+    let mut bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        market,
+        OrderSide::Bid,
+        97.into(),
+        10.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    bid.min_fill_qty = Some(10.into());
+
+    let actual_res: Result<OrderStatus, BookError> =
+        book.submit(bid, TEST_RPC_ADDRESS.to_string()).await;
+    assert_eq!(actual_res, Ok(OrderStatus::FullMatch));
+
+    /* the 5-unit maker at the better price was passed over */
+    assert_eq!(book.tape().len(), 1);
+    assert_eq!(book.tape()[0].price, 97.into());
+    assert_eq!(book.tape()[0].quantity, 10.into());
+    assert_eq!(book.order(small).map(|t| t.remaining), Some(5.into()));
+}
+
+#[tokio::test]
+pub async fn test_equals_state() {
+    let mut book: Book = setup().await;
+    let mut other: Book = setup().await;
+
+    assert!(book.equals_state(&other));
+
+    /* the tape is transient, but configuration is not */
+    book.tape.clear();
+    assert!(book.equals_state(&other));
+    other.fee_schedule = FeeSchedule::new(1, 2);
+    assert!(!book.equals_state(&other));
+    book.fee_schedule = other.fee_schedule;
+    assert!(book.equals_state(&other));
+
+    let cancelled: OrderId = other.bids[&U256::from(95)][0].id;
+    other.cancel(cancelled).unwrap();
+    assert!(!book.equals_state(&other));
+}
+
+#[tokio::test]
+pub async fn test_marginal_price() {
+    let book: Book = setup().await;
+
+    let marginal = |side: OrderSide, quantity: u64| {
+        book.marginal_price(side, quantity.into())
+    };
+
+    assert_eq!(marginal(OrderSide::Ask, 5), Some(96.into()));
+    assert_eq!(marginal(OrderSide::Ask, 6), Some(97.into()));
+    assert_eq!(marginal(OrderSide::Ask, 20), Some(97.into()));
+    assert_eq!(marginal(OrderSide::Ask, 21), Some(98.into()));
+    assert_eq!(marginal(OrderSide::Bid, 30), Some(94.into()));
+
+    /* more than the side can supply */
+    assert_eq!(marginal(OrderSide::Ask, 68), None);
+    assert_eq!(marginal(OrderSide::Ask, 0), None);
+}
+
+#[tokio::test]
+pub async fn test_merge_duplicate_levels() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+    book.merge_duplicate_levels = true;
+
+    let bids: Vec<(u64, u64)> = vec![(0, 10), (1, 5), (0, 10)];
+
+    for (trader, quantity) in bids.iter() {
+        let bid: Order = Order::new(
+            Address::from_low_u64_be(*trader),
+            market,
+            OrderSide::Bid,
+            95.into(),
+            (*quantity).into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit(bid, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+    }
+
+    /* the merged order keeps the priority of trader 0's first bid */
+    let level: Vec<(Address, U256)> = book.bids[&U256::from(95)]
+        .iter()
+        .map(|t| (t.trader, t.remaining))
+        .collect();
+    assert_eq!(
+        level,
+        vec![
+            (Address::from_low_u64_be(0), 20.into()),
+            (Address::from_low_u64_be(1), 5.into()),
+        ]
+    );
+    assert_eq!(book.depth(), (2, 0));
+    assert_eq!(book.total_bid_volume(), 25.into());
+}
+
+#[tokio::test]
+pub async fn test_tape_to_csv() {
+    let mut book: Book = setup().await;
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        99.into(),
+        42.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let csv: String = book.tape_to_csv();
+    let mut rows = csv.lines();
+
+    assert_eq!(
+        rows.next(),
+        Some("timestamp,price,quantity,maker,taker,side")
+    );
+
+    let rows: Vec<Vec<&str>> = rows.map(|t| t.split(',').collect()).collect();
+    assert_eq!(rows.len(), book.tape().len());
+
+    for (row, fill) in rows.iter().zip(book.tape()) {
+        assert_eq!(
+            DateTime::parse_from_rfc3339(row[0]).unwrap(),
+            fill.timestamp
+        );
+        assert_eq!(U256::from_dec_str(row[1]).unwrap(), fill.price);
+        assert_eq!(U256::from_dec_str(row[2]).unwrap(), fill.quantity);
+        assert_eq!(
+            row[3].trim_start_matches("0x").parse::<Address>().unwrap(),
+            fill.maker.trader
+        );
+        assert_eq!(row[3].len(), 42);
+        assert_eq!(row[4], "0x000000000000000000000000000000000000000a");
+        assert_eq!(row[5], "Bid");
+    }
+}
+
+#[tokio::test]
+pub async fn test_market_order_protection() {
+    let market: Address = Address::zero();
+    let asks: Vec<(Address, OrderSide, u64, u64)> = vec![
+        (Address::from_low_u64_be(1), OrderSide::Ask, 96, 5),
+        (Address::from_low_u64_be(2), OrderSide::Ask, 97, 5),
+        (Address::from_low_u64_be(3), OrderSide::Ask, 150, 10),
+    ];
+    let market_buy = || {
+        Order::new(
+            Address::from_low_u64_be(4),
+            market,
+            OrderSide::Bid,
+            U256::zero(), /* no explicit limit */
+            20.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+
+    /* 5% above the best ask of 96 protects to 100 */
+    let mut book: Book = submit_orders(market, asks.clone()).await;
+    book.set_market_order_protection(Some(500));
+
+    let actual_res: Result<OrderStatus, BookError> = book
+        .submit_market(market_buy(), TEST_RPC_ADDRESS.to_string())
+        .await;
+    assert_eq!(actual_res, Ok(OrderStatus::PartialMatch));
+
+    let prices: Vec<U256> = book.tape().iter().map(|t| t.price).collect();
+    assert_eq!(prices, vec![96.into(), 97.into()]);
+    assert_eq!(book.quantity_at(OrderSide::Ask, 150.into()), 10.into());
+
+    /* the limited taker is identified by its limit */
+    let taker: &Order = &book.tape()[0].taker;
+    assert_eq!(taker.price, 100.into());
+    assert_eq!(
+        taker.id,
+        order_id(
+            taker.trader,
+            taker.market,
+            taker.side,
+            taker.price,
+            taker.quantity,
+            taker.expiration,
+            taker.created,
+        )
+    );
+
+    /* the remainder does not rest */
+    assert_eq!(book.depth(), (0, 1));
+
+    /* unprotected, the same order sweeps through the gap */
+    let mut book: Book = submit_orders(market, asks).await;
+
+    book.submit_market(market_buy(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert_eq!(book.tape().len(), 3);
+    assert_eq!(book.quantity_at(OrderSide::Ask, 150.into()), 0.into());
+}
+
+#[tokio::test]
+pub async fn test_became_best_event() {
+    let market: Address = Address::zero();
+    let bids: Vec<(Address, OrderSide, u64, u64)> = vec![
+        (Address::from_low_u64_be(1), OrderSide::Bid, 95, 10),
+        (Address::from_low_u64_be(2), OrderSide::Bid, 94, 20),
+    ];
+    let mut book: Book = submit_orders(market, bids).await;
+    let best: OrderId = book.bids[&U256::from(95)][0].id;
+    let second: OrderId = book.bids[&U256::from(94)][0].id;
+    let events: Receiver<BookEvent> = book.subscribe();
+
+    book.cancel(best).unwrap();
+
+    let became_best: Vec<BookEvent> = events
+        .try_iter()
+        .filter(|t| matches!(t, BookEvent::BecameBest { .. }))
+        .collect();
+    assert_eq!(
+        became_best,
+        vec![BookEvent::BecameBest {
+            order_id: second,
+            side: OrderSide::Bid,
+        }]
+    );
+}
+
+#[tokio::test]
+pub async fn test_account_group() {
+    let market: Address = Address::zero();
+    let asks: Vec<(Address, OrderSide, u64, u64)> = vec![
+        (Address::from_low_u64_be(1), OrderSide::Ask, 96, 5),
+        (Address::from_low_u64_be(2), OrderSide::Ask, 97, 5),
+    ];
+    let mut book: Book = submit_orders(market, asks).await;
+    book.set_account_group(vec![
+        Address::from_low_u64_be(0),
+        Address::from_low_u64_be(1),
+    ]);
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(0),
+        market,
+        OrderSide::Bid,
+        97.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let actual_res: Result<OrderStatus, BookError> =
+        book.submit(bid, TEST_RPC_ADDRESS.to_string()).await;
+    assert_eq!(actual_res, Ok(OrderStatus::FullMatch));
+
+    /* trader 1's better-priced ask was skipped */
+    assert_eq!(book.tape().len(), 1);
+    assert_eq!(book.tape()[0].maker.trader, Address::from_low_u64_be(2));
+    assert_eq!(book.quantity_at(OrderSide::Ask, 96.into()), 5.into());
+}
+
+#[tokio::test]
+pub async fn test_best_excluding() {
+    let book: Book = setup().await;
+
+    /* trader 4 holds the only ask at 96 */
+    assert_eq!(
+        book.best_ask_excluding(Address::from_low_u64_be(4)),
+        Some(97.into())
+    );
+    assert_eq!(
+        book.best_ask_excluding(Address::from_low_u64_be(5)),
+        Some(96.into())
+    );
+    assert_eq!(
+        book.best_bid_excluding(Address::from_low_u64_be(5)),
+        Some(94.into())
+    );
+
+    assert_eq!(
+        Book::new(Address::zero()).best_bid_excluding(Address::zero()),
+        None
+    );
+}
+
+#[tokio::test]
+pub async fn test_ladder_display() {
+    let market: Address = Address::zero();
+    let orders: Vec<(Address, OrderSide, u64, u64)> = vec![
+        (Address::from_low_u64_be(1), OrderSide::Ask, 1250, 25),
+        (Address::from_low_u64_be(2), OrderSide::Bid, 95, 20),
+    ];
+    let book: Book = submit_orders(market, orders).await;
+    let config: DisplayConfig = DisplayConfig::new(2, 4);
+
+    assert_eq!(
+        book.snapshot().to_ladder_string(&config),
+        "Ask 1,250.00 25.0000\nBid 95.00 20.0000\n"
+    );
+
+    /* values with implied decimals round half up */
+    let config: DisplayConfig = config.with_base_decimals(3);
+    assert_eq!(config.format_price(1_234_565.into()), "1,234.57");
+    assert_eq!(config.format_price(1_234_564.into()), "1,234.56");
+    assert_eq!(config.format_quantity(5.into()), "0.0050");
+}
+
+#[tokio::test]
+pub async fn test_merkle_proof() {
+    let book: Book = setup().await;
+    let root: H256 = book.merkle_root();
+
+    for price in [91, 96, 100].iter() {
+        let order: &Order = match book.bids.get(&U256::from(*price)) {
+            Some(level) => &level[0],
+            None => &book.asks[&U256::from(*price)][0],
+        };
+        let proof: Vec<H256> = book.merkle_proof(order.id).unwrap();
+
+        assert!(merkle::verify(root, merkle::leaf(order), &proof));
+
+        /* the proof does not hold for any other remaining quantity */
+        let altered: Order = Order {
+            remaining: order.remaining + 1,
+            ..order.clone()
+        };
+        assert!(!merkle::verify(root, merkle::leaf(&altered), &proof));
+    }
+
+    /* an internal node cannot pass for an order made of its children */
+    let first: &Order = &book.bids[&U256::from(91)][0];
+    let proof: Vec<H256> = book.merkle_proof(first.id).unwrap();
+    let (left, right): (H256, H256) = match merkle::leaf(first) {
+        t if t <= proof[0] => (t, proof[0]),
+        t => (proof[0], t),
+    };
+    let forged: Order = Order {
+        id: left,
+        remaining: U256::from_big_endian(right.as_ref()),
+        ..first.clone()
+    };
+    assert!(!merkle::verify(root, merkle::leaf(&forged), &proof[1..]));
+
+    assert_eq!(book.merkle_proof(OrderId::zero()), None);
+    assert_eq!(Book::new(Address::zero()).merkle_root(), H256::zero());
+}
+
+#[tokio::test]
+pub async fn test_cancel_racing_fill() {
+    let market: Address = Address::zero();
+    let mut book: Book = Book::new(market);
+    let mut ask: Order = Order::new(
+        Address::from_low_u64_be(1),
+        market,
+        OrderSide::Ask,
+        96.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    ask.last_look = Some(Duration::seconds(60));
+    book.submit(ask.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let state: Arc<Mutex<OmeState>> = Arc::new(Mutex::new(OmeState::new()));
+    state.lock().await.add_book(book);
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(2),
+        market,
+        OrderSide::Bid,
+        96.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let submission = {
+        let state: Arc<Mutex<OmeState>> = state.clone();
+        tokio::spawn(async move {
+            let mut state = state.lock().await;
+            let book: &mut Book = state.book_mut(market).unwrap();
+            book.submit(bid, TEST_RPC_ADDRESS.to_string()).await
+        })
+    };
+    let cancellation = {
+        let state: Arc<Mutex<OmeState>> = state.clone();
+        let id: OrderId = ask.id;
+        tokio::spawn(async move {
+            let mut state = state.lock().await;
+            let book: &mut Book = state.book_mut(market).unwrap();
+            book.cancel(id)
+        })
+    };
+
+    let submitted: Result<OrderStatus, BookError> = submission.await.unwrap();
+    let cancelled = cancellation.await.unwrap();
+
+    let mut state = state.lock().await;
+    let book: &mut Book = state.book_mut(market).unwrap();
+
+    match cancelled {
+        /* the cancel won, so the bid found nothing to match */
+        Ok(Some(_)) => {
+            assert_eq!(submitted, Ok(OrderStatus::Add));
+            assert!(book.pending_fills.is_empty());
+            assert!(book.order(ask.id).is_none());
+        }
+        /* the fill won, so the cancel must wait for the maker's decision */
+        Err(BookError::OrderBusy) => {
+            assert!(book.is_matching(ask.id));
+
+            let fill_id = book.pending_fills[0].fill.id;
+            book.reject_fill(fill_id).unwrap();
+            assert_eq!(book.order(ask.id).unwrap().remaining, 5.into());
+
+            assert!(book.cancel(ask.id).unwrap().is_some());
+            assert!(book.order(ask.id).is_none());
+        }
+        other => panic!("Unexpected cancellation outcome {:?}", other),
+    }
+
+    assert_eq!(book.check_invariants(), Ok(()));
+}
+
+#[tokio::test]
+pub async fn test_replay_file() {
+    let market: Address = Address::zero();
+    let orders: Vec<Order> = vec![
+        (1, OrderSide::Ask, 96, 5),
+        (2, OrderSide::Ask, 97, 15),
+        (3, OrderSide::Bid, 95, 10),
+        (4, OrderSide::Bid, 97, 8),
+    ]
+    .into_iter()
+    .map(
+        |(trader, side, price, quantity): (u64, OrderSide, u64, u64)| {
+            Order::new(
+                Address::from_low_u64_be(trader),
+                market,
+                side,
+                price.into(),
+                quantity.into(),
+                Utc::now() + Duration::days(1),
+                Utc::now(),
+                vec![],
+            )
+        },
+    )
+    .collect();
+
+    let mut ops: Vec<ReplayOp> = orders
+        .iter()
+        .cloned()
+        .map(|t| ReplayOp::Submit(Box::new(t)))
+        .collect();
+    ops.push(ReplayOp::Cancel(orders[2].id));
+
+    let mut recording: Vec<String> = vec![
+        replay::header(market),
+        "# the bid at 97 sweeps the 96 level".to_string(),
+    ];
+    recording.extend(ops.iter().map(ReplayOp::to_line));
+
+    let path = std::env::temp_dir().join(format!("{}.ops", Address::random()));
+    std::fs::write(&path, recording.join("\n")).unwrap();
+    let replayed: Book = Book::replay_file(&path).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut expected: Book = Book::new(market);
+    for order in orders.iter().cloned() {
+        expected
+            .submit(order, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+    }
+    expected.cancel(orders[2].id).unwrap();
+
+    assert_eq!(replayed.depth(), expected.depth());
+    assert_eq!(replayed.depth(), (0, 1));
+    assert_eq!(replayed.checksum(), expected.checksum());
+    assert_eq!(replayed.tape().len(), 2);
+}
+
+#[tokio::test]
+pub async fn test_best_n_levels() {
+    let mut book: Book = setup().await;
+
+    assert_eq!(
+        book.best_n_levels(OrderSide::Ask, 3),
+        vec![
+            (96.into(), 5.into(), 1),
+            (97.into(), 15.into(), 1),
+            (98.into(), 35.into(), 1),
+        ]
+    );
+
+    /* a second order at the best bid fragments the level */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        95.into(),
+        3.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    assert_eq!(
+        book.best_n_levels(OrderSide::Bid, 2),
+        vec![(95.into(), 13.into(), 2), (94.into(), 20.into(), 1)]
+    );
+    assert_eq!(book.best_n_levels(OrderSide::Bid, 10).len(), 5);
+}
+
+#[tokio::test]
+pub async fn test_depth_trigger() {
+    let market: Address = Address::zero();
+    let ask = |trader: u64, quantity: u64| {
+        Order::new(
+            Address::from_low_u64_be(trader),
+            market,
+            OrderSide::Ask,
+            96.into(),
+            quantity.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+    let mut book: Book = Book::new(market);
+    book.submit(ask(1, 5), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(4),
+        market,
+        OrderSide::Bid,
+        96.into(),
+        20.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let trigger: DepthTrigger = DepthTrigger {
+        side: OrderSide::Ask,
+        price: 96.into(),
+        min_qty: 20.into(),
+    };
+    let actual_res: Result<OrderStatus, BookError> = book
+        .submit_depth_triggered(
+            bid.clone(),
+            trigger,
+            TEST_RPC_ADDRESS.to_string(),
+        )
+        .await;
+    assert_eq!(actual_res, Ok(OrderStatus::Armed));
+
+    /* 15 units at 96 is not yet enough */
+    book.submit(ask(2, 10), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert_eq!(book.depth_triggered.len(), 1);
+    assert!(book.tape().is_empty());
+
+    book.submit(ask(3, 5), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert!(book.depth_triggered.is_empty());
+    assert_eq!(book.tape().len(), 3);
+    assert!(book.tape().iter().all(|t| t.taker.id == bid.id));
+    assert_eq!(book.depth(), (0, 0));
+}
+
+#[tokio::test]
+pub async fn test_incentive_rebate_requires_minimum_rest() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let mut book: Book = Book::new(Address::zero());
+    book.set_clock(clock.clone());
+    book.incentive = Some(IncentiveProgram::new(Duration::seconds(60), 100));
+
+    /* trader 1 rests for 70 seconds before filling, trader 2 for only 40 */
+    for trader in [1, 2].iter() {
+        let ask: Order = Order::new(
+            Address::from_low_u64_be(*trader),
+            Address::zero(),
+            OrderSide::Ask,
+            100.into(),
+            10.into(),
+            clock.now() + Duration::days(1),
+            clock.now(),
+            vec![],
+        );
+        book.submit(ask, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+        clock.advance(Duration::seconds(30));
+    }
+
+    clock.advance(Duration::seconds(10));
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        Address::zero(),
+        OrderSide::Bid,
+        100.into(),
+        20.into(),
+        clock.now() + Duration::days(1),
+        clock.now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let actual_rebates: Vec<(Address, U256)> = book
+        .tape()
+        .iter()
+        .map(|t| (t.maker.trader, t.incentive_rebate))
+        .collect();
+    let expected_rebates: Vec<(Address, U256)> = vec![
+        (Address::from_low_u64_be(1), 10.into()),
+        (Address::from_low_u64_be(2), U256::zero()),
+    ];
+
+    assert_eq!(actual_rebates, expected_rebates);
+}
+
+#[tokio::test]
+pub async fn test_mark_to_market() {
+    let book = setup().await;
+
+    let actual_marks: HashMap<Address, i128> = book.mark_to_market(95.into());
+    let expected_marks: HashMap<Address, i128> = vec![
+        (0, 50),
+        (1, 8),
+        (2, 105),
+        (3, 30),
+        (4, 5),
+        (5, 0),
+        (6, 20),
+        (7, 10),
+        (8, 30),
+        (9, 60),
+    ]
+    .into_iter()
+    .map(|(trader, pnl)| (Address::from_low_u64_be(trader), pnl))
+    .collect();
+
+    assert_eq!(actual_marks, expected_marks);
+
+    /* resting orders on the wrong side of the mark are at a loss */
+    assert_eq!(book.mark_to_market(90.into())[&Address::zero()], 100);
+    assert_eq!(
+        book.mark_to_market(100.into())[&Address::from_low_u64_be(9)],
+        135
+    );
+    assert_eq!(
+        book.mark_to_market(100.into())[&Address::from_low_u64_be(4)],
+        -20
+    );
+}
+
+#[tokio::test]
+pub async fn test_round_robin_allocation() {
+    let mut makers: Vec<Vec<Address>> = vec![];
+
+    for allocation in [Allocation::Fifo, Allocation::RoundRobin].iter() {
+        let mut book: Book = Book::new(Address::zero());
+        book.allocation = *allocation;
+
+        for trader in 1..=3 {
+            let ask: Order = Order::new(
+                Address::from_low_u64_be(trader),
+                Address::zero(),
+                OrderSide::Ask,
+                100.into(),
+                10.into(),
+                Utc::now(),
+                Utc::now(),
+                vec![],
+            );
+            book.submit(ask, TEST_RPC_ADDRESS.to_string())
+                .await
+                .expect("Failed to submit order to book");
+        }
+
+        for taker in 4..=5 {
+            let bid: Order = Order::new(
+                Address::from_low_u64_be(taker),
+                Address::zero(),
+                OrderSide::Bid,
+                100.into(),
+                5.into(),
+                Utc::now(),
+                Utc::now(),
+                vec![],
+            );
+            book.submit(bid, TEST_RPC_ADDRESS.to_string())
+                .await
+                .expect("Failed to submit order to book");
+        }
+
+        makers.push(book.tape().iter().map(|t| t.maker.trader).collect());
+    }
+
+    /* FIFO keeps filling the oldest ask, round-robin moves on to the next */
+    assert_eq!(
+        makers[0],
+        vec![Address::from_low_u64_be(1), Address::from_low_u64_be(1)]
+    );
+    assert_eq!(
+        makers[1],
+        vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2)]
+    );
+}
+
+/// Returns the displayed slice sizes of a jittered iceberg across refreshes
+async fn iceberg_slice_sizes(seed: u64) -> Vec<U256> {
+    let mut book: Book = Book::new(Address::zero());
+    let iceberg: Order = Order::new(
+        Address::from_low_u64_be(1),
+        Address::zero(),
+        OrderSide::Ask,
+        100.into(),
+        100.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let status: OrderStatus = book
+        .submit_iceberg(
+            iceberg,
+            10.into(),
+            Some(SliceJitter::new(2000, seed)),
+            TEST_RPC_ADDRESS.to_string(),
+        )
+        .await
+        .expect("Failed to submit iceberg to book");
+    assert_eq!(status, OrderStatus::Add);
+
+    let mut sizes: Vec<U256> = vec![];
+
+    for taker in 2..7 {
+        let displayed: U256 = book.total_ask_volume();
+        sizes.push(displayed);
+
+        /* take exactly the displayed slice, forcing a refresh */
+        let bid: Order = Order::new(
+            Address::from_low_u64_be(taker),
+            Address::zero(),
+            OrderSide::Bid,
+            100.into(),
+            displayed,
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit(bid, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+    }
+
+    sizes
+}
+
+#[tokio::test]
+pub async fn test_iceberg_refresh_jitter() {
+    let sizes: Vec<U256> = iceberg_slice_sizes(42).await;
+    let expected_sizes: Vec<U256> =
+        vec![10.into(), 11.into(), 10.into(), 9.into(), 9.into()];
+
+    /* the same seed always reproduces the same slices */
+    assert_eq!(sizes, expected_sizes);
+    assert_eq!(iceberg_slice_sizes(42).await, expected_sizes);
+    assert_ne!(iceberg_slice_sizes(7).await, expected_sizes);
+}
+
+#[tokio::test]
+pub async fn test_iceberg_rejects_wide_jitter() {
+    let mut book: Book = Book::new(Address::zero());
+    let iceberg: Order = Order::new(
+        Address::from_low_u64_be(1),
+        Address::zero(),
+        OrderSide::Ask,
+        100.into(),
+        100.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    let result = book
+        .submit_iceberg(
+            iceberg,
+            10.into(),
+            Some(SliceJitter::new(10_001, 42)),
+            TEST_RPC_ADDRESS.to_string(),
+        )
+        .await;
+
+    assert_eq!(result, Err(BookError::InvalidOrder));
+    assert!(book.icebergs.is_empty());
+    assert_eq!(book.depth(), (0, 0));
+}
+
+#[tokio::test]
+pub async fn test_clearing_price_for_volume() {
+    let mut book: Book = Book::new(Address::zero());
+
+    /* restate orders directly so that the book is left crossed */
+    let orders: Vec<(u64, OrderSide, u64, u64)> = vec![
+        (1, OrderSide::Bid, 100, 10),
+        (2, OrderSide::Bid, 99, 5),
+        (3, OrderSide::Bid, 97, 20),
+        (4, OrderSide::Ask, 96, 5),
+        (5, OrderSide::Ask, 98, 10),
+        (6, OrderSide::Ask, 101, 10),
+    ];
+    for (trader, side, price, quantity) in orders {
+        let order: Order = Order::new(
+            Address::from_low_u64_be(trader),
+            Address::zero(),
+            side,
+            price.into(),
+            quantity.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.apply_replicated(ReplicatedOp::Restate(Box::new(order)));
+    }
+
+    /* 15 crosses at both 98 and 99 without imbalance, so 98 is chosen */
+    assert_eq!(book.clearing_price_for_volume(10.into()), Some(98.into()));
+    assert_eq!(book.clearing_price_for_volume(15.into()), Some(98.into()));
+    assert_eq!(book.clearing_price_for_volume(16.into()), None);
+
+    /* nothing crosses in an uncrossed book */
+    let uncrossed = setup().await;
+    assert_eq!(uncrossed.clearing_price_for_volume(U256::zero()), None);
+}
+
+#[tokio::test]
+pub async fn test_post_only_bid_at_touch() {
+    let mut book = setup().await;
+    let post_only_bid = |price: u64| {
+        Order::new(
+            Address::from_low_u64_be(10),
+            Address::zero(),
+            OrderSide::Bid,
+            price.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+
+    /* exactly at the best ask of 96 would match */
+    let at_touch = book
+        .submit_post_only(post_only_bid(96), TEST_RPC_ADDRESS.to_string())
+        .await;
+    assert_eq!(at_touch, Err(BookError::WouldMatch));
+    assert!(book.tape().is_empty());
+
+    /* one tick short of the touch rests */
+    let below_touch = book
+        .submit_post_only(post_only_bid(95), TEST_RPC_ADDRESS.to_string())
+        .await;
+    assert_eq!(below_touch, Ok(OrderStatus::Add));
+    assert_eq!(book.quantity_at(OrderSide::Bid, 95.into()), 15.into());
+}
+
+#[tokio::test]
+pub async fn test_post_only_ask_at_touch() {
+    let mut book = setup().await;
+    let post_only_ask = |price: u64| {
+        Order::new(
+            Address::from_low_u64_be(10),
+            Address::zero(),
+            OrderSide::Ask,
+            price.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+
+    /* exactly at the best bid of 95 would match */
+    let at_touch = book
+        .submit_post_only(post_only_ask(95), TEST_RPC_ADDRESS.to_string())
+        .await;
+    assert_eq!(at_touch, Err(BookError::WouldMatch));
+    assert!(book.tape().is_empty());
+
+    /* one tick short of the touch rests */
+    let above_touch = book
+        .submit_post_only(post_only_ask(96), TEST_RPC_ADDRESS.to_string())
+        .await;
+    assert_eq!(above_touch, Ok(OrderStatus::Add));
+    assert_eq!(book.quantity_at(OrderSide::Ask, 96.into()), 10.into());
+}
+
+#[tokio::test]
+pub async fn test_order_count_and_is_empty() {
+    let book = setup().await;
+
+    assert_eq!(book.order_count(), 10);
+    assert!(!book.is_empty());
+
+    let fresh: Book = Book::new(Address::zero());
+
+    assert_eq!(fresh.order_count(), 0);
+    assert!(fresh.is_empty());
+}
+
+/// Settlement venue that fails to prepare the legs it is told to
+struct MockSettlement {
+    failing: SettlementLeg,
+    calls: StdMutex<Vec<String>>,
+}
+
+impl Settlement for MockSettlement {
+    fn prepare(
+        &self,
+        fill: &Fill,
+        leg: SettlementLeg,
+    ) -> Result<(), BookError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("prepare {} {:?}", leg, fill.maker.trader));
+
+        if leg == self.failing {
+            return Err(BookError::Web3Error);
+        }
+
+        Ok(())
+    }
+
+    fn commit(&self, fill: &Fill) -> Result<(), BookError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("commit {:?}", fill.maker.trader));
+        Ok(())
+    }
+
+    fn abort(&self, fill: &Fill) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("abort {:?}", fill.maker.trader));
+    }
+}
+
+#[tokio::test]
+pub async fn test_two_phase_settlement_rolls_back_failed_taker_leg() {
+    let mut book = setup().await;
+    let settlement: Arc<MockSettlement> = Arc::new(MockSettlement {
+        failing: SettlementLeg::Taker,
+        calls: StdMutex::new(vec![]),
+    });
+    book.set_settlement(settlement.clone());
+    let maker_id: OrderId = book.next_to_fill(OrderSide::Ask).unwrap().id;
+
+    /* takes trader 4's entire ask of 5 at 96 */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        96.into(),
+        8.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let bid_id: OrderId = bid.id;
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let maker: Address = Address::from_low_u64_be(4);
+    assert_eq!(
+        *settlement.calls.lock().unwrap(),
+        vec![
+            format!("prepare Maker {:?}", maker),
+            format!("prepare Taker {:?}", maker),
+            format!("abort {:?}", maker),
+        ]
+    );
+
+    /* the match is undone, with the maker back at the front of its level */
+    assert!(book.tape().is_empty());
+    assert!(book.settlements.is_empty());
+    assert_eq!(book.next_to_fill(OrderSide::Ask).unwrap().id, maker_id);
+    assert_eq!(book.quantity_at(OrderSide::Ask, 96.into()), 5.into());
+    assert!(book.order(bid_id).is_none());
+    assert_eq!(book.top(), (Some(95.into()), Some(96.into())));
+}
+
+#[tokio::test]
+pub async fn test_priority_boost() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let mut book: Book = Book::new(Address::zero());
+    book.set_clock(clock.clone());
+
+    let boosted: Address = Address::from_low_u64_be(2);
+    book.set_priority_boost(boosted, Duration::seconds(5));
+
+    /* the boosted ask arrives a second after the unboosted one */
+    for trader in [1, 2].iter() {
+        let ask: Order = Order::new(
+            Address::from_low_u64_be(*trader),
+            Address::zero(),
+            OrderSide::Ask,
+            100.into(),
+            10.into(),
+            clock.now() + Duration::days(1),
+            clock.now(),
+            vec![],
+        );
+        book.submit(ask, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+        clock.advance(Duration::seconds(1));
+    }
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        Address::zero(),
+        OrderSide::Bid,
+        100.into(),
+        10.into(),
+        clock.now() + Duration::days(1),
+        clock.now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let makers: Vec<Address> =
+        book.tape().iter().map(|t| t.maker.trader).collect();
+    assert_eq!(makers, vec![boosted]);
+    assert_eq!(
+        book.next_to_fill(OrderSide::Ask).unwrap().trader,
+        Address::from_low_u64_be(1)
+    );
+
+    /* boosts are keyed by address and so must serialise as a map */
+    assert!(serde_json::to_string(&book).is_ok());
+}
+
+#[tokio::test]
+pub async fn test_trade_velocity() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let mut book = setup().await;
+    book.set_clock(clock.clone());
+
+    /* one fill against the best ask every ten seconds */
+    for _ in 0..5 {
+        let bid: Order = Order::new(
+            Address::from_low_u64_be(10),
+            Address::zero(),
+            OrderSide::Bid,
+            100.into(),
+            1.into(),
+            clock.now() + Duration::days(1),
+            clock.now(),
+            vec![],
+        );
+        book.submit(bid, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+        clock.advance(Duration::seconds(10));
+    }
+
+    /* fills at 0s, 10s, 20s, 30s and 40s, measured at 50s */
+    let now: DateTime<Utc> = clock.now();
+    assert_eq!(book.trade_velocity(Duration::seconds(5), now), 0);
+    assert_eq!(book.trade_velocity(Duration::seconds(30), now), 3);
+    assert_eq!(book.trade_velocity(Duration::seconds(50), now), 5);
+    assert_eq!(book.trade_velocity(Duration::seconds(30), start), 1);
+}
+
+#[tokio::test]
+pub async fn test_ttl_requote() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let mut book: Book = Book::new(Address::zero());
+    book.set_clock(clock.clone());
+
+    /* re-quote whatever is left one tick lower, without a TTL */
+    let requote_clock: Arc<MockClock> = clock.clone();
+    let mut quote: Order = Order::new(
+        Address::from_low_u64_be(1),
+        Address::zero(),
+        OrderSide::Bid,
+        95.into(),
+        10.into(),
+        clock.now() + Duration::days(1),
+        clock.now(),
+        vec![],
+    );
+    quote.ttl = Some(Duration::seconds(10));
+    quote.on_expire = Some(RequoteFn(Arc::new(move |order: &Order| {
+        Some(Order::new(
+            order.trader,
+            order.market,
+            order.side,
+            order.price - 1,
+            order.remaining,
+            order.expiration,
+            requote_clock.now(),
+            vec![],
+        ))
+    })));
+    let quote_id: OrderId = quote.id;
+    book.submit(quote, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let ask = |clock: &MockClock| {
+        Order::new(
+            Address::from_low_u64_be(2),
+            Address::zero(),
+            OrderSide::Ask,
+            100.into(),
+            1.into(),
+            clock.now() + Duration::days(1),
+            clock.now(),
+            vec![],
+        )
+    };
+
+    /* still within its TTL */
+    clock.advance(Duration::seconds(5));
+    book.submit(ask(&clock), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert!(book.order(quote_id).is_some());
+
+    /* the next submission finds the TTL lapsed */
+    clock.advance(Duration::seconds(5));
+    book.submit(ask(&clock), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    assert!(book.order(quote_id).is_none());
+    assert_eq!(book.top(), (Some(94.into()), Some(100.into())));
+    assert_eq!(book.quantity_at(OrderSide::Bid, 94.into()), 10.into());
+    assert_eq!(book.order_count(), 3);
+    assert!(book.expire_orders(clock.now()).await.is_empty());
+}
+
+#[tokio::test]
+pub async fn test_microprice() {
+    let book = setup().await;
+
+    /* (95 * 5 + 96 * 10) / 15 = 95.67, leaning towards the thinner ask */
+    assert_eq!(book.microprice(), Some(95.into()));
+
+    /* a thin bid pulls the microprice down towards it */
+    let wide: Book = submit_orders(
+        Address::zero(),
+        vec![
+            (Address::from_low_u64_be(1), OrderSide::Bid, 100, 1),
+            (Address::from_low_u64_be(2), OrderSide::Ask, 200, 3),
+        ],
+    )
+    .await;
+    assert_eq!(wide.microprice(), Some(125.into()));
+
+    assert_eq!(Book::new(Address::zero()).microprice(), None);
+}
+
+#[tokio::test]
+pub async fn test_dust_policy() {
+    for policy in [DustPolicy::DropRemainder, DustPolicy::RoundLastFill].iter()
+    {
+        let mut book: Book = submit_orders(
+            Address::zero(),
+            vec![
+                (Address::from_low_u64_be(1), OrderSide::Ask, 100, 10),
+                (Address::from_low_u64_be(2), OrderSide::Ask, 100, 10),
+            ],
+        )
+        .await;
+        book.lot_size = Some(5.into());
+        book.dust_policy = *policy;
+
+        /* 10 fills against the first ask, leaving 2 which is under a lot */
+        let bid: Order = Order::new(
+            Address::from_low_u64_be(3),
+            Address::zero(),
+            OrderSide::Bid,
+            100.into(),
+            12.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        let bid_id: OrderId = bid.id;
+        let status: OrderStatus = book
+            .submit(bid, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+
+        let quantities: Vec<U256> =
+            book.tape().iter().map(|t| t.quantity).collect();
+        assert!(book.order(bid_id).is_none());
+
+        match policy {
+            DustPolicy::DropRemainder => {
+                assert_eq!(status, OrderStatus::PartialMatch);
+                assert_eq!(quantities, vec![U256::from(10)]);
+                assert_eq!(book.total_ask_volume(), 10.into());
+            }
+            DustPolicy::RoundLastFill => {
+                assert_eq!(status, OrderStatus::FullMatch);
+                assert_eq!(quantities, vec![U256::from(10), U256::from(5)]);
+                assert_eq!(book.total_ask_volume(), 5.into());
+
+                /* the maker gives up exactly what the taker is filled */
+                let last: &Fill = &book.tape()[1];
+                assert_eq!(last.maker.remaining, 5.into());
+                assert_eq!(
+                    U256::from(10) - last.maker.remaining,
+                    last.quantity
+                );
+                assert_eq!(last.taker.quantity, 15.into());
+                assert!(last.taker.remaining.is_zero());
+            }
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn test_with_capacity_matches_new() {
+    let mut preallocated: Book = Book::with_capacity(Address::zero(), 64);
+    let mut plain: Book = Book::new(Address::zero());
+
+    let orders: Vec<(u64, OrderSide, u64, u64)> = vec![
+        (1, OrderSide::Ask, 100, 10),
+        (2, OrderSide::Ask, 99, 5),
+        (3, OrderSide::Bid, 95, 20),
+        (4, OrderSide::Bid, 100, 12),
+    ];
+    for (trader, side, price, quantity) in orders {
+        let order: Order = Order::new(
+            Address::from_low_u64_be(trader),
+            Address::zero(),
+            side,
+            price.into(),
+            quantity.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+
+        for book in [&mut preallocated, &mut plain].iter_mut() {
+            book.submit(order.clone(), TEST_RPC_ADDRESS.to_string())
+                .await
+                .expect("Failed to submit order to book");
+        }
+    }
+
+    assert_eq!(preallocated.checksum(), plain.checksum());
+    assert_eq!(preallocated.depth(), plain.depth());
+    assert_eq!(preallocated.top(), plain.top());
+
+    /* each level opened reserved its queue up front */
+    for level in preallocated.bids.values().chain(preallocated.asks.values()) {
+        assert!(level.capacity() >= 64);
+    }
+    assert_eq!(preallocated.clone_empty().level_capacity, 64);
+}
+
+#[tokio::test]
+pub async fn test_traders() {
+    let mut book = setup().await;
+
+    /* every setup trader rests one order */
+    let expected_traders: Vec<Address> =
+        (0..10).map(Address::from_low_u64_be).collect();
+    assert_eq!(book.traders(), expected_traders);
+
+    /* trader 4's only order is filled away */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(5),
+        Address::zero(),
+        OrderSide::Bid,
+        96.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let expected_traders: Vec<Address> = (0..10)
+        .filter(|x| *x != 4)
+        .map(Address::from_low_u64_be)
+        .collect();
+    assert_eq!(book.traders(), expected_traders);
+    assert!(Book::new(Address::zero()).traders().is_empty());
+}
+
+#[tokio::test]
+pub async fn test_settlement_timeout_rerests_maker() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let (executioner, requests) = spawn_executioner();
+    let mut book = setup().await;
+    book.set_clock(clock.clone());
+    book.settlement_timeout = Some(Duration::seconds(30));
+    let maker_id: OrderId = book.next_to_fill(OrderSide::Ask).unwrap().id;
+
+    /* takes trader 4's entire ask of 5 at 96 */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        96.into(),
+        5.into(),
+        clock.now() + Duration::days(1),
+        clock.now(),
+        vec![],
+    );
+    let bid_id: OrderId = bid.id;
+    book.submit(bid, executioner)
+        .await
+        .expect("Failed to submit order to book");
+
+    assert_eq!(requests.load(AtomicOrdering::SeqCst), 1);
+    assert_eq!(book.awaiting_confirmation.len(), 1);
+    assert_eq!(book.cancel(maker_id), Err(BookError::OrderBusy));
+
+    /* the executioner never confirms */
+    let events: Receiver<BookEvent> = book.subscribe();
+    clock.advance(Duration::seconds(29));
+    assert!(book.sweep_settlement_timeouts(clock.now()).is_empty());
+    clock.advance(Duration::seconds(1));
+    let takers: Vec<Order> = book.sweep_settlement_timeouts(clock.now());
+
+    assert_eq!(takers.len(), 1);
+    assert_eq!(takers[0].id, bid_id);
+    assert_eq!(takers[0].remaining, 5.into());
+    assert!(book.tape().is_empty());
+    assert!(book.awaiting_confirmation.is_empty());
+    assert_eq!(book.next_to_fill(OrderSide::Ask).unwrap().id, maker_id);
+    assert_eq!(book.quantity_at(OrderSide::Ask, 96.into()), 5.into());
+
+    let fill_id = events
+        .try_iter()
+        .find_map(|t| match t {
+            BookEvent::SettlementTimedOut { fill_id, taker_id } => {
+                assert_eq!(taker_id, bid_id);
+                Some(fill_id)
+            }
+            _ => None,
+        })
+        .expect("Taker was not notified of the timeout");
+    assert_eq!(book.confirm_settlement(fill_id), None);
+}
+
+#[tokio::test]
+pub async fn test_depth_within_bps() {
+    let book = setup().await;
+
+    /* 200 bps of 96 is 1.92, so the asks at 96 and 97 qualify */
+    assert_eq!(book.depth_within_bps(OrderSide::Ask, 200), 20.into());
+    /* 200 bps of 95 is 1.9, so the bids at 95 and 94 qualify */
+    assert_eq!(book.depth_within_bps(OrderSide::Bid, 200), 30.into());
+
+    assert_eq!(book.depth_within_bps(OrderSide::Ask, 0), 5.into());
+    assert_eq!(book.depth_within_bps(OrderSide::Ask, 10_000), 67.into());
+    assert_eq!(
+        Book::new(Address::zero()).depth_within_bps(OrderSide::Bid, 200),
+        U256::zero()
+    );
+}
+
+#[tokio::test]
+pub async fn test_size_priority_allocation() {
+    let mut book: Book = Book::new(Address::zero());
+    book.allocation = Allocation::SizePriority;
+
+    for (trader, quantity) in [(1, 5), (2, 20), (3, 10)].iter() {
+        let ask: Order = Order::new(
+            Address::from_low_u64_be(*trader),
+            Address::zero(),
+            OrderSide::Ask,
+            100.into(),
+            (*quantity).into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit(ask, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+    }
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(4),
+        Address::zero(),
+        OrderSide::Bid,
+        100.into(),
+        25.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    /* the largest ask fills first despite arriving second */
+    let fills: Vec<(Address, U256)> = book
+        .tape()
+        .iter()
+        .map(|t| (t.maker.trader, t.quantity))
+        .collect();
+    assert_eq!(
+        fills,
+        vec![
+            (Address::from_low_u64_be(2), 20.into()),
+            (Address::from_low_u64_be(3), 5.into()),
+        ]
+    );
+}
+
+#[tokio::test]
+pub async fn test_simulate_reports_skipped_makers() {
+    let mut book = setup().await;
+    book.self_trade_prevention = SelfTradePrevention::Reject;
+    let checksum = book.checksum();
+    let asks: Vec<OrderId> =
+        book.l3_snapshot().asks.iter().map(|t| t.id).collect();
+
+    /* trader 3 has an ask resting at 97, between the asks at 96 and 98 */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        Address::zero(),
+        OrderSide::Bid,
+        98.into(),
+        30.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    let simulation = book.simulate(&bid);
+
+    assert_eq!(
+        simulation.fills,
+        vec![
+            (asks[0], 96.into(), 5.into()),
+            (asks[2], 98.into(), 25.into())
+        ]
+    );
+    assert_eq!(simulation.skipped, vec![SkipReason::SelfTrade(asks[1])]);
+    assert_eq!(book.checksum(), checksum);
+
+    /* the self-trade the simulation found is what submission rejects */
+    assert_eq!(
+        book.submit(bid, TEST_RPC_ADDRESS.to_string()).await,
+        Err(BookError::SelfTrade)
+    );
+}
+
+#[tokio::test]
+pub async fn test_last_update_advances_on_mutation() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let mut book: Book = Book::new(Address::zero());
+    book.set_clock(clock.clone());
+
+    assert_eq!(book.created_at(), start);
+    assert_eq!(book.last_update(), None);
+
+    for trader in [1, 2].iter() {
+        clock.advance(Duration::seconds(30));
+        let ask: Order = Order::new(
+            Address::from_low_u64_be(*trader),
+            Address::zero(),
+            OrderSide::Ask,
+            100.into(),
+            10.into(),
+            clock.now() + Duration::days(1),
+            clock.now(),
+            vec![],
+        );
+        book.submit(ask, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+
+        assert_eq!(book.last_update(), Some(clock.now()));
+    }
+
+    /* queries leave the last update alone */
+    clock.advance(Duration::seconds(30));
+    book.top();
+    assert_eq!(book.last_update(), Some(start + Duration::seconds(60)));
+}
+
+#[tokio::test]
+pub async fn test_price_bucket_matching() {
+    let mut filled: Vec<usize> = vec![];
+
+    for bucket in [None, Some(U256::from(10))].iter() {
+        let mut book: Book = Book::new(Address::zero());
+        book.set_price_bucket(*bucket);
+
+        let ask: Order = Order::new(
+            Address::from_low_u64_be(1),
+            Address::zero(),
+            OrderSide::Ask,
+            102.into(),
+            10.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit(ask, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+
+        /* both prices fall in the bucket from 100 to 109 */
+        let bid: Order = Order::new(
+            Address::from_low_u64_be(2),
+            Address::zero(),
+            OrderSide::Bid,
+            101.into(),
+            10.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit(bid, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+
+        filled.push(book.tape().len());
+        if bucket.is_some() {
+            assert_eq!(book.tape()[0].price, 102.into());
+            assert_eq!(book.depth(), (0, 0));
+        }
+    }
+
+    /* exact matching rests the bid, bucketed matching fills it */
+    assert_eq!(filled, vec![0, 1]);
+}
+
+#[tokio::test]
+pub async fn test_delta_feed_tracks_snapshot() {
+    let mut book = setup().await;
+    let feed: Receiver<SequencedDelta> = book.delta_feed();
+    let mut client: BookSnapshot = book.snapshot();
+
+    /* a partial sweep of the asks, a new bid level and a cancellation */
+    let orders: Vec<Order> = vec![
+        Order::new(
+            Address::from_low_u64_be(10),
+            Address::zero(),
+            OrderSide::Bid,
+            97.into(),
+            12.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        ),
+        Order::new(
+            Address::from_low_u64_be(11),
+            Address::zero(),
+            OrderSide::Bid,
+            90.into(),
+            7.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        ),
+    ];
+    for order in orders {
+        book.submit(order, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+    }
+    let id: OrderId = book.next_to_fill(OrderSide::Bid).unwrap().id;
+    book.cancel(id).unwrap();
+
+    let deltas: Vec<SequencedDelta> = feed.try_iter().collect();
+    for delta in deltas.iter() {
+        client.apply(&delta.delta);
+    }
+
+    assert_eq!(client, book.snapshot());
+    assert_eq!(
+        deltas.iter().map(|t| t.sequence).collect::<Vec<u64>>(),
+        (1..=book.delta_sequence).collect::<Vec<u64>>()
+    );
+    assert_eq!(deltas.len(), 3);
+}
+
+#[tokio::test]
+pub async fn test_seed_from_amm() {
+    let mut book: Book = Book::new(Address::zero());
+
+    /* a pool of 1000 contracts priced at 100 */
+    let curve: AmmCurve = AmmCurve::new(
+        1000.into(),
+        100_000.into(),
+        1.into(),
+        Address::from_low_u64_be(1),
+        Utc::now() + Duration::days(1),
+    );
+    book.seed_from_amm(curve, 5);
+
+    let snapshot: BookSnapshot = book.snapshot();
+    let bids: Vec<(U256, U256)> =
+        snapshot.bids.iter().rev().map(|(p, q)| (*p, *q)).collect();
+    let asks: Vec<(U256, U256)> =
+        snapshot.asks.iter().map(|(p, q)| (*p, *q)).collect();
+
+    assert_eq!(bids.len(), 5);
+    assert_eq!(asks.len(), 5);
+    assert_eq!(book.top(), (Some(99.into()), Some(101.into())));
+
+    /* prices move away from the pool's price one tick at a time */
+    assert!(bids.windows(2).all(|t| t[1].0 < t[0].0));
+    assert!(asks.windows(2).all(|t| t[1].0 > t[0].0));
+    assert!(bids.iter().chain(asks.iter()).all(|t| !t.1.is_zero()));
+    assert_eq!(book.traders(), vec![Address::from_low_u64_be(1)]);
+}
+
+#[tokio::test]
+pub async fn test_fill_stats() {
+    let mut book = setup().await;
+    let trader: Address = Address::from_low_u64_be(10);
+
+    /* sweeps 5 at 96 and 10 of the 15 at 97, resting nothing */
+    let bid: Order = Order::new(
+        trader,
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        15.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let stats = book.fill_stats(trader);
+    assert_eq!(stats.submitted, 1);
+    assert_eq!(stats.filled_qty, 15.into());
+    assert_eq!(stats.cancelled, 0);
+    /* (5 * 96 + 10 * 97) / 15, rounded down */
+    assert_eq!(stats.avg_fill_price, Some(96.into()));
+
+    /* takes the 5 left at 97, resting 3 to cancel */
+    let bid: Order = Order::new(
+        trader,
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        8.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let id: OrderId = bid.id;
+    book.submit(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    book.cancel(id).unwrap();
+
+    let stats = book.fill_stats(trader);
+    assert_eq!(stats.submitted, 2);
+    assert_eq!(stats.filled_qty, 20.into());
+    assert_eq!(stats.cancelled, 1);
+    assert_eq!(stats.avg_fill_price, Some(96.into()));
+
+    assert_eq!(
+        book.fill_stats(Address::from_low_u64_be(42)),
+        Default::default()
+    );
+}
+
+#[tokio::test]
+pub async fn test_emergency_stop() {
+    let mut book = setup().await;
+    let events: Receiver<BookEvent> = book.subscribe();
+    let resting: Vec<OrderId> = book
+        .l3_snapshot()
+        .bids
+        .iter()
+        .chain(book.l3_snapshot().asks.iter())
+        .map(|t| t.id)
+        .collect();
+
+    let cancelled: Vec<Order> = book.emergency_stop();
+
+    assert_eq!(cancelled.iter().map(|t| t.id).collect::<Vec<_>>(), resting);
+    assert_eq!(book.depth(), (0, 0));
+    assert_eq!(
+        events.try_iter().collect::<Vec<BookEvent>>(),
+        vec![BookEvent::EmergencyStop]
+    );
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        95.into(),
+        10.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    assert_eq!(
+        book.submit(bid.clone(), TEST_RPC_ADDRESS.to_string()).await,
+        Err(BookError::MarketHalted)
+    );
+    assert!(book.halt_queue.is_empty());
+
+    book.resume(None).await;
+    assert_eq!(
+        book.submit(bid, TEST_RPC_ADDRESS.to_string()).await,
+        Ok(OrderStatus::Add)
+    );
+}
+
+#[tokio::test]
+pub async fn test_emergency_stop_withdraws_held_fills() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let (executioner, _) = spawn_executioner();
+    let mut book = setup().await;
+    book.set_clock(clock.clone());
+    book.settlement_timeout = Some(Duration::seconds(30));
+
+    /* takes trader 4's entire ask of 5 at 96, forwarded unconfirmed */
+    let forwarded: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        96.into(),
+        5.into(),
+        clock.now() + Duration::days(1),
+        clock.now(),
+        vec![],
+    );
+    book.submit(forwarded, executioner.clone())
+        .await
+        .expect("Failed to submit order to book");
+
+    /* a fresh ask at 96 with a last look window, partially lifted */
+    let mut held: Order = Order::new(
+        Address::from_low_u64_be(11),
+        Address::zero(),
+        OrderSide::Ask,
+        96.into(),
+        10.into(),
+        clock.now() + Duration::days(1),
+        clock.now(),
+        vec![],
+    );
+    held.last_look = Some(Duration::seconds(60));
+    let held_id: OrderId = held.id;
+    let taker: Order = Order::new(
+        Address::from_low_u64_be(12),
+        Address::zero(),
+        OrderSide::Bid,
+        96.into(),
+        4.into(),
+        clock.now() + Duration::days(1),
+        clock.now(),
+        vec![],
+    );
+    let taker_id: OrderId = taker.id;
+    for order in [held, taker].iter() {
+        book.submit(order.clone(), executioner.clone())
+            .await
+            .expect("Failed to submit order to book");
+    }
+    assert_eq!(book.awaiting_confirmation.len(), 1);
+    assert_eq!(book.pending_fills.len(), 1);
+    let fill_id = book.pending_fills[0].fill.id;
+
+    let cancelled: Vec<Order> = book.emergency_stop();
+
+    /* the held maker is cancelled in full, and its taker with it */
+    let maker: &Order = cancelled.iter().find(|t| t.id == held_id).unwrap();
+    assert_eq!(maker.remaining, 10.into());
+    let taker: &Order = cancelled.iter().find(|t| t.id == taker_id).unwrap();
+    assert_eq!(taker.remaining, 4.into());
+    assert!(book.pending_fills.is_empty());
+    assert_eq!(book.reject_fill(fill_id), Ok(None));
+
+    /* the unconfirmed fill lapses without resting its maker again */
+    clock.advance(Duration::seconds(30));
+    assert_eq!(book.sweep_settlement_timeouts(clock.now()).len(), 1);
+    assert!(book.tape().is_empty());
+    assert_eq!(book.depth(), (0, 0));
+}
+
+#[tokio::test]
+pub async fn test_fill_granularity() {
+    let mut counts: Vec<usize> = vec![];
+
+    for granularity in
+        [FillGranularity::PerSlice, FillGranularity::PerMaker].iter()
+    {
+        let mut book: Book = Book::new(Address::zero());
+        book.fill_granularity = *granularity;
+
+        let iceberg: Order = Order::new(
+            Address::from_low_u64_be(1),
+            Address::zero(),
+            OrderSide::Ask,
+            100.into(),
+            100.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit_iceberg(
+            iceberg,
+            10.into(),
+            None,
+            TEST_RPC_ADDRESS.to_string(),
+        )
+        .await
+        .expect("Failed to submit iceberg to book");
+
+        /* lifts the first slice, then rests for the next three to hit */
+        let bid: Order = Order::new(
+            Address::from_low_u64_be(2),
+            Address::zero(),
+            OrderSide::Bid,
+            100.into(),
+            35.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        let receipt: SubmitReceipt = book
+            .submit_with_receipt(bid, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+
+        assert_eq!(book.tape().len(), 4);
+        assert_eq!(
+            receipt
+                .fills
+                .iter()
+                .fold(U256::zero(), |acc, t| acc + t.quantity),
+            35.into()
+        );
+        counts.push(receipt.fills.len());
+    }
+
+    /* the bid takes the first slice, but makes for the three after it */
+    assert_eq!(counts, vec![4, 2]);
+}
+
+#[tokio::test]
+pub async fn test_clone_empty() {
+    let mut book = setup().await;
+    book.set_fee_schedule(FeeSchedule::new(5, 10));
+    book.tick_grid = Some(TickGrid::new(100.into(), 1.into()));
+    book.lot_size = Some(5.into());
+    book.allocation = Allocation::RoundRobin;
+    book.max_notional = Some(1_000_000.into());
+    book.set_account_group(vec![
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+    ]);
+
+    let empty: Book = book.clone_empty();
+
+    assert_eq!(empty.market(), book.market());
+    assert_eq!(empty.fee_schedule(), book.fee_schedule());
+    assert_eq!(empty.tick_grid, book.tick_grid);
+    assert_eq!(empty.lot_size, book.lot_size);
+    assert_eq!(empty.allocation, book.allocation);
+    assert_eq!(empty.max_notional, book.max_notional);
+    assert_eq!(empty.account_groups, book.account_groups);
+
+    assert_eq!(empty.depth(), (0, 0));
+    assert!(empty.tape().is_empty());
+    assert_eq!(empty.sequence, 0);
+    assert_eq!(book.depth(), (5, 5));
+
+    /* only the resting orders tell the two apart */
+    assert!(!empty.equals_state(&book));
+    assert!(empty.equals_state(&book.clone_empty()));
+}
+
+#[tokio::test]
+pub async fn test_spread_bps() {
+    let mut book = setup().await;
+
+    /* a spread of 1 around a mid of 95.5 */
+    assert_eq!(book.spread_bps(), Some(104));
+
+    assert_eq!(Book::new(Address::zero()).spread_bps(), None);
+
+    for price in [96, 97, 98, 99, 100].iter() {
+        book.cancel_level(OrderSide::Ask, (*price).into());
+    }
+    assert_eq!(book.spread_bps(), None);
+}
+
+#[tokio::test]
+pub async fn test_reject_if_one_sided() {
+    let mut outcomes: Vec<(Result<OrderStatus, BookError>, BookError)> = vec![];
+
+    for reject in [false, true].iter() {
+        let mut book = setup().await;
+        book.reject_if_one_sided = *reject;
+        for price in [91, 92, 93, 94, 95].iter() {
+            book.cancel_level(OrderSide::Bid, (*price).into());
+        }
+        let ask = |trader: u64, price: u64| {
+            Order::new(
+                Address::from_low_u64_be(trader),
+                Address::zero(),
+                OrderSide::Ask,
+                price.into(),
+                10.into(),
+                Utc::now(),
+                Utc::now(),
+                vec![],
+            )
+        };
+
+        /* with no bids, a taker ask has nothing to match */
+        let ioc: Result<OrderStatus, BookError> = book
+            .submit_ioc(ask(10, 95), TEST_RPC_ADDRESS.to_string())
+            .await
+            .map(|(status, _)| status);
+        let market: BookError = book
+            .submit_market(ask(11, 95), TEST_RPC_ADDRESS.to_string())
+            .await
+            .unwrap_err();
+        outcomes.push((ioc, market));
+
+        /* passive asks still rest, so the bid side can be seeded later */
+        assert_eq!(
+            book.submit(ask(12, 101), TEST_RPC_ADDRESS.to_string())
+                .await,
+            Ok(OrderStatus::Add)
+        );
+        assert_eq!(book.quantity_at(OrderSide::Ask, 101.into()), 10.into());
+
+        /* a bid still has the asks to match against */
+        let bid: Order = Order::new(
+            Address::from_low_u64_be(13),
+            Address::zero(),
+            OrderSide::Bid,
+            96.into(),
+            5.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        assert_eq!(
+            book.submit_ioc(bid, TEST_RPC_ADDRESS.to_string())
+                .await
+                .map(|(status, _)| status),
+            Ok(OrderStatus::FullMatch)
+        );
+    }
+
+    assert_eq!(
+        outcomes,
+        vec![
+            (Ok(OrderStatus::Add), BookError::InsufficientLiquidity),
+            (Err(BookError::OneSidedBook), BookError::OneSidedBook),
+        ]
+    );
+}
+
+/// Treats every order from the provided trader as no longer valid
+struct RevokedTrader(Address);
+
+impl Revalidator for RevokedTrader {
+    fn revalidate(&self, order: &Order) -> bool {
+        order.trader != self.0
+    }
+}
+
+#[tokio::test]
+pub async fn test_revalidation_quarantines_aged_makers() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let mut book: Book = Book::new(Address::zero());
+    book.set_clock(clock.clone());
+    book.set_revalidation(
+        Duration::hours(1),
+        Arc::new(RevokedTrader(Address::from_low_u64_be(1))),
+    );
+
+    /* trader 1's first ask ages past the limit, its second does not */
+    let mut asks: Vec<Order> = vec![];
+    for price in [100, 101].iter() {
+        let ask: Order = Order::new(
+            Address::from_low_u64_be(1),
+            Address::zero(),
+            OrderSide::Ask,
+            (*price).into(),
+            10.into(),
+            clock.now() + Duration::days(1),
+            clock.now(),
+            vec![],
+        );
+        book.submit(ask.clone(), TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+        asks.push(ask);
+        if asks.len() == 1 {
+            clock.advance(Duration::minutes(90));
+        }
+    }
+
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(2),
+        Address::zero(),
+        OrderSide::Bid,
+        101.into(),
+        10.into(),
+        clock.now() + Duration::days(1),
+        clock.now(),
+        vec![],
+    );
+    let simulation = book.simulate(&bid);
+    assert_eq!(
+        simulation.skipped,
+        vec![SkipReason::FailedRevalidation(asks[0].id)]
+    );
+
+    assert_eq!(
+        book.submit(bid, TEST_RPC_ADDRESS.to_string()).await,
+        Ok(OrderStatus::FullMatch)
+    );
+
+    /* the aged ask is skipped and quarantined, the younger one fills */
+    assert_eq!(book.tape().len(), 1);
+    assert_eq!(book.tape()[0].maker.id, asks[1].id);
+    assert_eq!(
+        book.quarantined
+            .iter()
+            .map(|t| t.id)
+            .collect::<Vec<OrderId>>(),
+        vec![asks[0].id]
+    );
+    assert_eq!(book.quarantined[0].remaining, 10.into());
+    assert!(book.order(asks[0].id).is_none());
+    assert_eq!(book.depth(), (0, 0));
+}
+
+#[tokio::test]
+pub async fn test_from_snapshot() {
+    let book = setup().await;
+    let snapshot: BookSnapshot = book.snapshot();
+
+    let rebuilt: Book = Book::from_snapshot(*book.market(), snapshot.clone());
+
+    assert_eq!(rebuilt.market(), book.market());
+    assert_eq!(rebuilt.depth(), book.depth());
+    assert_eq!(rebuilt.top(), book.top());
+    assert_eq!(rebuilt.snapshot(), snapshot);
+    assert_eq!(rebuilt.order_count(), 10);
+}
+
+#[tokio::test]
+pub async fn test_market_order_fallback() {
+    let mut outcomes: Vec<(Result<OrderStatus, BookError>, U256)> = vec![];
+
+    for fallback in [
+        MarketOrderFallback::Reject,
+        MarketOrderFallback::RestAtReference,
+    ]
+    .iter()
+    {
+        /* sweeps every bid, leaving only asks */
+        let mut book = setup().await;
+        book.market_order_fallback = *fallback;
+        let ask: Order = Order::new(
+            Address::from_low_u64_be(10),
+            Address::zero(),
+            OrderSide::Ask,
+            91.into(),
+            60.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        book.submit(ask, TEST_RPC_ADDRESS.to_string())
+            .await
+            .expect("Failed to submit order to book");
+        assert_eq!(book.depth(), (0, 5));
+
+        let market_sell: Order = Order::new(
+            Address::from_low_u64_be(11),
+            Address::zero(),
+            OrderSide::Ask,
+            U256::zero(),
+            10.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        outcomes.push((
+            book.submit_market(market_sell, TEST_RPC_ADDRESS.to_string())
+                .await,
+            book.quantity_at(OrderSide::Ask, book.ltp()),
+        ));
+    }
+
+    /* the last trade took the bid at 91, so the fallback rests there */
+    assert_eq!(
+        outcomes,
+        vec![
+            (Err(BookError::InsufficientLiquidity), 0.into()),
+            (Ok(OrderStatus::Add), 10.into()),
+        ]
+    );
+
+    /* an explicit limit is kept rather than reset to the reference */
+    let mut book = setup().await;
+    book.market_order_fallback = MarketOrderFallback::RestAtReference;
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Ask,
+        91.into(),
+        60.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(ask, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    let limited_sell: Order = Order::new(
+        Address::from_low_u64_be(11),
+        Address::zero(),
+        OrderSide::Ask,
+        95.into(),
+        10.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let id: OrderId = limited_sell.id;
+    assert_eq!(
+        book.submit_market(limited_sell, TEST_RPC_ADDRESS.to_string())
+            .await,
+        Ok(OrderStatus::Add)
+    );
+    assert_eq!(book.quantity_at(OrderSide::Ask, 95.into()), 10.into());
+    assert_eq!(book.quantity_at(OrderSide::Ask, 91.into()), 0.into());
+    assert_eq!(book.asks[&U256::from(95)][0].id, id);
+}
+
+#[tokio::test]
+pub async fn test_receipt_slippage_bps() {
+    let mut book = setup().await;
+
+    /* sweeps 5 at 96, 15 at 97 and 10 at 98 against a best ask of 96 */
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(10),
+        Address::zero(),
+        OrderSide::Bid,
+        98.into(),
+        30.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let receipt: SubmitReceipt = book
+        .submit_with_receipt(bid, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    /* (2915 - 96 * 30) / (96 * 30), or 121.5 bps */
+    assert_eq!(receipt.fills.len(), 3);
+    /* each fill is referenced against the touch before the bid */
+    assert!(receipt
+        .fills
+        .iter()
+        .all(|t| t.reference_bid == Some(95.into())
+            && t.reference_ask == Some(96.into())));
+    assert_eq!(receipt.slippage_bps(), Some(121));
+
+    /* filling entirely at the touch does not slip */
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(11),
+        Address::zero(),
+        OrderSide::Ask,
+        95.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let receipt: SubmitReceipt = book
+        .submit_with_receipt(ask, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert_eq!(receipt.slippage_bps(), Some(0));
+
+    /* an order that only rests took no liquidity */
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(12),
+        Address::zero(),
+        OrderSide::Ask,
+        110.into(),
+        5.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    let receipt: SubmitReceipt = book
+        .submit_with_receipt(ask, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert_eq!(receipt.slippage_bps(), None);
+}
+
+#[tokio::test]
+pub async fn test_max_open_orders_per_trader() {
+    let mut book: Book = Book::new(Address::zero());
+    book.max_open_orders_per_trader = Some(2);
+    let bid = |trader: u64, price: u64| {
+        Order::new(
+            Address::from_low_u64_be(trader),
+            Address::zero(),
+            OrderSide::Bid,
+            price.into(),
+            10.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        )
+    };
+
+    for price in [90, 91].iter() {
+        assert_eq!(
+            book.submit(bid(1, *price), TEST_RPC_ADDRESS.to_string())
+                .await,
+            Ok(OrderStatus::Add)
+        );
+    }
+
+    assert_eq!(
+        book.submit(bid(1, 92), TEST_RPC_ADDRESS.to_string()).await,
+        Err(BookError::TooManyOpenOrders)
+    );
+    assert_eq!(book.depth(), (2, 0));
+
+    /* other traders are counted separately */
+    assert_eq!(
+        book.submit(bid(2, 92), TEST_RPC_ADDRESS.to_string()).await,
+        Ok(OrderStatus::Add)
+    );
+
+    /* orders filled out of the book free up their traders' slots */
+    let ask: Order = Order::new(
+        Address::from_low_u64_be(3),
+        Address::zero(),
+        OrderSide::Ask,
+        91.into(),
+        20.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    book.submit(ask, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert_eq!(book.open_orders.get(&Address::from_low_u64_be(1)), Some(&1));
+    assert_eq!(book.open_orders.get(&Address::from_low_u64_be(2)), None);
+    assert_eq!(book.check_invariants(), Ok(()));
+}
+
+#[tokio::test]
+pub async fn test_ioc_returns_remainder() {
+    let mut book: Book = setup().await;
+    book.return_ioc_remainder = true;
+    let bid: Order = Order::new(
+        Address::from_low_u64_be(3),
+        Address::zero(),
+        OrderSide::Bid,
+        96.into(),
+        8.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+
+    let (status, remainder): (OrderStatus, Option<Order>) = book
+        .submit_ioc(bid.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert_eq!(status, OrderStatus::PartialMatch);
+
+    /* only the ask at 96 was taken and nothing rested in its place */
+    assert_eq!(book.depth(), (5, 4));
+    assert_eq!(book.total_bid_volume(), 60.into());
+
+    let remainder: Order = remainder.expect("Remainder not returned");
+    assert_ne!(remainder.id, bid.id);
+    assert_eq!(remainder.trader, bid.trader);
+    assert_eq!(remainder.side, OrderSide::Bid);
+    assert_eq!(remainder.price, 96.into());
+    assert_eq!(remainder.quantity, 3.into());
+    assert_eq!(remainder.remaining, 3.into());
+    assert!(book.order(remainder.id).is_none());
+}
+
+#[tokio::test]
+pub async fn test_concentration() {
+    let market: Address = Address::zero();
+    let traders: Vec<Address> = (0..4).map(Address::from_low_u64_be).collect();
+
+    /* one large bid against the same quantity split four ways */
+    let book: Book = submit_orders(
+        market,
+        vec![
+            (traders[0], OrderSide::Bid, 95, 40),
+            (traders[0], OrderSide::Ask, 100, 10),
+            (traders[1], OrderSide::Ask, 100, 10),
+            (traders[2], OrderSide::Ask, 101, 10),
+            (traders[3], OrderSide::Ask, 102, 10),
+        ],
+    )
+    .await;
+
+    assert!((book.concentration(OrderSide::Bid) - 1.0).abs() < 1e-9);
+    assert!((book.concentration(OrderSide::Ask) - 0.25).abs() < 1e-9);
+    assert!(
+        book.concentration(OrderSide::Bid) > book.concentration(OrderSide::Ask)
+    );
+    assert_eq!(Book::new(market).concentration(OrderSide::Bid), 0.0);
+}
+
+#[tokio::test]
+pub async fn test_rfq_accept_quote() {
+    let mut book: Book = setup().await;
+    book.rfq = true;
+    let taker: Address = Address::from_low_u64_be(10);
+
+    /* takers cannot match by submission */
+    let bid: Order = Order::new(
+        taker,
+        Address::zero(),
+        OrderSide::Bid,
+        97.into(),
+        10.into(),
+        Utc::now(),
+        Utc::now(),
+        vec![],
+    );
+    assert_eq!(
+        book.submit(bid, TEST_RPC_ADDRESS.to_string()).await,
+        Err(BookError::WouldMatch)
+    );
+
+    /* only asks able to fill all 10 quote, best first */
+    let quotes: Vec<Quote> = book.request_quotes(OrderSide::Bid, 10.into());
+    let prices: Vec<U256> = quotes.iter().map(|t| t.price).collect();
+    assert_eq!(prices, vec![97.into(), 98.into(), 100.into()]);
+
+    let fill: Fill = book
+        .accept_quote(quotes[0].id, taker)
+        .expect("Failed to accept quote");
+    assert_eq!(fill.maker.id, quotes[0].maker);
+    assert_eq!(fill.maker.trader, Address::from_low_u64_be(3));
+    assert_eq!(fill.taker.trader, taker);
+    assert_eq!(fill.price, 97.into());
+    assert_eq!(fill.quantity, 10.into());
+
+    /* the taker is counted as a submission and never clashes by ID */
+    assert_eq!(fill.taker.id, quotes[0].id);
+    assert_eq!(fill.taker.expiration, fill.maker.expiration);
+    assert_eq!(book.order_counts.get(&taker), Some(&(1, 0)));
+    assert_eq!(book.tape(), &[fill]);
+    assert_eq!(book.ltp(), 97.into());
+
+    /* the maker keeps the rest of its order and its place */
+    assert_eq!(book.quantity_at(OrderSide::Ask, 97.into()), 5.into());
+    assert_eq!(book.depth(), (5, 5));
+
+    /* a quote can only be accepted once */
+    assert_eq!(
+        book.accept_quote(quotes[0].id, taker),
+        Err(BookError::OrderNotFound)
+    );
+}
+
+#[tokio::test]
+pub async fn test_rfq_accept_quote_busy_maker() {
+    let mut book: Book = Book::new(Address::zero());
+    book.rfq = true;
+    let sequence: u64 = book.sequence;
+
+    let mut ask: Order = Order::new(
+        Address::from_low_u64_be(1),
+        Address::zero(),
+        OrderSide::Ask,
+        100.into(),
+        10.into(),
+        Utc::now() + Duration::days(1),
+        Utc::now(),
+        vec![],
+    );
+    ask.last_look = Some(Duration::seconds(60));
+    book.submit(ask, TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    /* the first acceptance is held for the maker's last look */
+    let quotes: Vec<Quote> = book.request_quotes(OrderSide::Bid, 5.into());
+    book.accept_quote(quotes[0].id, Address::from_low_u64_be(2))
+        .expect("Failed to accept quote");
+    assert_eq!(book.pending_fills.len(), 1);
+    assert_eq!(book.sequence, sequence + 2);
+
+    let quotes: Vec<Quote> = book.request_quotes(OrderSide::Bid, 5.into());
+    assert_eq!(
+        book.accept_quote(quotes[0].id, Address::from_low_u64_be(3)),
+        Err(BookError::OrderBusy)
+    );
+
+    /* the quote stays outstanding once the maker is free again */
+    let fill_id = book.pending_fills[0].fill.id;
+    book.reject_fill(fill_id).unwrap();
+    assert!(book
+        .accept_quote(quotes[0].id, Address::from_low_u64_be(3))
+        .is_ok());
+}
+
+#[tokio::test]
+pub async fn test_priority_decay() {
+    let start: DateTime<Utc> = DateTime::from_utc(
+        NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        Utc,
+    );
+    let clock: Arc<MockClock> = Arc::new(MockClock::new(start));
+    let mut book: Book = Book::new(Address::zero());
+    book.set_clock(clock.clone());
+    book.priority_decay = Some(Duration::seconds(60));
+    let order = |trader: u64, side: OrderSide, quantity: u64| {
+        Order::new(
+            Address::from_low_u64_be(trader),
+            Address::zero(),
+            side,
+            100.into(),
+            quantity.into(),
+            clock.now() + Duration::days(1),
+            clock.now(),
+            vec![],
+        )
+    };
+
+    let old: Order = order(1, OrderSide::Ask, 10);
+    book.submit(old.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    clock.advance(Duration::seconds(30));
+    let new: Order = order(2, OrderSide::Ask, 10);
+    book.submit(new.clone(), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+
+    /* before the threshold, the older order keeps its priority */
+    book.submit(order(3, OrderSide::Bid, 5), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert_eq!(book.tape()[0].maker.id, old.id);
+
+    /* past it, the newer order at the same price fills first */
+    clock.advance(Duration::seconds(40));
+    book.submit(order(3, OrderSide::Bid, 5), TEST_RPC_ADDRESS.to_string())
+        .await
+        .expect("Failed to submit order to book");
+    assert_eq!(book.tape()[1].maker.id, new.id);
+    assert_eq!(book.order(old.id).unwrap().remaining, 5.into());
 }
@@ -0,0 +1,108 @@
+//! Contains the time source used throughout the matching engine
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Represents a source of the current time
+pub trait Clock: Send + Sync {
+    /// Returns the current time according to this clock
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Represents the system's wall clock
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Represents a clock that only moves when told to
+///
+/// Intended for driving the engine deterministically in tests.
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    /// Constructor for the `MockClock` type, stopped at `start`
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    /// Moves the clock to the provided time
+    pub fn set(&self, time: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) = time;
+    }
+
+    /// Moves the clock forward by the provided duration
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        /* `DateTime` has no `AddAssign` impl in our version of chrono */
+        let later: DateTime<Utc> = *now + duration;
+        *now = later;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Represents a clock shared between an order book and its owner
+///
+/// Clones share the same underlying clock. The clock is not part of a book's
+/// state, so it never affects equality.
+#[derive(Clone)]
+pub struct SharedClock(pub Arc<dyn Clock>);
+
+impl SharedClock {
+    /// Returns the current time according to the underlying clock
+    pub fn now(&self) -> DateTime<Utc> {
+        self.0.now()
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self(Arc::new(SystemClock))
+    }
+}
+
+impl PartialEq for SharedClock {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SharedClock {}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SharedClock")
+    }
+}
+
+/// Represents a value read from a book's clock
+///
+/// Readings are serialised as the underlying value. Like the clock itself,
+/// they are not part of a book's state, so they never affect equality.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ClockReading<T>(pub T);
+
+impl<T> PartialEq for ClockReading<T> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for ClockReading<T> {}
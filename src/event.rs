@@ -0,0 +1,119 @@
+//! Contains type definitions for events emitted by order books
+use std::fmt;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use web3::types::U256;
+
+use crate::fee::FeeSchedule;
+use crate::fill::FillId;
+use crate::order::{Order, OrderId, OrderSide};
+
+/// Represents a notable change to the state of an order book
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum BookEvent {
+    FeeScheduleChanged {
+        old: FeeSchedule,
+        new: FeeSchedule,
+    },
+    Crossed,   /* the best bid has reached the best ask */
+    Uncrossed, /* the best bid is back below the best ask */
+    SpreadChanged {
+        old: Option<U256>, /* `None` while either side is empty */
+        new: Option<U256>,
+    },
+    BecameBest {
+        order_id: OrderId, /* now first to fill on its side */
+        side: OrderSide,
+    },
+    EmergencyStop, /* halted with every order cancelled */
+    SettlementTimedOut {
+        fill_id: FillId, /* rolled back for want of confirmation */
+        taker_id: OrderId,
+    },
+}
+
+/// Represents a single mutation of an order book, as replicated to standbys
+///
+/// These mirror the records of the order log: an order is either restated as
+/// it stands after the mutation or, once it has left the book, removed.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ReplicatedOp {
+    Restate(Box<Order>),
+    Remove(OrderId),
+}
+
+impl From<&Order> for ReplicatedOp {
+    fn from(order: &Order) -> Self {
+        if order.remaining.is_zero() {
+            ReplicatedOp::Remove(order.id)
+        } else {
+            ReplicatedOp::Restate(Box::new(order.clone()))
+        }
+    }
+}
+
+/// Holds the subscribers to a stream of events
+///
+/// Subscribers are not part of a book's state: they are never serialised, are
+/// not carried over when a book is cloned and never affect equality. Senders
+/// are held behind mutexes so that books remain `Sync`.
+pub struct Subscribers<T> {
+    senders: Vec<Mutex<Sender<T>>>,
+}
+
+impl<T: Clone> Subscribers<T> {
+    /// Registers a new subscriber, returning the receiving end of its channel
+    pub fn subscribe(&mut self) -> Receiver<T> {
+        let (sender, receiver) = channel();
+        self.senders.push(Mutex::new(sender));
+        receiver
+    }
+
+    /// Sends the provided event to every subscriber
+    ///
+    /// Subscribers whose receivers have been dropped are removed.
+    pub fn publish(&mut self, event: T) {
+        self.senders.retain(|t| match t.lock() {
+            Ok(sender) => sender.send(event.clone()).is_ok(),
+            Err(_e) => false,
+        });
+    }
+
+    /// Returns the number of live subscribers
+    pub fn len(&self) -> usize {
+        self.senders.len()
+    }
+
+    /// Returns whether there are no subscribers
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+}
+
+impl<T> Default for Subscribers<T> {
+    fn default() -> Self {
+        Self { senders: vec![] }
+    }
+}
+
+impl<T> Clone for Subscribers<T> {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<T> PartialEq for Subscribers<T> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for Subscribers<T> {}
+
+impl<T> fmt::Debug for Subscribers<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Subscribers({})", self.senders.len())
+    }
+}
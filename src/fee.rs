@@ -0,0 +1,153 @@
+//! Contains type definitions for the fees charged on fills
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use web3::types::U256;
+
+use crate::util::{duration_de, duration_opt_de, duration_opt_se, duration_se};
+
+/// The number of basis points in one whole
+pub const BPS_DENOMINATOR: u32 = 10_000;
+
+/// Represents the fees charged to each counterparty of a fill
+///
+/// Fees are expressed in basis points of the fill's notional value and are
+/// rounded down.
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize,
+)]
+pub struct FeeSchedule {
+    pub maker_bps: u32,
+    pub taker_bps: u32,
+    #[serde(default)]
+    pub size_discount: Option<SizeDiscount>, /* reduced taker rate for size */
+}
+
+/// Represents a reduced taker rate for fills above a quantity threshold
+///
+/// If `marginal` is set, only the quantity beyond `threshold` is charged the
+/// reduced rate. Otherwise the entire qualifying fill is.
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize,
+)]
+pub struct SizeDiscount {
+    pub threshold: U256, /* quantity of contracts */
+    pub taker_bps: u32,
+    pub marginal: bool,
+}
+
+impl FeeSchedule {
+    /// Constructor for the `FeeSchedule` type
+    pub fn new(maker_bps: u32, taker_bps: u32) -> Self {
+        Self {
+            maker_bps,
+            taker_bps,
+            size_discount: None,
+        }
+    }
+
+    /// Applies the provided size discount to taker fees
+    pub fn with_size_discount(self, discount: SizeDiscount) -> Self {
+        Self {
+            size_discount: Some(discount),
+            ..self
+        }
+    }
+
+    /// Returns the fee charged to the maker of a fill of the given notional
+    pub fn maker_fee(&self, notional: U256) -> U256 {
+        apply_bps(notional, self.maker_bps)
+    }
+
+    /// Returns the fee charged to the taker of a fill of the given notional
+    /// and quantity
+    pub fn taker_fee(&self, notional: U256, quantity: U256) -> U256 {
+        match self.size_discount {
+            Some(discount) if quantity > discount.threshold => {
+                if !discount.marginal {
+                    return apply_bps(notional, discount.taker_bps);
+                }
+
+                /* notional is linear in quantity, so split it pro rata */
+                let base: U256 = notional / quantity * discount.threshold;
+                apply_bps(base, self.taker_bps)
+                    + apply_bps(notional - base, discount.taker_bps)
+            }
+            _ => apply_bps(notional, self.taker_bps),
+        }
+    }
+}
+
+/// Represents a fee schedule that applies once a trader reaches a volume
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct FeeTier {
+    pub min_volume: U256, /* notional volume required to qualify */
+    pub schedule: FeeSchedule,
+}
+
+/// Represents a fee program tiered by each trader's recent notional volume
+///
+/// Traders are charged according to the highest tier their volume within
+/// `window` qualifies them for. A window of `None` counts all volume.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TieredFeeSchedule {
+    pub tiers: Vec<FeeTier>, /* ascending by minimum volume */
+    #[serde(
+        serialize_with = "duration_opt_se",
+        deserialize_with = "duration_opt_de"
+    )]
+    pub window: Option<Duration>,
+}
+
+impl TieredFeeSchedule {
+    /// Constructor for the `TieredFeeSchedule` type
+    pub fn new(mut tiers: Vec<FeeTier>, window: Option<Duration>) -> Self {
+        tiers.sort_by_key(|t| t.min_volume);
+        Self { tiers, window }
+    }
+
+    /// Returns the schedule of the highest tier the given volume qualifies for
+    pub fn schedule_for(&self, volume: U256) -> Option<FeeSchedule> {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|t| t.min_volume <= volume)
+            .map(|t| t.schedule)
+    }
+}
+
+/// Represents a maker incentive program, paying an extra rebate on fills
+/// against makers that rested for at least `min_rest` beforehand
+///
+/// Resting time is measured from the maker's creation time, as with
+/// `Book::time_in_book`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct IncentiveProgram {
+    #[serde(serialize_with = "duration_se", deserialize_with = "duration_de")]
+    pub min_rest: Duration,
+    pub rebate_bps: u32,
+}
+
+impl IncentiveProgram {
+    /// Constructor for the `IncentiveProgram` type
+    pub fn new(min_rest: Duration, rebate_bps: u32) -> Self {
+        Self {
+            min_rest,
+            rebate_bps,
+        }
+    }
+
+    /// Returns the rebate owed on a fill of the given notional against a
+    /// maker that had rested for `rested`
+    pub fn rebate(&self, notional: U256, rested: Duration) -> U256 {
+        if rested < self.min_rest {
+            return U256::zero();
+        }
+
+        apply_bps(notional, self.rebate_bps)
+    }
+}
+
+/// Returns the provided number of basis points of `value`, rounded down
+pub fn apply_bps(value: U256, bps: u32) -> U256 {
+    value.saturating_mul(bps.into()) / U256::from(BPS_DENOMINATOR)
+}
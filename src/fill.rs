@@ -0,0 +1,217 @@
+//! Contains type definitions for fills produced by the matching engine
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use ethabi::Token;
+use serde::{Deserialize, Serialize};
+use web3::types::{Address, H256, U256};
+
+use crate::fee::FeeSchedule;
+use crate::order::{Order, OrderSide};
+
+pub type FillId = H256;
+
+/// Represents a single match between a resting (maker) order and an incoming
+/// (taker) order
+///
+/// Both orders are recorded as they stood immediately after the fill.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Fill {
+    pub id: FillId,
+    pub maker: Order,
+    pub taker: Order,
+    pub maker_side: OrderSide, /* the taker is on the opposite side */
+    pub price: U256,
+    pub quantity: U256,
+    pub notional: U256, /* price times quantity, scaled by contract size */
+    pub maker_fee: U256,
+    pub taker_fee: U256,
+    #[serde(default)]
+    pub incentive_rebate: U256, /* paid to the maker by incentive programs */
+    #[serde(default)]
+    pub reference_bid: Option<U256>, /* best bid before the taker arrived */
+    #[serde(default)]
+    pub reference_ask: Option<U256>, /* best ask before the taker arrived */
+    pub timestamp: DateTime<Utc>,
+}
+
+impl fmt::Display for Fill {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "<Fill:{} Maker: {} Taker: {} Price: {} Quantity: {}>",
+            self.id, self.maker.id, self.taker.id, self.price, self.quantity
+        )
+    }
+}
+
+pub fn fill_id(maker: &Order, taker: &Order) -> FillId {
+    let components: Vec<Token> = vec![
+        Token::FixedBytes(maker.id.as_ref().to_vec()),
+        Token::FixedBytes(taker.id.as_ref().to_vec()),
+    ];
+
+    web3::signing::keccak256(&ethabi::encode(&components)).into()
+}
+
+impl Fill {
+    /// Constructor for the `Fill` type
+    ///
+    /// Accepts both orders (as they stand after the fill) along with the
+    /// execution price, quantity and notional, then calculates the fill's ID.
+    pub fn new(
+        maker: Order,
+        taker: Order,
+        price: U256,
+        quantity: U256,
+        notional: U256,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: fill_id(&maker, &taker),
+            maker_side: maker.side,
+            maker,
+            taker,
+            price,
+            quantity,
+            notional,
+            maker_fee: U256::zero(),
+            taker_fee: U256::zero(),
+            incentive_rebate: U256::zero(),
+            reference_bid: None,
+            reference_ask: None,
+            timestamp,
+        }
+    }
+
+    /// Charges the maker and taker according to their respective schedules
+    pub fn with_fees(self, maker: &FeeSchedule, taker: &FeeSchedule) -> Self {
+        Self {
+            maker_fee: maker.maker_fee(self.notional),
+            taker_fee: taker.taker_fee(self.notional, self.quantity),
+            ..self
+        }
+    }
+
+    /// Pays the maker the provided incentive rebate
+    pub fn with_incentive_rebate(self, rebate: U256) -> Self {
+        Self {
+            incentive_rebate: rebate,
+            ..self
+        }
+    }
+
+    /// Records the best bid and ask prevailing before the taker arrived, for
+    /// transaction cost analysis
+    ///
+    /// Every fill of a taker shares the same pre-trade references, so they
+    /// measure the taker's whole execution rather than each fill alone.
+    pub fn with_reference(self, bid: Option<U256>, ask: Option<U256>) -> Self {
+        Self {
+            reference_bid: bid,
+            reference_ask: ask,
+            ..self
+        }
+    }
+
+    /// Returns the calldata settling this fill through the Tracer contracts
+    ///
+    /// The call takes the maker and taker orders (as tuples in the layout of
+    /// `Order::to_tokens`) followed by the execution price and quantity.
+    pub fn to_calldata(&self, contract_selector: [u8; 4]) -> Vec<u8> {
+        let arguments: Vec<Token> = vec![
+            Token::Tuple(self.maker.to_tokens()),
+            Token::Tuple(self.taker.to_tokens()),
+            Token::Uint(self.price),
+            Token::Uint(self.quantity),
+        ];
+
+        let mut calldata: Vec<u8> = contract_selector.to_vec();
+        calldata.extend(ethabi::encode(&arguments));
+        calldata
+    }
+
+    /// Returns whether the taker of this fill was the buyer
+    pub fn is_taker_buy(&self) -> bool {
+        self.maker_side == OrderSide::Ask
+    }
+
+    /// Returns the buy-side counterparty of this fill
+    pub fn buyer(&self) -> &Order {
+        match self.maker_side {
+            OrderSide::Bid => &self.maker,
+            OrderSide::Ask => &self.taker,
+        }
+    }
+
+    /// Returns the sell-side counterparty of this fill
+    pub fn seller(&self) -> &Order {
+        match self.maker_side {
+            OrderSide::Bid => &self.taker,
+            OrderSide::Ask => &self.maker,
+        }
+    }
+
+    /// Returns whether the provided trader was a counterparty to this fill
+    pub fn involves(&self, trader: Address) -> bool {
+        self.maker.trader == trader || self.taker.trader == trader
+    }
+
+    /// Returns the fees the provided trader was charged on this fill, as
+    /// maker, taker or both
+    pub fn fees_paid_by(&self, trader: Address) -> U256 {
+        let mut fees: U256 = U256::zero();
+
+        if self.maker.trader == trader {
+            fees = fees.saturating_add(self.maker_fee);
+        }
+
+        if self.taker.trader == trader {
+            fees = fees.saturating_add(self.taker_fee);
+        }
+
+        fees
+    }
+}
+
+/// Returns the total notional value of the provided fills that the trader was
+/// a counterparty to, counting only fills made at or after `since`
+pub fn trader_volume(
+    fills: &[Fill],
+    trader: Address,
+    since: Option<DateTime<Utc>>,
+) -> U256 {
+    fills
+        .iter()
+        .filter(|t| t.involves(trader))
+        .filter(|t| match since {
+            Some(s) => t.timestamp >= s,
+            None => true,
+        })
+        .fold(U256::zero(), |acc, t| acc.saturating_add(t.notional))
+}
+
+/// Represents a trader's execution quality over the life of a book
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize,
+)]
+pub struct FillStats {
+    pub submitted: u64,   /* orders accepted by the book */
+    pub filled_qty: U256, /* as either maker or taker */
+    pub cancelled: u64,
+    pub avg_fill_price: Option<U256>, /* quantity-weighted, rounded down */
+}
+
+/// Represents a fill that is being held pending confirmation
+///
+/// Pending fills arise when the maker order has a last look window, in which
+/// case the fill is automatically confirmed once `deadline` has passed. They
+/// also arise while awaiting confirmation of settlement, in which case the
+/// fill is rolled back once `deadline` has passed.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PendingFill {
+    pub fill: Fill,
+    pub deadline: DateTime<Utc>,
+    #[serde(default)]
+    pub withdrawn: bool, /* were both orders cancelled while pending? */
+}
@@ -12,7 +12,7 @@ use warp::http::StatusCode;
 use warp::reply::json;
 use warp::{Rejection, Reply};
 
-use crate::book::{Book, ExternalBook};
+use crate::book::{Book, BookError, ExternalBook};
 use crate::order::{ExternalOrder, Order, OrderId, OrderSide};
 use crate::rpc;
 use crate::state::OmeState;
@@ -45,6 +45,8 @@ pub struct CreateOrderRequest {
     #[serde(with = "ts_seconds")]
     created: DateTime<Utc>, /* creation time of the order */
     signed_data: String,    /* digital signature of the order */
+    #[serde(default)]
+    metadata: HashMap<String, String>, /* opaque tags passed through */
 }
 
 impl From<CreateOrderRequest> for ExternalOrder {
@@ -58,6 +60,7 @@ impl From<CreateOrderRequest> for ExternalOrder {
         let expiration: DateTime<Utc> = value.expiration;
         let created: DateTime<Utc> = value.created;
         let signed_data: String = value.signed_data;
+        let metadata: HashMap<String, String> = value.metadata;
 
         let user_bytes: Vec<u8> = user.as_ref().to_vec();
         let target_tracer_bytes: Vec<u8> = target_tracer.as_ref().to_vec();
@@ -78,6 +81,7 @@ impl From<CreateOrderRequest> for ExternalOrder {
                 chr.next();
                 chr.as_str().to_string()
             },
+            metadata,
         };
 
         order
@@ -280,6 +284,72 @@ pub async fn create_order_handler(
                 status,
             ))
         }
+        Err(BookError::SettlementBacklog) => {
+            warn!(
+                "Failed to create order {:?} as settlement is backlogged!",
+                new_order
+            );
+            let status: StatusCode = StatusCode::SERVICE_UNAVAILABLE;
+            let resp_body: OmeResponse = OmeResponse {
+                status: status.as_u16(),
+                message: "Settlement backlogged".to_string(),
+            };
+            Ok(warp::reply::with_status(
+                warp::reply::json(&resp_body),
+                status,
+            ))
+        }
+        Err(BookError::MarketHalted) => {
+            warn!(
+                "Failed to create order {:?} as the market is halted!",
+                new_order
+            );
+            let status: StatusCode = StatusCode::SERVICE_UNAVAILABLE;
+            let resp_body: OmeResponse = OmeResponse {
+                status: status.as_u16(),
+                message: "Market halted".to_string(),
+            };
+            Ok(warp::reply::with_status(
+                warp::reply::json(&resp_body),
+                status,
+            ))
+        }
+        Err(BookError::InvalidOrder) | Err(BookError::NotionalTooLarge) => {
+            warn!("Engine rejected order {:?} as invalid!", new_order);
+            let status: StatusCode = StatusCode::BAD_REQUEST;
+            let resp_body: OmeResponse = OmeResponse {
+                status: status.as_u16(),
+                message: "Invalid order".to_string(),
+            };
+            Ok(warp::reply::with_status(
+                warp::reply::json(&resp_body),
+                status,
+            ))
+        }
+        Err(e) if e.is_client_error() => {
+            warn!("Engine rejected order {:?}: {}", new_order, e);
+            let status: StatusCode = StatusCode::BAD_REQUEST;
+            let resp_body: OmeResponse = OmeResponse {
+                status: status.as_u16(),
+                message: format!("Order rejected: {}", e),
+            };
+            Ok(warp::reply::with_status(
+                warp::reply::json(&resp_body),
+                status,
+            ))
+        }
+        Err(e) if e.is_transient() => {
+            warn!("Failed to create order {:?} for now: {}", new_order, e);
+            let status: StatusCode = StatusCode::SERVICE_UNAVAILABLE;
+            let resp_body: OmeResponse = OmeResponse {
+                status: status.as_u16(),
+                message: format!("Temporarily unavailable: {}", e),
+            };
+            Ok(warp::reply::with_status(
+                warp::reply::json(&resp_body),
+                status,
+            ))
+        }
         Err(e) => {
             warn!("Failed to create order {:?}! Engine said: {}", new_order, e);
             let status: StatusCode = StatusCode::INTERNAL_SERVER_ERROR;
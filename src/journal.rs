@@ -0,0 +1,73 @@
+//! Contains logic for persisting order books as append-only order logs
+//!
+//! An order log is a sequence of records, each of which is a 4-byte
+//! big-endian length followed by the binary encoding of an order. Every record
+//! states an order as it stood after a mutation, so replaying the log in
+//! sequence reconstructs the book. Records with no remaining quantity mark
+//! orders that have left the book.
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+
+use crate::order::{Order, OrderParseError};
+
+/// The length in bytes of the prefix preceding each record
+const RECORD_PREFIX_LEN: usize = 4;
+
+fn encode(orders: &[Order]) -> io::Result<Vec<u8>> {
+    let mut data: Vec<u8> = vec![];
+
+    for order in orders {
+        let record: Vec<u8> = order.to_bytes();
+        data.write_u32::<BigEndian>(record.len() as u32)?;
+        data.extend(record);
+    }
+
+    Ok(data)
+}
+
+/// Writes a fresh order log to `path`, replacing any existing log
+pub fn write(path: &Path, orders: &[Order]) -> io::Result<()> {
+    let mut file: File = File::create(path)?;
+    file.write_all(&encode(orders)?)?;
+    file.sync_data()
+}
+
+/// Appends records for the provided orders to the order log at `path`
+pub fn append(path: &Path, orders: &[Order]) -> io::Result<()> {
+    let mut file: File =
+        OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(&encode(orders)?)?;
+    file.sync_data()
+}
+
+/// Decodes every record in the provided order log, oldest first
+///
+/// A truncated final record (e.g., from a crash mid-append) is ignored.
+pub fn decode(data: &[u8]) -> Result<Vec<Order>, OrderParseError> {
+    let mut orders: Vec<Order> = vec![];
+    let mut offset: usize = 0;
+
+    while offset + RECORD_PREFIX_LEN <= data.len() {
+        let len: usize =
+            BigEndian::read_u32(&data[offset..offset + RECORD_PREFIX_LEN])
+                as usize;
+        let start: usize = offset + RECORD_PREFIX_LEN;
+
+        if start + len > data.len() {
+            break;
+        }
+
+        orders.push(Order::from_bytes(&data[start..start + len])?);
+        offset = start + len;
+    }
+
+    if offset != data.len() {
+        warn!("Ignoring truncated order log record at offset {}", offset);
+    }
+
+    Ok(orders)
+}
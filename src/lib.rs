@@ -9,8 +9,22 @@ extern crate enum_display_derive;
 extern crate log;
 extern crate pretty_env_logger;
 
+pub mod amm;
 pub mod book;
+pub mod clock;
+pub mod event;
+pub mod fee;
+pub mod fill;
+pub mod journal;
+pub mod merkle;
 pub mod order;
+pub mod quote;
+pub mod replay;
+pub mod revalidation;
 pub mod rpc;
+pub mod settlement;
+pub mod shutdown;
+pub mod snapshot;
 pub mod state;
+pub mod tick;
 pub mod util;
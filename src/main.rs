@@ -17,13 +17,27 @@ extern crate enum_display_derive;
 extern crate log;
 extern crate pretty_env_logger;
 
+pub mod amm;
 pub mod args;
 pub mod book;
+pub mod clock;
+pub mod event;
+pub mod fee;
+pub mod fill;
 pub mod handler;
+pub mod journal;
+pub mod merkle;
 pub mod order;
+pub mod quote;
+pub mod replay;
+pub mod revalidation;
 pub mod rpc;
+pub mod settlement;
+pub mod shutdown;
+pub mod snapshot;
 pub mod state;
 pub mod tests;
+pub mod tick;
 pub mod util;
 
 #[cfg(test)]
@@ -0,0 +1,85 @@
+//! Contains logic for committing to the resting orders of a book with a
+//! Merkle tree
+//!
+//! Each leaf is the Keccak-256 digest of the Keccak-256 digest of the ABI
+//! encoding of an order's ID and remaining quantity. Hashing leaves twice
+//! keeps them distinct from internal nodes, whose preimages are also 64
+//! bytes long, so no internal node can be passed off as an order (as with
+//! OpenZeppelin's `StandardMerkleTree`). Pairs are hashed in ascending order,
+//! so proofs can be checked without knowing a leaf's position. An unpaired
+//! node is carried up to the next layer as is.
+use ethabi::Token;
+use web3::types::H256;
+
+use crate::order::Order;
+
+/// Returns the leaf committing to the provided order
+pub fn leaf(order: &Order) -> H256 {
+    let components: Vec<Token> = vec![
+        Token::FixedBytes(order.id.as_ref().to_vec()),
+        Token::Uint(order.remaining),
+    ];
+
+    let inner: [u8; 32] =
+        web3::signing::keccak256(&ethabi::encode(&components));
+    web3::signing::keccak256(&inner).into()
+}
+
+fn hash_pair(a: H256, b: H256) -> H256 {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut data: Vec<u8> = first.as_ref().to_vec();
+    data.extend_from_slice(second.as_ref());
+
+    web3::signing::keccak256(&data).into()
+}
+
+fn next_layer(layer: &[H256]) -> Vec<H256> {
+    layer
+        .chunks(2)
+        .map(|pair| match pair {
+            [a, b] => hash_pair(*a, *b),
+            [a] => *a,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Returns the root of the tree over the provided leaves, which is zero if
+/// there are none
+pub fn root(leaves: &[H256]) -> H256 {
+    let mut layer: Vec<H256> = leaves.to_vec();
+
+    while layer.len() > 1 {
+        layer = next_layer(&layer);
+    }
+
+    layer.first().copied().unwrap_or_else(H256::zero)
+}
+
+/// Returns the sibling hashes proving the inclusion of the leaf at `index`,
+/// from the bottom of the tree up
+pub fn proof(leaves: &[H256], mut index: usize) -> Option<Vec<H256>> {
+    if index >= leaves.len() {
+        return None;
+    }
+
+    let mut layer: Vec<H256> = leaves.to_vec();
+    let mut siblings: Vec<H256> = vec![];
+
+    while layer.len() > 1 {
+        if let Some(sibling) = layer.get(index ^ 1) {
+            siblings.push(*sibling);
+        }
+
+        layer = next_layer(&layer);
+        index /= 2;
+    }
+
+    Some(siblings)
+}
+
+/// Returns whether `proof` shows that `leaf` is included in the tree with
+/// the provided root
+pub fn verify(root: H256, leaf: H256, proof: &[H256]) -> bool {
+    proof.iter().fold(leaf, |acc, t| hash_pair(acc, *t)) == root
+}
@@ -1,18 +1,26 @@
 //! Contains logic and type definitions for orders
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::num::ParseIntError;
 use std::str::FromStr;
+use std::sync::Arc;
 
-use chrono::{DateTime, NaiveDateTime, ParseError, Utc};
+use chrono::{DateTime, Duration, NaiveDateTime, ParseError, TimeZone, Utc};
 use derive_more::Display;
 use ethabi::Token;
 use hex::FromHexError;
+use rlp::{DecoderError, Rlp, RlpStream};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use thiserror::Error;
 use web3::types::{Address, H256, U256};
 
+use crate::book::BookError;
+use crate::fee::apply_bps;
+use crate::util::{duration_opt_de, duration_opt_se, splitmix64};
+
 pub type OrderId = H256;
 
 /// Represents which side of the market an order is on
@@ -28,6 +36,38 @@ pub enum OrderSide {
     Ask,
 }
 
+impl OrderSide {
+    /// Returns the integer encoding of the side used by the Tracer contracts
+    ///
+    /// The mapping is `Bid` to 0 and `Ask` to 1, matching the contract ABI.
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            OrderSide::Bid => 0,
+            OrderSide::Ask => 1,
+        }
+    }
+
+    /// Returns the side corresponding to the Tracer contracts' integer
+    /// encoding
+    ///
+    /// This is the inverse of `OrderSide::as_u8`.
+    pub fn from_u8(value: u8) -> Result<OrderSide, BookError> {
+        match value {
+            0 => Ok(OrderSide::Bid),
+            1 => Ok(OrderSide::Ask),
+            _ => Err(BookError::InvalidSide),
+        }
+    }
+
+    /// Returns the opposing side of the market
+    pub fn opposite(&self) -> OrderSide {
+        match self {
+            OrderSide::Bid => OrderSide::Ask,
+            OrderSide::Ask => OrderSide::Bid,
+        }
+    }
+}
+
 impl FromStr for OrderSide {
     type Err = OrderParseError;
 
@@ -55,6 +95,51 @@ pub struct Order {
     pub expiration: DateTime<Utc>,
     pub created: DateTime<Utc>,
     pub signed_data: Vec<u8>,
+    #[serde(
+        default,
+        serialize_with = "duration_opt_se",
+        deserialize_with = "duration_opt_de"
+    )]
+    pub last_look: Option<Duration>, /* maker's window to reject matches */
+    #[serde(default)]
+    pub metadata: HashMap<String, String>, /* opaque to the engine */
+    #[serde(default)]
+    pub activate_at: Option<DateTime<Utc>>, /* held inactive until then */
+    #[serde(default)]
+    pub min_fill_qty: Option<U256>, /* least quantity to take per maker */
+    #[serde(
+        default,
+        serialize_with = "duration_opt_se",
+        deserialize_with = "duration_opt_de"
+    )]
+    pub ttl: Option<Duration>, /* lifetime from creation, if bounded */
+    #[serde(skip)]
+    pub on_expire: Option<RequoteFn>, /* invoked once the TTL lapses */
+}
+
+/// Represents a callback invoked when an order's TTL lapses, returning the
+/// order to quote in its place, if any
+///
+/// Callbacks are not part of an order's state: they are never serialised or
+/// journaled, and never affect equality.
+#[derive(Clone)]
+pub struct RequoteFn(pub Arc<RequoteCallback>);
+
+/// The signature of re-quote callbacks
+pub type RequoteCallback = dyn Fn(&Order) -> Option<Order> + Send + Sync;
+
+impl PartialEq for RequoteFn {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for RequoteFn {}
+
+impl fmt::Debug for RequoteFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RequoteFn")
+    }
 }
 
 impl fmt::Display for Order {
@@ -72,6 +157,140 @@ impl fmt::Display for Order {
     }
 }
 
+/// Represents an order held back from the book until the market trades
+/// through its trigger price
+///
+/// Sell stops trigger once the last traded price falls to or below the
+/// trigger, buy stops once it rises to or above it.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct StopOrder {
+    pub order: Order,
+    pub trigger: U256,
+}
+
+impl StopOrder {
+    /// Constructor for the `StopOrder` type
+    pub fn new(order: Order, trigger: U256) -> Self {
+        Self { order, trigger }
+    }
+
+    /// Returns whether the stop has been triggered by the provided last
+    /// traded price
+    pub fn is_triggered(&self, ltp: U256) -> bool {
+        match self.order.side {
+            OrderSide::Bid => ltp >= self.trigger,
+            OrderSide::Ask => ltp <= self.trigger,
+        }
+    }
+}
+
+/// Represents a condition on the depth of a book, met once at least
+/// `min_qty` rests at exactly `price` on `side`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct DepthTrigger {
+    pub side: OrderSide,
+    pub price: U256,
+    pub min_qty: U256,
+}
+
+/// Represents the random variance applied to the displayed size of each
+/// iceberg slice
+///
+/// Sizes vary uniformly by up to `bps` basis points either side of the
+/// iceberg's display size. The generator is seeded explicitly so that a
+/// sequence of refreshes can be reproduced.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct SliceJitter {
+    pub bps: u32,
+    pub state: u64, /* the seed, advanced on each refresh */
+}
+
+impl SliceJitter {
+    /// Constructor for the `SliceJitter` type
+    pub fn new(bps: u32, seed: u64) -> Self {
+        Self { bps, state: seed }
+    }
+
+    /// Returns the next jittered size around `display`, advancing the
+    /// generator
+    ///
+    /// Variances of more than the whole display size are floored at zero.
+    pub fn next_size(&mut self, display: U256) -> U256 {
+        let span: u64 = 2 * u64::from(self.bps) + 1;
+        let offset: u32 = (splitmix64(&mut self.state) % span) as u32;
+
+        if offset >= self.bps {
+            display.saturating_add(apply_bps(display, offset - self.bps))
+        } else {
+            display.saturating_sub(apply_bps(display, self.bps - offset))
+        }
+    }
+}
+
+/// Represents an order that displays only a slice of its quantity at a time
+///
+/// The template order's `remaining` quantity is the hidden reserve. Each
+/// time the displayed slice fills, a new slice of around `display` is cut
+/// from the reserve.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct IcebergOrder {
+    pub order: Order,
+    pub display: U256,
+    pub jitter: Option<SliceJitter>,
+    pub slice: Option<OrderId>, /* the currently displayed slice, if any */
+    pub refreshes: u64,
+}
+
+impl IcebergOrder {
+    /// Constructor for the `IcebergOrder` type
+    pub fn new(
+        order: Order,
+        display: U256,
+        jitter: Option<SliceJitter>,
+    ) -> Self {
+        Self {
+            order,
+            display,
+            jitter,
+            slice: None,
+            refreshes: 0,
+        }
+    }
+
+    /// Cuts the next slice from the hidden reserve
+    ///
+    /// Slices share every field of the template but their quantity and
+    /// their ID, which is derived from the template's ID and the number of
+    /// the slice. Returns `None` once the reserve is exhausted.
+    pub fn next_slice(&mut self) -> Option<Order> {
+        if self.order.remaining.is_zero() {
+            return None;
+        }
+
+        let size: U256 = match &mut self.jitter {
+            Some(jitter) => jitter.next_size(self.display),
+            None => self.display,
+        }
+        .max(U256::one())
+        .min(self.order.remaining);
+
+        let mut data: Vec<u8> = self.order.id.as_ref().to_vec();
+        data.extend_from_slice(&self.refreshes.to_be_bytes());
+        let id: OrderId = web3::signing::keccak256(&data).into();
+
+        self.order.remaining -= size;
+        self.refreshes += 1;
+        self.slice = Some(id);
+
+        Some(Order {
+            id,
+            quantity: size,
+            remaining: size,
+            ..self.order.clone()
+        })
+    }
+}
+
 /// Represents an error in interpreting a byte-level representation of an order
 #[derive(Clone, Copy, Debug, Error, Serialize, Deserialize)]
 pub enum OrderParseError {
@@ -80,6 +299,7 @@ pub enum OrderParseError {
     InvalidTimestamp,
     IntegerBounds,
     InvalidDecimal,
+    InvalidEncoding,
 }
 
 impl Display for OrderParseError {
@@ -87,6 +307,7 @@ impl Display for OrderParseError {
         match self {
             Self::InvalidHexadecimal => write!(f, "Invalid hexadecimal"),
             Self::InvalidSide => write!(f, "Invalid side"),
+            Self::InvalidEncoding => write!(f, "Invalid encoding"),
             _ => write!(f, "Unknown"),
         }
     }
@@ -110,6 +331,12 @@ impl From<ParseError> for OrderParseError {
     }
 }
 
+impl From<DecoderError> for OrderParseError {
+    fn from(_value: DecoderError) -> Self {
+        OrderParseError::InvalidEncoding
+    }
+}
+
 impl From<ParseIntError> for OrderParseError {
     fn from(_value: ParseIntError) -> Self {
         OrderParseError::IntegerBounds
@@ -126,10 +353,7 @@ pub fn order_id(
     created: DateTime<Utc>,
 ) -> OrderId {
     /* handle indirect conversions */
-    let side_num: U256 = U256::from(match side {
-        OrderSide::Bid => 0u8,
-        OrderSide::Ask => 1u8,
-    });
+    let side_num: U256 = U256::from(side.as_u8());
     let expiration_timestamp: U256 = U256::from(expiration.timestamp());
     let created_timestamp: U256 = U256::from(created.timestamp());
 
@@ -177,7 +401,289 @@ impl Order {
             expiration,
             created,
             signed_data,
+            last_look: None,
+            metadata: HashMap::new(),
+            activate_at: None,
+            min_fill_qty: None,
+            ttl: None,
+            on_expire: None,
+        }
+    }
+
+    /// Returns the unfilled quantity of the order as a new, unsigned order
+    /// created at `created`
+    ///
+    /// The new order keeps the original's terms and metadata but, as its ID
+    /// differs, must be signed afresh before it can settle.
+    pub fn remainder(&self, created: DateTime<Utc>) -> Order {
+        Order {
+            last_look: self.last_look,
+            metadata: self.metadata.clone(),
+            min_fill_qty: self.min_fill_qty,
+            ttl: self.ttl,
+            ..Order::new(
+                self.trader,
+                self.market,
+                self.side,
+                self.price,
+                self.remaining,
+                self.expiration,
+                created,
+                vec![],
+            )
+        }
+    }
+
+    /// Returns the order repriced at `price`
+    ///
+    /// The ID is derived afresh from the new terms, so the repriced order
+    /// must be signed afresh before it can settle. Everything else, including
+    /// what is left of the order, is kept.
+    pub fn with_price(&self, price: U256) -> Order {
+        Order {
+            id: order_id(
+                self.trader,
+                self.market,
+                self.side,
+                price,
+                self.quantity,
+                self.expiration,
+                self.created,
+            ),
+            price,
+            ..self.clone()
+        }
+    }
+
+    /// Returns whether the order's TTL has lapsed as of `now`
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        matches!(self.ttl, Some(ttl) if self.created + ttl <= now)
+    }
+
+    /// Returns the ABI tokens of the order as laid out by the Tracer
+    /// contracts' order struct
+    ///
+    /// This is the same layout the order's ID is derived from.
+    pub fn to_tokens(&self) -> Vec<Token> {
+        vec![
+            Token::Address(self.trader),
+            Token::Address(self.market),
+            Token::Uint(self.price),
+            Token::Uint(self.quantity),
+            Token::Uint(U256::from(self.side.as_u8())),
+            Token::Uint(U256::from(self.expiration.timestamp())),
+            Token::Uint(U256::from(self.created.timestamp())),
+        ]
+    }
+
+    /// Returns whether the order is still awaiting activation as of `now`
+    pub fn is_inactive(&self, now: DateTime<Utc>) -> bool {
+        matches!(self.activate_at, Some(t) if t > now)
+    }
+
+    /// Returns the binary encoding of the order
+    ///
+    /// The encoding is an RLP list of every field of the order. Metadata is
+    /// sorted by key so that equal orders always encode identically.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut metadata: Vec<(&String, &String)> =
+            self.metadata.iter().collect();
+        metadata.sort();
+
+        let mut stream: RlpStream = RlpStream::new_list(18);
+        stream
+            .append(&self.id)
+            .append(&self.trader)
+            .append(&self.market)
+            .append(&self.side.as_u8())
+            .append(&self.price)
+            .append(&self.quantity)
+            .append(&self.remaining)
+            .append(&(self.expiration.timestamp() as u64))
+            .append(&self.expiration.timestamp_subsec_nanos())
+            .append(&(self.created.timestamp() as u64))
+            .append(&self.created.timestamp_subsec_nanos())
+            .append(&self.signed_data)
+            .append(&self.last_look.map(|t| t.num_milliseconds() as u64));
+        stream.begin_list(metadata.len());
+        for (key, value) in metadata {
+            stream.begin_list(2).append(key).append(value);
+        }
+        stream
+            .append(&self.activate_at.map(|t| t.timestamp() as u64))
+            .append(&self.activate_at.map(|t| t.timestamp_subsec_nanos()))
+            .append(&self.min_fill_qty)
+            .append(&self.ttl.map(|t| t.num_milliseconds() as u64));
+
+        stream.out()
+    }
+
+    /// Constructs an order from its binary encoding
+    ///
+    /// This is the inverse of `Order::to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, OrderParseError> {
+        let rlp: Rlp = Rlp::new(bytes);
+
+        let side: OrderSide = match OrderSide::from_u8(rlp.val_at(3)?) {
+            Ok(t) => t,
+            Err(_e) => return Err(OrderParseError::InvalidSide),
+        };
+
+        let timestamp =
+            |index: usize| -> Result<DateTime<Utc>, OrderParseError> {
+                let secs: u64 = rlp.val_at(index)?;
+                let nanos: u32 = rlp.val_at(index + 1)?;
+
+                match Utc.timestamp_opt(secs as i64, nanos).single() {
+                    Some(t) => Ok(t),
+                    None => Err(OrderParseError::InvalidTimestamp),
+                }
+            };
+
+        let mut metadata: HashMap<String, String> = HashMap::new();
+        for entry in rlp.at(13)?.iter() {
+            metadata.insert(entry.val_at(0)?, entry.val_at(1)?);
         }
+
+        Ok(Self {
+            id: rlp.val_at(0)?,
+            trader: rlp.val_at(1)?,
+            market: rlp.val_at(2)?,
+            side,
+            price: rlp.val_at(4)?,
+            quantity: rlp.val_at(5)?,
+            remaining: rlp.val_at(6)?,
+            expiration: timestamp(7)?,
+            created: timestamp(9)?,
+            signed_data: rlp.val_at(11)?,
+            last_look: rlp
+                .val_at::<Option<u64>>(12)?
+                .map(|t| Duration::milliseconds(t as i64)),
+            metadata,
+            activate_at: match (
+                rlp.val_at::<Option<u64>>(14)?,
+                rlp.val_at::<Option<u32>>(15)?,
+            ) {
+                (Some(secs), Some(nanos)) => {
+                    match Utc.timestamp_opt(secs as i64, nanos).single() {
+                        Some(t) => Some(t),
+                        None => return Err(OrderParseError::InvalidTimestamp),
+                    }
+                }
+                _ => None,
+            },
+            min_fill_qty: rlp.val_at(16)?,
+            ttl: rlp
+                .val_at::<Option<u64>>(17)?
+                .map(|t| Duration::milliseconds(t as i64)),
+            on_expire: None,
+        })
+    }
+
+    /// Returns a JSON Schema describing the serialised form of an order
+    ///
+    /// Integers are serialised as `0x`-prefixed hexadecimal strings and
+    /// timestamps as RFC 3339 strings. The schema requires a non-zero
+    /// quantity, so clients can reject such orders before submitting them.
+    /// Fields that deserialise to a default when absent are not required.
+    pub fn json_schema() -> serde_json::Value {
+        let uint = |description: &str| {
+            json!({
+                "type": "string",
+                "pattern": "^0x(0|[1-9a-f][0-9a-f]*)$",
+                "description": description,
+            })
+        };
+        let nullable_uint = |description: &str| {
+            json!({
+                "type": ["string", "null"],
+                "pattern": "^0x(0|[1-9a-f][0-9a-f]*)$",
+                "description": description,
+            })
+        };
+        let address = |description: &str| {
+            json!({
+                "type": "string",
+                "pattern": "^0x[0-9a-f]{40}$",
+                "description": description,
+            })
+        };
+        let timestamp = |description: &str| {
+            json!({
+                "type": "string",
+                "format": "date-time",
+                "description": description,
+            })
+        };
+
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Order",
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "pattern": "^0x[0-9a-f]{64}$",
+                    "description": "Keccak-256 digest of the order's fields",
+                },
+                "trader": address("address of the trader"),
+                "market": address("address of the Tracer market"),
+                "side": {
+                    "type": "string",
+                    "enum": ["Bid", "Ask"],
+                },
+                "price": uint("price per contract"),
+                "quantity": {
+                    "type": "string",
+                    "pattern": "^0x[1-9a-f][0-9a-f]*$",
+                    "description": "number of contracts, non-zero",
+                },
+                "remaining": uint("number of contracts left unfilled"),
+                "expiration": timestamp("time after which the order lapses"),
+                "created": timestamp("time the order was created"),
+                "signed_data": {
+                    "type": "array",
+                    "items": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 255,
+                    },
+                },
+                "last_look": {
+                    "type": ["integer", "null"],
+                    "minimum": 0,
+                    "description": "maker's window to reject matches, in ms",
+                },
+                "metadata": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                },
+                "activate_at": {
+                    "type": ["string", "null"],
+                    "format": "date-time",
+                    "description": "time before which the order is inactive",
+                },
+                "min_fill_qty": nullable_uint("least quantity per maker"),
+                "ttl": {
+                    "type": ["integer", "null"],
+                    "minimum": 0,
+                    "description": "lifetime from creation, in ms",
+                },
+            },
+            "required": [
+                "id",
+                "trader",
+                "market",
+                "side",
+                "price",
+                "quantity",
+                "remaining",
+                "expiration",
+                "created",
+                "signed_data",
+            ],
+            "additionalProperties": false,
+        })
     }
 }
 
@@ -193,6 +699,8 @@ pub struct ExternalOrder {
     pub expiration: String,
     pub created: String,
     pub signed_data: String,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
 }
 
 impl From<Order> for ExternalOrder {
@@ -211,6 +719,7 @@ impl From<Order> for ExternalOrder {
             expiration: value.expiration.timestamp().to_string(),
             created: value.created.timestamp().to_string(),
             signed_data: "0x".to_string() + &hex::encode(value.signed_data),
+            metadata: value.metadata,
         }
     }
 }
@@ -287,6 +796,12 @@ impl TryFrom<ExternalOrder> for Order {
             expiration,
             created,
             signed_data,
+            last_look: None,
+            metadata: value.metadata,
+            activate_at: None,
+            min_fill_qty: None,
+            ttl: None,
+            on_expire: None,
         })
     }
 }
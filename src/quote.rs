@@ -0,0 +1,41 @@
+//! Contains type definitions for the quotes makers offer to takers in
+//! request-for-quote (RFQ) flows
+use serde::{Deserialize, Serialize};
+use web3::types::{H256, U256};
+
+use crate::order::{Order, OrderId, OrderSide};
+
+pub type QuoteId = H256;
+
+/// Represents a firm offer by a resting maker to fill the entire quantity a
+/// taker requested, at the maker's price
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Quote {
+    pub id: QuoteId,
+    pub maker: OrderId,
+    pub side: OrderSide, /* of the requesting taker */
+    pub price: U256,
+    pub quantity: U256,
+}
+
+impl Quote {
+    /// Constructor for the `Quote` type
+    ///
+    /// The quote's ID is derived from the maker's ID, the quantity and the
+    /// number of the request, so quotes from different requests never clash.
+    pub fn new(maker: &Order, quantity: U256, round: u64) -> Self {
+        let mut data: Vec<u8> = maker.id.as_ref().to_vec();
+        let mut amount: [u8; 32] = [0; 32];
+        quantity.to_big_endian(&mut amount);
+        data.extend_from_slice(&amount);
+        data.extend_from_slice(&round.to_be_bytes());
+
+        Self {
+            id: web3::signing::keccak256(&data).into(),
+            maker: maker.id,
+            side: maker.side.opposite(),
+            price: maker.price,
+            quantity,
+        }
+    }
+}
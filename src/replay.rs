@@ -0,0 +1,85 @@
+//! Contains logic for recorded market data files, used to drive order books
+//! for backtesting
+//!
+//! A recording is line-delimited text. The first line names the market, and
+//! each subsequent line is a single operation:
+//!
+//! ```text
+//! market <address>
+//! submit <binary order encoding>
+//! cancel <order ID>
+//! ```
+//!
+//! Every value is hexadecimal, optionally prefixed with `0x`. Blank lines and
+//! lines starting with `#` are ignored.
+use web3::types::{Address, H256};
+
+use crate::order::{Order, OrderId, OrderParseError};
+
+/// Represents a single recorded operation on an order book
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ReplayOp {
+    Submit(Box<Order>),
+    Cancel(OrderId),
+}
+
+impl ReplayOp {
+    /// Returns the line recording this operation
+    pub fn to_line(&self) -> String {
+        match self {
+            ReplayOp::Submit(order) => {
+                format!("submit 0x{}", hex::encode(order.to_bytes()))
+            }
+            ReplayOp::Cancel(id) => format!("cancel 0x{}", hex::encode(id)),
+        }
+    }
+}
+
+/// Returns the header line naming the market a recording is for
+pub fn header(market: Address) -> String {
+    format!("market 0x{}", hex::encode(market))
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, OrderParseError> {
+    Ok(hex::decode(value.trim_start_matches("0x"))?)
+}
+
+/// Parses a recording into its market and operations, in order
+pub fn parse(text: &str) -> Result<(Address, Vec<ReplayOp>), OrderParseError> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|t| !t.is_empty() && !t.starts_with('#'))
+        .map(|t| t.split_whitespace().collect::<Vec<&str>>());
+
+    let market: Address = match lines.next().as_deref() {
+        Some(["market", address]) => {
+            let bytes: Vec<u8> = decode_hex(address)?;
+            if bytes.len() != Address::len_bytes() {
+                return Err(OrderParseError::InvalidEncoding);
+            }
+            Address::from_slice(&bytes)
+        }
+        _ => return Err(OrderParseError::InvalidEncoding),
+    };
+
+    let mut ops: Vec<ReplayOp> = vec![];
+
+    for line in lines {
+        ops.push(match line.as_slice() {
+            ["submit", order] => ReplayOp::Submit(Box::new(Order::from_bytes(
+                &decode_hex(order)?,
+            )?)),
+            ["cancel", id] => {
+                let bytes: Vec<u8> = decode_hex(id)?;
+                if bytes.len() != H256::len_bytes() {
+                    return Err(OrderParseError::InvalidEncoding);
+                }
+                ReplayOp::Cancel(H256::from_slice(&bytes))
+            }
+            _ => return Err(OrderParseError::InvalidEncoding),
+        });
+    }
+
+    Ok((market, ops))
+}
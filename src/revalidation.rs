@@ -0,0 +1,56 @@
+//! Contains the interface for re-validating long-resting orders before they
+//! are allowed to match
+use std::fmt;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::order::Order;
+
+/// Represents a check that a resting order may still be executed, such as
+/// re-verifying its signature or its trader's allowance
+pub trait Revalidator: Send + Sync {
+    /// Returns whether the order remains valid
+    fn revalidate(&self, order: &Order) -> bool;
+}
+
+/// Represents a policy of re-validating resting orders older than `max_age`
+/// whenever they are about to match
+///
+/// The revalidator is not part of a book's state, so it never affects
+/// equality.
+#[derive(Clone)]
+pub struct RevalidationPolicy {
+    pub max_age: Duration,
+    pub revalidator: Arc<dyn Revalidator>,
+}
+
+impl RevalidationPolicy {
+    /// Constructor for the `RevalidationPolicy` type
+    pub fn new(max_age: Duration, revalidator: Arc<dyn Revalidator>) -> Self {
+        Self {
+            max_age,
+            revalidator,
+        }
+    }
+
+    /// Returns whether the order may match as of `now`, re-validating it
+    /// only if it is at least `max_age` old
+    pub fn permits(&self, order: &Order, now: DateTime<Utc>) -> bool {
+        now - order.created < self.max_age || self.revalidator.revalidate(order)
+    }
+}
+
+impl PartialEq for RevalidationPolicy {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for RevalidationPolicy {}
+
+impl fmt::Debug for RevalidationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RevalidationPolicy({})", self.max_age)
+    }
+}
@@ -0,0 +1,73 @@
+//! Contains the interface to settlement venues able to settle both legs of
+//! a fill atomically
+use std::fmt;
+use std::sync::Arc;
+
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+use crate::book::BookError;
+use crate::fill::Fill;
+
+/// Represents one counterparty's side of a fill
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Display, Serialize, Deserialize,
+)]
+pub enum SettlementLeg {
+    Maker,
+    Taker,
+}
+
+/// Represents a venue settling fills in two phases
+///
+/// Both legs of a fill are prepared before either is committed. Should
+/// either leg fail to prepare, or the commit fail, the fill is aborted and
+/// neither leg may be settled.
+pub trait Settlement: Send + Sync {
+    /// Prepares the transfers of one leg of a fill, without settling them
+    fn prepare(&self, fill: &Fill, leg: SettlementLeg)
+        -> Result<(), BookError>;
+
+    /// Settles both prepared legs of a fill together
+    fn commit(&self, fill: &Fill) -> Result<(), BookError>;
+
+    /// Discards whatever has been prepared for a fill
+    fn abort(&self, fill: &Fill);
+}
+
+/// Represents a settlement venue shared between an order book and its owner
+///
+/// The venue is not part of a book's state, so it never affects equality.
+#[derive(Clone)]
+pub struct SharedSettlement(pub Arc<dyn Settlement>);
+
+impl SharedSettlement {
+    /// Settles a fill in two phases, aborting it if either phase fails
+    pub fn settle(&self, fill: &Fill) -> Result<(), BookError> {
+        let outcome: Result<(), BookError> = self
+            .0
+            .prepare(fill, SettlementLeg::Maker)
+            .and_then(|_| self.0.prepare(fill, SettlementLeg::Taker))
+            .and_then(|_| self.0.commit(fill));
+
+        if outcome.is_err() {
+            self.0.abort(fill);
+        }
+
+        outcome
+    }
+}
+
+impl PartialEq for SharedSettlement {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SharedSettlement {}
+
+impl fmt::Debug for SharedSettlement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SharedSettlement")
+    }
+}
@@ -0,0 +1,22 @@
+//! Contains type definitions for the state an order book leaves behind when
+//! shut down gracefully
+use serde::{Deserialize, Serialize};
+use web3::types::{Address, U256};
+
+use crate::fill::{Fill, PendingFill};
+use crate::order::Order;
+
+/// Represents everything needed to bring an order book back after a restart
+///
+/// Alongside the resting orders, this includes every fill that had not yet
+/// been settled, so that settlement can be retried once the book resumes.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ShutdownState {
+    pub market: Address,
+    pub orders: Vec<Order>, /* resting orders in priority order, bids first */
+    pub inactive: Vec<Order>, /* orders awaiting activation */
+    pub pending_fills: Vec<PendingFill>, /* fills awaiting last look */
+    pub settlements: Vec<Fill>, /* fills awaiting the executioner */
+    pub tape: Vec<Fill>,
+    pub ltp: U256,
+}
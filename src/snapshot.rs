@@ -0,0 +1,252 @@
+//! Contains type definitions for aggregated (L2) and market-by-order (L3)
+//! views of the order book
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use web3::types::{Address, U256};
+
+use crate::order::{OrderId, OrderSide};
+use crate::util::format_fixed;
+
+/// Represents an aggregated view of the order book at a point in time
+///
+/// Each side maps a price level to the total remaining quantity resting at
+/// that level.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct BookSnapshot {
+    pub bids: BTreeMap<U256, U256>, /* buy-side */
+    pub asks: BTreeMap<U256, U256>, /* sell-side */
+}
+
+/// Represents how prices and quantities are rendered for display
+///
+/// Raw values carry `base_decimals` implied decimal places, and are rounded
+/// half up to the configured number of places.
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize,
+)]
+pub struct DisplayConfig {
+    pub price_decimals: u32,
+    pub qty_decimals: u32,
+    #[serde(default)]
+    pub base_decimals: u32, /* implied decimal places of raw values */
+}
+
+impl DisplayConfig {
+    /// Constructor for the `DisplayConfig` type, for raw values with no
+    /// implied decimal places
+    pub fn new(price_decimals: u32, qty_decimals: u32) -> Self {
+        Self {
+            price_decimals,
+            qty_decimals,
+            base_decimals: 0,
+        }
+    }
+
+    /// Treats raw values as carrying the provided implied decimal places
+    pub fn with_base_decimals(self, base_decimals: u32) -> Self {
+        Self {
+            base_decimals,
+            ..self
+        }
+    }
+
+    /// Renders the provided price
+    pub fn format_price(&self, price: U256) -> String {
+        format_fixed(price, self.base_decimals, self.price_decimals)
+    }
+
+    /// Renders the provided quantity
+    pub fn format_quantity(&self, quantity: U256) -> String {
+        format_fixed(quantity, self.base_decimals, self.qty_decimals)
+    }
+}
+
+/// Represents a single resting order within a market-by-order snapshot
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct L3Entry {
+    pub id: OrderId,
+    pub trader: Address,
+    pub price: U256,
+    pub remaining: U256,
+    pub rank: usize, /* position in the queue at its price level */
+}
+
+/// Represents a market-by-order view of the order book at a point in time
+///
+/// Each side lists every resting order in priority order (best price first,
+/// then by time).
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct L3Snapshot {
+    pub bids: Vec<L3Entry>, /* buy-side */
+    pub asks: Vec<L3Entry>, /* sell-side */
+}
+
+impl L3Snapshot {
+    /// Returns the entries of the specified side
+    pub fn side(&self, side: OrderSide) -> &[L3Entry] {
+        match side {
+            OrderSide::Bid => &self.bids,
+            OrderSide::Ask => &self.asks,
+        }
+    }
+
+    /// Returns the total number of resting orders across both sides
+    pub fn len(&self) -> usize {
+        self.bids.len() + self.asks.len()
+    }
+
+    /// Returns whether both sides are empty
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+}
+
+/// Represents a change to a single price level between two snapshots
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum LevelChange {
+    Add {
+        side: OrderSide,
+        price: U256,
+        quantity: U256,
+    },
+    Remove {
+        side: OrderSide,
+        price: U256,
+    },
+    Change {
+        side: OrderSide,
+        price: U256,
+        quantity: U256,
+    },
+}
+
+/// Represents the minimal set of level changes taking one snapshot to another
+///
+/// Changes are ordered bid-side first, then ask-side, each by ascending price.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct BookDelta {
+    pub changes: Vec<LevelChange>,
+}
+
+/// Represents a delta published on a book's delta feed
+///
+/// Sequence numbers increase by one with each delta, so a gap means a delta
+/// was missed and the client should start again from a fresh snapshot.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct SequencedDelta {
+    pub sequence: u64,
+    pub delta: BookDelta,
+}
+
+impl BookDelta {
+    /// Returns whether the delta contains no changes at all
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl BookSnapshot {
+    /// Returns a reference to the levels of the specified side
+    pub fn side(&self, side: OrderSide) -> &BTreeMap<U256, U256> {
+        match side {
+            OrderSide::Bid => &self.bids,
+            OrderSide::Ask => &self.asks,
+        }
+    }
+
+    /// Returns a mutable reference to the levels of the specified side
+    pub fn side_mut(&mut self, side: OrderSide) -> &mut BTreeMap<U256, U256> {
+        match side {
+            OrderSide::Bid => &mut self.bids,
+            OrderSide::Ask => &mut self.asks,
+        }
+    }
+
+    /// Renders the snapshot as a price ladder, one level per line
+    ///
+    /// Levels run from the highest price to the lowest, so asks are followed
+    /// by bids and the spread sits in the middle.
+    pub fn to_ladder_string(&self, config: &DisplayConfig) -> String {
+        let mut ladder: String = String::new();
+
+        for (side, levels) in
+            [(OrderSide::Ask, &self.asks), (OrderSide::Bid, &self.bids)].iter()
+        {
+            for (price, quantity) in levels.iter().rev() {
+                ladder.push_str(&format!(
+                    "{} {} {}\n",
+                    side,
+                    config.format_price(*price),
+                    config.format_quantity(*quantity)
+                ));
+            }
+        }
+
+        ladder
+    }
+
+    /// Computes the delta that takes this snapshot to `other`
+    ///
+    /// The diff is exact: applying the result to `self` yields `other`.
+    pub fn diff(&self, other: &BookSnapshot) -> BookDelta {
+        let mut changes: Vec<LevelChange> = vec![];
+
+        for side in [OrderSide::Bid, OrderSide::Ask].iter().cloned() {
+            let before: &BTreeMap<U256, U256> = self.side(side);
+            let after: &BTreeMap<U256, U256> = other.side(side);
+
+            /* walk the union of both sides' prices in ascending order */
+            let mut prices: Vec<U256> =
+                before.keys().chain(after.keys()).cloned().collect();
+            prices.sort();
+            prices.dedup();
+
+            for price in prices {
+                match (before.get(&price), after.get(&price)) {
+                    (None, Some(quantity)) => changes.push(LevelChange::Add {
+                        side,
+                        price,
+                        quantity: *quantity,
+                    }),
+                    (Some(_), None) => {
+                        changes.push(LevelChange::Remove { side, price })
+                    }
+                    (Some(old), Some(new)) if old != new => {
+                        changes.push(LevelChange::Change {
+                            side,
+                            price,
+                            quantity: *new,
+                        })
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        BookDelta { changes }
+    }
+
+    /// Applies the provided delta to this snapshot in place
+    pub fn apply(&mut self, delta: &BookDelta) {
+        for change in delta.changes.iter() {
+            match *change {
+                LevelChange::Add {
+                    side,
+                    price,
+                    quantity,
+                }
+                | LevelChange::Change {
+                    side,
+                    price,
+                    quantity,
+                } => {
+                    self.side_mut(side).insert(price, quantity);
+                }
+                LevelChange::Remove { side, price } => {
+                    self.side_mut(side).remove(&price);
+                }
+            }
+        }
+    }
+}
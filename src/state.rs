@@ -4,7 +4,7 @@ use std::fs::read_to_string;
 use std::path::Path;
 
 use serde::{Deserialize, Serialize};
-use web3::types::Address;
+use web3::types::{Address, U256};
 
 use crate::book::Book;
 
@@ -28,10 +28,17 @@ impl OmeState {
             Err(_e) => return None,
         };
 
-        match serde_json::from_str(&dump_data) {
-            Ok(t) => Some(t),
-            Err(_e) => None,
+        let mut state: Self = match serde_json::from_str(&dump_data) {
+            Ok(t) => t,
+            Err(_e) => return None,
+        };
+
+        /* older dumps lack some of the metadata books now cache */
+        for book in state.books.values_mut() {
+            book.recompute_cache();
         }
+
+        Some(state)
     }
 
     /// Returns a reference to the mapping from tickers to `Book` types
@@ -55,6 +62,17 @@ impl OmeState {
         self.books.insert(*book.market(), book);
     }
 
+    /// Returns the total fees the trader has been charged on each market
+    ///
+    /// Markets on which the trader has paid no fees are omitted.
+    pub fn fee_summary(&self, trader: Address) -> HashMap<Address, U256> {
+        self.books
+            .iter()
+            .map(|(market, book)| (*market, book.fees_paid(trader)))
+            .filter(|(_, fees)| !fees.is_zero())
+            .collect()
+    }
+
     /// Remove an order book from the OME
     pub fn remove_book(&mut self, market: Address) -> Option<Book> {
         self.books.remove(&market)
@@ -1,9 +1,12 @@
 #[cfg(test)]
 mod order_tests {
-    use chrono::Utc;
+    use chrono::{Duration, Utc};
+    use serde_json::json;
     use web3::types::{Address, U256};
 
-    use crate::order::{Order, OrderSide};
+    use crate::book::BookError;
+    use crate::journal;
+    use crate::order::{Order, OrderSide, SliceJitter};
 
     #[test]
     pub fn basic_order_info() {
@@ -30,13 +33,256 @@ mod order_tests {
         assert_eq!(order.quantity, amount);
         assert!(order.signed_data.is_empty());
     }
+
+    #[test]
+    pub fn side_contract_encoding() {
+        assert_eq!(OrderSide::Bid.as_u8(), 0);
+        assert_eq!(OrderSide::Ask.as_u8(), 1);
+
+        for side in [OrderSide::Bid, OrderSide::Ask].iter() {
+            assert_eq!(OrderSide::from_u8(side.as_u8()), Ok(*side));
+        }
+
+        assert_eq!(OrderSide::from_u8(2), Err(BookError::InvalidSide));
+    }
+
+    #[test]
+    pub fn binary_encoding_roundtrip() {
+        let mut order = Order::new(
+            Address::from_low_u64_be(1),
+            Address::zero(),
+            OrderSide::Ask,
+            U256::from_dec_str("1150000000000000000").unwrap(),
+            U256::from_dec_str("1200000000000000000").unwrap(),
+            Utc::now(),
+            Utc::now(),
+            vec![0xde, 0xad],
+        );
+        order.remaining = 7.into();
+        order.last_look = Some(Duration::milliseconds(1500));
+        order.activate_at = Some(Utc::now());
+        order.min_fill_qty = Some(3.into());
+        order.ttl = Some(Duration::seconds(30));
+        order
+            .metadata
+            .insert("strategy".to_string(), "mm".to_string());
+
+        let decoded = Order::from_bytes(&order.to_bytes()).unwrap();
+
+        assert_eq!(decoded, order);
+        assert!(Order::from_bytes(&[0xc0]).is_err());
+    }
+
+    #[test]
+    pub fn order_log_ignores_truncated_record() {
+        let order = Order::new(
+            Address::from_low_u64_be(1),
+            Address::zero(),
+            OrderSide::Bid,
+            5.into(),
+            10.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        let path =
+            std::env::temp_dir().join(format!("{}.log", Address::random()));
+
+        journal::write(&path, std::slice::from_ref(&order)).unwrap();
+        journal::append(&path, std::slice::from_ref(&order)).unwrap();
+
+        let mut data: Vec<u8> = std::fs::read(&path).unwrap();
+        data.truncate(data.len() - 1);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(journal::decode(&data).unwrap(), vec![order]);
+    }
+
+    #[test]
+    pub fn order_json_schema() {
+        let schema = Order::json_schema();
+        let properties = &schema["properties"];
+
+        assert_eq!(properties["price"]["type"], "string");
+        assert_eq!(properties["quantity"]["type"], "string");
+        assert_eq!(properties["side"]["type"], "string");
+        assert_eq!(properties["side"]["enum"], json!(["Bid", "Ask"]));
+
+        /* the schema describes exactly the fields an order serialises to */
+        let order = Order::new(
+            Address::from_low_u64_be(1),
+            Address::zero(),
+            OrderSide::Bid,
+            5.into(),
+            10.into(),
+            Utc::now(),
+            Utc::now(),
+            vec![],
+        );
+        let serialised = serde_json::to_value(&order).unwrap();
+        let mut fields: Vec<&String> =
+            serialised.as_object().unwrap().keys().collect();
+        let mut described: Vec<&String> =
+            properties.as_object().unwrap().keys().collect();
+        fields.sort();
+        described.sort();
+
+        assert_eq!(fields, described);
+        assert_eq!(serialised["quantity"], "0xa");
+
+        /* only the fields without a default are required */
+        let required: Vec<&str> = schema["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t.as_str().unwrap())
+            .collect();
+        assert_eq!(
+            required,
+            vec![
+                "id",
+                "trader",
+                "market",
+                "side",
+                "price",
+                "quantity",
+                "remaining",
+                "expiration",
+                "created",
+                "signed_data",
+            ]
+        );
+
+        let mut minimal = serialised.clone();
+        for field in fields.iter().filter(|t| !required.contains(&t.as_str())) {
+            minimal.as_object_mut().unwrap().remove(field.as_str());
+        }
+        let parsed: Order = serde_json::from_value(minimal).unwrap();
+        assert_eq!(parsed, order);
+    }
+
+    #[test]
+    pub fn slice_jitter_wider_than_display() {
+        let mut jitter = SliceJitter::new(30_000, 7);
+        let display: U256 = 10.into();
+
+        /* variances beyond the whole display size floor at zero */
+        let sizes: Vec<U256> =
+            (0..100).map(|_| jitter.next_size(display)).collect();
+
+        assert!(sizes.iter().any(|t| t.is_zero()));
+        assert!(sizes.iter().all(|t| *t <= U256::from(40)));
+    }
+}
+
+#[cfg(test)]
+mod fill_tests {
+    use chrono::Utc;
+    use web3::types::{Address, U256};
+
+    use crate::fill::Fill;
+    use crate::order::{Order, OrderSide};
+
+    #[test]
+    pub fn settlement_calldata() {
+        let order = |trader: u64, side: OrderSide| {
+            Order::new(
+                Address::from_low_u64_be(trader),
+                Address::zero(),
+                side,
+                96.into(),
+                5.into(),
+                Utc::now(),
+                Utc::now(),
+                vec![],
+            )
+        };
+        let fill = Fill::new(
+            order(4, OrderSide::Ask),
+            order(10, OrderSide::Bid),
+            96.into(),
+            5.into(),
+            480.into(),
+            Utc::now(),
+        );
+        let selector: [u8; 4] = [0xde, 0xad, 0xbe, 0xef];
+
+        let calldata: Vec<u8> = fill.to_calldata(selector);
+
+        /* two static 7-word tuples followed by price and quantity */
+        assert_eq!(calldata.len(), 4 + 32 * 16);
+        assert_eq!(calldata[..4], selector);
+        assert_eq!(
+            U256::from_big_endian(&calldata[4 + 32 * 14..4 + 32 * 15]),
+            96.into()
+        );
+        assert_eq!(U256::from_big_endian(&calldata[4 + 32 * 15..]), 5.into());
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use crate::book::BookError;
+
+    #[test]
+    pub fn error_classification() {
+        assert!(BookError::Web3Error.is_transient());
+        assert!(BookError::SettlementBacklog.is_transient());
+        assert!(BookError::OrderBusy.is_transient());
+        assert!(!BookError::InvalidOrder.is_transient());
+        assert!(!BookError::PersistenceError.is_transient());
+
+        assert!(BookError::InvalidOrder.is_client_error());
+        assert!(BookError::SelfTrade.is_client_error());
+        assert!(BookError::OrderNotFound.is_client_error());
+        assert!(BookError::SpreadTooNarrow.is_client_error());
+        assert!(BookError::TimestampSkew.is_client_error());
+        assert!(BookError::WouldMatch.is_client_error());
+        assert!(!BookError::WouldMatch.is_transient());
+        assert!(BookError::MarketHalted.is_transient());
+        assert!(!BookError::MarketHalted.is_client_error());
+        assert!(BookError::OneSidedBook.is_transient());
+        assert!(!BookError::OneSidedBook.is_client_error());
+        assert!(BookError::InsufficientLiquidity.is_transient());
+        assert!(!BookError::InsufficientLiquidity.is_client_error());
+        assert!(BookError::TooManyOpenOrders.is_client_error());
+        assert!(!BookError::TooManyOpenOrders.is_transient());
+        assert!(!BookError::Web3Error.is_client_error());
+        assert!(!BookError::InvariantViolation.is_client_error());
+    }
+}
+
+#[cfg(test)]
+mod util_tests {
+    use web3::types::U256;
+
+    use crate::util::u256_sqrt;
+
+    #[test]
+    pub fn integer_square_root() {
+        let roots: Vec<(u64, u64)> =
+            vec![(0, 0), (1, 1), (2, 1), (3, 1), (4, 2), (15, 3), (16, 4)];
+
+        for (x, root) in roots {
+            assert_eq!(u256_sqrt(x.into()), root.into(), "sqrt({})", x);
+        }
+
+        /* 2^128 - 1 is the largest root whose square fits */
+        let max_root: U256 = U256::from(u128::MAX);
+        assert_eq!(u256_sqrt(U256::MAX), max_root);
+    }
 }
 
 #[cfg(test)]
 mod state_tests {
-    use web3::types::Address;
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+    use web3::types::{Address, U256};
 
     use crate::book::Book;
+    use crate::fill::Fill;
+    use crate::order::{Order, OrderSide};
     use crate::OmeState;
 
     #[test]
@@ -75,4 +321,115 @@ mod state_tests {
         state.remove_book(market);
         assert!(state.books().is_empty());
     }
+
+    #[test]
+    pub fn load_dump_predating_book_extensions() {
+        /* as written by the OME before books tracked more than their levels */
+        let zero: String = format!("0x{}", "0".repeat(40));
+        let dump: String = format!(
+            r#"{{"books": {{"{market}": {{
+                "market": "{market}",
+                "bids": {{"0x5f": [{{
+                    "id": "0x{id}",
+                    "trader": "0x{trader}",
+                    "market": "{market}",
+                    "side": "Bid",
+                    "price": "0x5f",
+                    "quantity": "0xa",
+                    "remaining": "0x6",
+                    "expiration": "2020-09-14T12:26:40Z",
+                    "created": "2020-09-13T12:26:40Z",
+                    "signed_data": []
+                }}]}},
+                "asks": {{}},
+                "LTP": 96,
+                "depth": [1, 0],
+                "crossed": false,
+                "spread": 0
+            }}}}}}"#,
+            market = zero,
+            id = "ab".repeat(32),
+            trader = "01".repeat(20),
+        );
+        let path =
+            std::env::temp_dir().join(format!("{}.json", Address::random()));
+        std::fs::write(&path, dump).unwrap();
+
+        let state: OmeState = OmeState::from_dumpfile(&path)
+            .expect("Failed to load dump predating book extensions");
+        std::fs::remove_file(&path).unwrap();
+
+        let book: &Book = state.book(Address::zero()).unwrap();
+        assert_eq!(book.ltp(), 96.into());
+        assert_eq!(book.depth(), (1, 0));
+        assert_eq!(book.total_bid_volume(), 6.into());
+        assert_eq!(book.contract_size(), U256::one());
+        assert_eq!(book.max_cascade_iterations, 16);
+        assert!(book.tape().is_empty());
+
+        let order: &Order = book.next_to_fill(OrderSide::Bid).unwrap();
+        assert_eq!(order.trader, Address::repeat_byte(1));
+        assert!(order.last_look.is_none());
+        assert!(order.metadata.is_empty());
+    }
+
+    #[test]
+    pub fn fee_summary() {
+        let trader: Address = Address::from_low_u64_be(1);
+        let other: Address = Address::from_low_u64_be(2);
+        let fill = |market: Address, maker: Address, taker: Address| {
+            let order = |trader: Address, side: OrderSide| {
+                Order::new(
+                    trader,
+                    market,
+                    side,
+                    100.into(),
+                    5.into(),
+                    Utc::now(),
+                    Utc::now(),
+                    vec![],
+                )
+            };
+
+            Fill {
+                maker_fee: 2.into(),
+                taker_fee: 7.into(),
+                ..Fill::new(
+                    order(maker, OrderSide::Ask),
+                    order(taker, OrderSide::Bid),
+                    100.into(),
+                    5.into(),
+                    500.into(),
+                    Utc::now(),
+                )
+            }
+        };
+        let mut state = OmeState::new();
+
+        /* the trader takes twice on the first market */
+        let market: Address = Address::repeat_byte(1);
+        let mut book = Book::new(market);
+        book.tape.push(fill(market, other, trader));
+        book.tape.push(fill(market, other, trader));
+        state.add_book(book);
+
+        /* and makes once on the second */
+        let market2: Address = Address::repeat_byte(2);
+        let mut book2 = Book::new(market2);
+        book2.tape.push(fill(market2, trader, other));
+        state.add_book(book2);
+
+        /* but never trades on the third */
+        let market3: Address = Address::repeat_byte(3);
+        let mut book3 = Book::new(market3);
+        book3.tape.push(fill(market3, other, other));
+        state.add_book(book3);
+
+        let expected: HashMap<Address, U256> =
+            vec![(market, 14.into()), (market2, 2.into())]
+                .into_iter()
+                .collect();
+        assert_eq!(state.fee_summary(trader), expected);
+        assert!(state.fee_summary(Address::zero()).is_empty());
+    }
 }
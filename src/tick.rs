@@ -0,0 +1,72 @@
+//! Contains logic for expressing prices as signed tick offsets from a
+//! reference price
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+use web3::types::U256;
+
+/// Represents a grid of valid prices spaced `tick_size` apart and centred on
+/// `reference`
+///
+/// A grid only constrains and re-expresses prices. Books still key their
+/// levels by absolute `U256` prices and compare them as such when matching.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TickGrid {
+    pub reference: U256,
+    pub tick_size: U256,
+}
+
+impl TickGrid {
+    /// Constructor for the `TickGrid` type
+    pub fn new(reference: U256, tick_size: U256) -> Self {
+        Self {
+            reference,
+            tick_size,
+        }
+    }
+
+    /// Returns the signed number of ticks between `price` and the reference
+    ///
+    /// Returns `None` if the price is not on the grid or is too far from the
+    /// reference to be represented.
+    pub fn to_ticks(&self, price: U256) -> Option<i64> {
+        if self.tick_size.is_zero() {
+            return None;
+        }
+
+        let (distance, negative): (U256, bool) = if price >= self.reference {
+            (price - self.reference, false)
+        } else {
+            (self.reference - price, true)
+        };
+
+        if !(distance % self.tick_size).is_zero() {
+            return None;
+        }
+
+        let ticks: U256 = distance / self.tick_size;
+        if ticks > U256::from(i64::MAX as u64) {
+            return None;
+        }
+
+        let ticks: i64 = i64::try_from(ticks.as_u64()).ok()?;
+        Some(if negative { -ticks } else { ticks })
+    }
+
+    /// Returns the price lying the provided number of ticks from the
+    /// reference
+    ///
+    /// Returns `None` if the price would be negative or overflow.
+    pub fn to_price(&self, ticks: i64) -> Option<U256> {
+        /* the wrapped absolute value of `i64::MIN` is still correct as a u64 */
+        let distance: U256 = self
+            .tick_size
+            .checked_mul(U256::from(ticks.wrapping_abs() as u64))?;
+
+        if ticks >= 0 {
+            self.reference.checked_add(distance)
+        } else {
+            self.reference.checked_sub(distance)
+        }
+    }
+}
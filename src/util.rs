@@ -1,9 +1,11 @@
+use std::fmt;
 use std::fs;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
+use chrono::Duration;
 use ethereum_types::U256;
-use serde::de::{Error, Unexpected};
+use serde::de::{Error, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer, Serializer};
 
 use crate::state::OmeState;
@@ -33,15 +35,163 @@ pub fn from_hex_de<'de, D>(deserializer: D) -> Result<U256, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s: String = Deserialize::deserialize(deserializer)?;
-    U256::from_dec_str(&s).map_err(|_e| {
-        D::Error::invalid_type(
-            Unexpected::Other("non-decimal string"),
-            &"decimal string",
-        )
+    /* `from_hex_se` writes integers, but decimal strings are accepted too */
+    struct DecimalVisitor;
+
+    impl<'de> Visitor<'de> for DecimalVisitor {
+        type Value = U256;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "an unsigned integer or decimal string")
+        }
+
+        fn visit_u64<E: Error>(self, x: u64) -> Result<U256, E> {
+            Ok(x.into())
+        }
+
+        fn visit_u128<E: Error>(self, x: u128) -> Result<U256, E> {
+            Ok(x.into())
+        }
+
+        fn visit_str<E: Error>(self, s: &str) -> Result<U256, E> {
+            U256::from_dec_str(s).map_err(|_e| {
+                E::invalid_type(
+                    Unexpected::Other("non-decimal string"),
+                    &"decimal string",
+                )
+            })
+        }
+    }
+
+    deserializer.deserialize_any(DecimalVisitor)
+}
+
+/// Converts the provided integer to the nearest double-precision float
+pub fn u256_to_f64(x: U256) -> f64 {
+    /* limbs are stored least significant first */
+    x.0.iter().rev().fold(0.0, |acc, limb| {
+        acc * 18_446_744_073_709_551_616.0 + *limb as f64
     })
 }
 
+/// Returns the integer square root of the provided value, rounded down
+pub fn u256_sqrt(x: U256) -> U256 {
+    if x.is_zero() {
+        return x;
+    }
+
+    /* Newton's method, starting from above the root and descending. The
+     * first step from `x` is `x / 2` rounded up, written so as not to
+     * overflow */
+    let mut root: U256 = x;
+    let mut next: U256 = (x >> 1) + (x & U256::one());
+
+    while next < root {
+        root = next;
+        next = (next + x / next) >> 1;
+    }
+
+    root
+}
+
+/// Advances the provided SplitMix64 generator state, returning its next
+/// pseudorandom output
+///
+/// This is not cryptographically secure, only cheap and reproducible.
+pub fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z: u64 = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Formats a fixed-point integer with the provided number of decimal places
+/// and comma thousands separators
+///
+/// `value` carries `base_decimals` implied decimal places. Digits beyond
+/// `places` are rounded half up (i.e., away from zero).
+pub fn format_fixed(value: U256, base_decimals: u32, places: u32) -> String {
+    let pow10 = |n: u32| U256::from(10).pow(U256::from(n));
+
+    let scaled: U256 = if places >= base_decimals {
+        value.saturating_mul(pow10(places - base_decimals))
+    } else {
+        let divisor: U256 = pow10(base_decimals - places);
+        value.saturating_add(divisor / 2) / divisor
+    };
+
+    let whole: String = (scaled / pow10(places)).to_string();
+    let mut grouped: String = String::new();
+    let offset: usize = whole.len() % 3; /* digits before the first comma */
+
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && i % 3 == offset {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    if places == 0 {
+        return grouped;
+    }
+
+    format!(
+        "{}.{:0>width$}",
+        grouped,
+        (scaled % pow10(places)).to_string(),
+        width = places as usize
+    )
+}
+
+/// Helper to serialise optional durations as a number of milliseconds
+///
+/// This is necessary as `chrono`'s `Duration` type has no serde support of
+/// its own.
+pub fn duration_opt_se<S>(
+    x: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match x {
+        Some(t) => serializer.serialize_some(&t.num_milliseconds()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Helper to deserialise optional durations from a number of milliseconds
+///
+/// This is necessary as `chrono`'s `Duration` type has no serde support of
+/// its own.
+pub fn duration_opt_de<'de, D>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let millis: Option<i64> = Deserialize::deserialize(deserializer)?;
+    Ok(millis.map(Duration::milliseconds))
+}
+
+/// Helper to serialise durations as a number of milliseconds
+pub fn duration_se<S>(x: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_i64(x.num_milliseconds())
+}
+
+/// Helper to deserialise durations from a number of milliseconds
+pub fn duration_de<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let millis: i64 = Deserialize::deserialize(deserializer)?;
+    Ok(Duration::milliseconds(millis))
+}
+
 pub fn is_existing_state(path: &Path) -> bool {
     path.exists()
 }